@@ -266,7 +266,12 @@ pub mod select {
     }
 
     pub fn create_unit(name: &str, unit: &unit::Unit, selected: usize) -> web_sys::Element {
-        let options = unit::Prefix::as_array()
+        create(name, unit_options(unit), selected)
+    }
+
+    /// The options of a select to choose the prefix of a unit.
+    pub fn unit_options(unit: &unit::Unit) -> Vec<(String, String)> {
+        unit::Prefix::as_array()
             .iter()
             .enumerate()
             .map(|(idx, prefix)| {
@@ -275,16 +280,22 @@ pub mod select {
                     format!("{}{}", prefix.to_string(), unit.to_string()),
                 )
             })
-            .collect::<Vec<(String, String)>>();
-        create(name, options, selected)
+            .collect::<Vec<(String, String)>>()
     }
 
+    /// Replace the options of a select. The selected option stays the same if it is still one of
+    /// the new options, otherwise the first option is selected.
     pub fn update_options(name: &str, options: Vec<(String, String)>) {
         let select = dom::select(&format!("[name=\"{}\"]", name));
+        let previous = value::<String>(select.clone()).unwrap_or_default();
+        let selected = options
+            .iter()
+            .position(|(value, _)| value == &previous)
+            .unwrap_or(0);
         select.set_inner_html("");
         dom::append_children(
             &select,
-            create_options(options, 0)
+            create_options(options, selected)
                 .iter()
                 .collect::<Vec<&web_sys::Element>>(),
         );
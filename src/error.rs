@@ -4,33 +4,110 @@ use thiserror::Error;
 pub type Error = Box<dyn std::error::Error>;
 
 pub fn show(error: Error) {
-    let container = dom::select("#error__messages");
-    container.set_inner_html("");
-    dom::append_children(&container, vec![&to_html(&error)]);
-    let _ = dom::select("#error__container").set_attribute("class", "");
+    show_diagnostics(&[Diagnostic::from(error)]);
 }
 
-pub fn show_multiple(errors: Vec<Error>) {
+/// Show a list of diagnostics. The messages of the diagnostics that point at entities of the
+/// schema can be clicked, the index of the diagnostic in the list is kept in `data-diagnostic`.
+pub fn show_diagnostics(diagnostics: &[Diagnostic]) {
     let container = dom::select("#error__messages");
     container.set_inner_html("");
-    errors
+    diagnostics
         .iter()
-        .for_each(|error| dom::append_children(&container, vec![&to_html(error)]));
+        .enumerate()
+        .for_each(|(idx, diagnostic)| {
+            dom::append_children(&container, vec![&to_html(idx, diagnostic)])
+        });
     let _ = dom::select("#error__container").set_attribute("class", "");
 }
 
-fn to_html(error: &Error) -> web_sys::Element {
-    let message = error.to_string();
-    dom::create_element(
-        "div",
-        dom::attributes! {
-            "class" => "error__message",
-            "inner_html" => &message,
-        },
-        vec![],
-    )
+fn to_html(idx: usize, diagnostic: &Diagnostic) -> web_sys::Element {
+    let message = diagnostic.to_string();
+    let class = match diagnostic.severity {
+        Severity::Error => "error__message",
+        Severity::Warning => "error__message error__message--warning",
+    };
+    let idx = idx.to_string();
+    let mut attributes = dom::attributes! {
+        "class" => class,
+        "inner_html" => &message,
+    };
+    if !diagnostic.entities.is_empty() {
+        attributes.insert("data-diagnostic", &idx);
+    }
+    dom::create_element("div", attributes, vec![])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    /// The circuit cannot be simulated.
+    Error,
+    /// The circuit is simulated but the result might not be the one expected.
+    Warning,
+}
+
+/// An entity of the schema. Parts and wires are referenced by their index in the schema, the
+/// diagnostics are discarded as soon as the circuit changes so the indices stay valid.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Entity {
+    Part(usize),
+    Wire(usize),
+    Node(String),
+}
+
+/// An error (or a warning) found in the circuit with the entities that caused it.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub error: Error,
+    pub severity: Severity,
+    pub entities: Vec<Entity>,
 }
 
+impl Diagnostic {
+    pub fn error(error: impl std::error::Error + 'static, entities: Vec<Entity>) -> Self {
+        Self {
+            error: Box::new(error),
+            severity: Severity::Error,
+            entities,
+        }
+    }
+
+    pub fn warning(error: impl std::error::Error + 'static, entities: Vec<Entity>) -> Self {
+        Self {
+            error: Box::new(error),
+            severity: Severity::Warning,
+            entities,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl From<Error> for Diagnostic {
+    /// A diagnostic that was boxed as an error is unboxed, any other error does not point at an
+    /// entity.
+    fn from(error: Error) -> Self {
+        match error.downcast::<Diagnostic>() {
+            Ok(diagnostic) => *diagnostic,
+            Err(error) => Self {
+                error,
+                severity: Severity::Error,
+                entities: Vec::new(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
 #[derive(Error, Debug)]
 pub enum Dom {
     #[error("The queried element `{0}` was not found in the current page.")]
@@ -51,6 +128,51 @@ pub enum Import {
     PartNotFound,
     #[error("The library was not found.")]
     LibNotFound,
+    #[error("The netlist does not contain any supported element.")]
+    EmptyNetlist,
+    #[error("The card `{0}` is not supported, it was skipped.")]
+    UnsupportedCard(String),
+    #[error("The card `{0}` is incomplete or contains an invalid value, it was skipped.")]
+    InvalidCard(String),
+    #[error("Only a part of the card `{0}` was imported.")]
+    PartialCard(String),
+    #[error("The model `{1}` of the card `{0}` is not defined, the card was skipped.")]
+    UnknownModel(String, String),
+}
+
+/// A mistake in the syntax of a SPICE template (see `parts::Spice`).
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum Syntax {
+    #[error("l'accolade `{{` n'est jamais fermée")]
+    Unclosed,
+    #[error("l'accolade `}}` ne ferme aucune accolade")]
+    UnexpectedClose,
+    #[error("la balise `{0}` est inconnue (`prop`, `conn`, `conns` ou `?`)")]
+    UnknownTag(String),
+    #[error("`{0}` est attendu")]
+    Expected(&'static str),
+    #[error("le nom de la propriétée est manquant")]
+    MissingKey,
+    #[error("le numéro de connecteur `{0}` n'est pas valide")]
+    InvalidConnector(String),
+    #[error("le format `{0}` est inconnu (`spice` ou `si`)")]
+    UnknownFormat(String),
+    #[error("la séquence d'échappement `\\{0}` est inconnue")]
+    UnknownEscape(char),
+    #[error("le modèle se termine par `\\`")]
+    TrailingEscape,
+}
+
+#[derive(Error, Debug)]
+pub enum Template {
+    #[error("Le modèle SPICE de `{0}` est invalide à la colonne {1} : {2}.")]
+    Syntax(String, usize, Syntax),
+    #[error("Le modèle SPICE de `{0}` utilise la propriétée `{2}` (colonne {1}) mais le composant ne l'a pas.")]
+    MissingProperty(String, usize, String),
+    #[error("Le modèle SPICE de `{0}` utilise le connecteur {2} (colonne {1}) mais le composant ne l'a pas.")]
+    MissingConnector(String, usize, usize),
+    #[error("Le modèle SPICE de `{0}` demande la valeur numérique de `{2}` (colonne {1}) mais ce n'est pas un nombre.")]
+    NotANumber(String, usize, String),
 }
 
 #[derive(Error, Debug)]
@@ -65,16 +187,22 @@ pub enum Internal {
 
 #[derive(Error, Debug)]
 pub enum Sim {
-    #[error("Un composant doit avoir une propriétée nommée `{1}` mais la propriétée n'a pas été trouvée")]
-    PropertyNotFound(usize, String),
+    #[error("Un composant doit avoir une propriétée nommée `{0}` mais la propriétée n'a pas été trouvée")]
+    PropertyNotFound(String),
     #[error("Le nom d'un composant doit être au moins de 1 caractère")]
     EmptyName,
     #[error("Un des connecteurs d'un composant n'est pas connecté.")]
-    MissingConnectionPart(usize),
+    MissingConnectionPart,
     #[error("Un des noeuds est en circuit ouvert. (`{0}`)")]
     MissingConnectionNode(String),
-    #[error("Le même node est nommé plusieurs fois.")]
-    MultipleNameOnNode(usize),
+    #[error("Un fil n'est relié à aucun composant, il est ignoré.")]
+    UnconnectedWire,
+    #[error(
+        "Des noeuds séparés portent le même nom `{0}` et sont reliés, vérifier que c'est voulu."
+    )]
+    MergedNodes(String),
+    #[error("Le même node est nommé plusieurs fois. (`{0}`)")]
+    MultipleNameOnNode(String),
     #[error("Le circuit comporte aucun ground.")]
     NoGround,
     #[error("Aucune probe est présent dans le circuit.")]
@@ -87,4 +215,84 @@ pub enum Sim {
     SpiceNoData,
     #[error("L'analyse sélectionné n'existe pas, veuillez en sélectionner une autre")]
     UnavailableAnalysis,
+    #[error("Aucune source n'est sélectionnée pour l'analyse DC.")]
+    NoSweptSource,
+    #[error("Le point d'opération ne peut pas être calculé lorsqu'une propriétée prend plusieurs valeurs.")]
+    SweepWithOperatingPoint,
+    #[error("Les propriétées balayées, les températures et les tirages Monte Carlo donnent {0} simulations, le maximum est de {1}.")]
+    TooManyRuns(usize, usize),
+    #[error("Aucun voltmètre n'est sélectionné comme sortie de l'analyse de bruit.")]
+    NoNoiseOutput,
+    #[error("Aucune source d'entrée n'est sélectionnée pour l'analyse de bruit.")]
+    NoNoiseSource,
+    #[error("La fréquence fondamentale de l'analyse de Fourier doit être plus grande que 0.")]
+    InvalidFundamental,
+    #[error("La simulation doit durer au moins une période de la fréquence fondamentale pour l'analyse de Fourier.")]
+    FourierTooShort,
+    #[error("La probe `{0}` n'existe pas dans le circuit.")]
+    UnknownProbe(String),
+    #[error("Aucune sortie n'est sélectionnée pour l'analyse.")]
+    NoAnalysisOutput,
+    #[error("Aucune source d'entrée n'est sélectionnée pour l'analyse.")]
+    NoAnalysisSource,
+    #[error("Le nombre de simulations Monte Carlo doit être un entier positif.")]
+    InvalidMonteCarloRuns,
+    #[error("Aucune simulation Monte Carlo n'a produit de mesure pour la probe `{0}`.")]
+    NoMonteCarloMeasurement(String),
+    #[error("La température `{0}` n'est pas valide (ex. : `27`, `-40,27,85` ou `-40:85:25`).")]
+    InvalidTemperature(String),
+    #[error("Le paramètre de simulation `{0}` est manquant.")]
+    SettingNotFound(String),
+    #[error("Une partie du circuit n'est pas reliée à un ground (noeuds : {0}).")]
+    IslandWithoutGround(String),
+    #[error("Le noeud `{0}` n'a aucun chemin DC vers le ground (ex. : relié seulement par des condensateurs ou des sources de courant).")]
+    NoDcPathToGround(String),
+    #[error("Une boucle est formée seulement de sources de tension et d'inductances : {0}.")]
+    VoltageSourceLoop(String),
+    #[error("Les noeuds `{1}` sont alimentés seulement par des sources de courant et des condensateurs : {0}.")]
+    CurrentSourceCutset(String, String),
+    #[error("Le modèle `{0}` n'existe pas dans les librairies, il n'est pas ajouté à la netlist.")]
+    MissingModel(String),
+    #[error("Un sous-circuit doit contenir au moins un noeud nommé, chaque noeud nommé devient une broche du bloc.")]
+    SubcircuitWithoutPort,
+    #[error("Le nom `{0}` ne peut pas être donné à un sous-circuit (lettres, chiffres et `_` seulement, sans reprendre un nom existant).")]
+    InvalidSubcircuitName(String),
+    #[error("Le sous-circuit `{0}` n'existe pas.")]
+    SubcircuitNotFound(String),
+    #[error("Le sous-circuit `{0}` se contient lui-même.")]
+    RecursiveSubcircuit(String),
+    #[error("Le sous-circuit `{0}` est invalide : {1}")]
+    InvalidSubcircuit(String, String),
+    #[error("Le simulateur intégré ne supporte pas le composant `{0}`, utiliser ngspice.")]
+    NativeUnsupportedDevice(String),
+    #[error("Le simulateur intégré ne supporte pas l'analyse `{0}`, utiliser ngspice.")]
+    NativeUnsupportedAnalysis(String),
+    #[error("La ligne `{0}` de la netlist n'est pas comprise par le simulateur intégré.")]
+    NativeInvalidCard(String),
+    #[error("La netlist ne contient aucune analyse.")]
+    NativeNoAnalysis,
+    #[error("La probe `{0}` ne mesure aucun noeud ni aucune source du circuit.")]
+    NativeUnknownProbe(String),
+    #[error("La source balayée `{0}` n'existe pas dans le circuit.")]
+    NativeUnknownSource(String),
+    #[error("Le composant `{0}` est contrôlé par le courant de `{1}` qui n'est pas une source de tension du circuit.")]
+    NativeInvalidControl(String, String),
+    #[error("Les équations du circuit n'ont pas de solution unique (matrice singulière).")]
+    SingularMatrix,
+    #[error("Le pas et la durée de l'analyse transitoire doivent être plus grands que 0.")]
+    NativeInvalidStep,
+    #[error("Le pas de l'analyse transitoire est devenu trop petit à t = {0} s, la solution varie trop vite.")]
+    NativeTimestepTooSmall(f64),
+    #[error("Le modèle `{0}` n'est défini par aucune ligne `.model` de la netlist.")]
+    NativeUnknownModel(String),
+    #[error("Le modèle `{0}` n'est pas du type attendu par le composant (`d`, `npn` ou `pnp`).")]
+    NativeInvalidModel(String),
+    #[error("La méthode de Newton-Raphson ne converge pas, même en augmentant progressivement gmin et les sources.")]
+    NativeNoConvergence,
+    #[error("La simulation {0} a été annulée.")]
+    BackendCancelled(u64),
+    #[error("Le simulateur n'a pas pu être joint : {0}.")]
+    BackendTransport(String),
+    #[error("La simulation a été arrêtée après {0} s, le délai maximal est dépassé.")]
+    BackendTimeout(f64),
 }
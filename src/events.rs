@@ -6,6 +6,9 @@ use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
+/// The interval between two updates of the running job in milliseconds.
+const JOBS_INTERVAL: i32 = 250;
+
 pub enum Event {
     MouseMove,
     MouseDown,
@@ -15,9 +18,11 @@ pub enum Event {
     KeyDown,
     Click,
     Change,
+    Focus,
     Resize,
     NgspiceResponse,
     NgspiceRequest,
+    NgspiceProgress,
 }
 
 impl fmt::Display for Event {
@@ -31,9 +36,11 @@ impl fmt::Display for Event {
             Self::KeyDown => "keydown",
             Self::Click => "click",
             Self::Change => "change",
+            Self::Focus => "focus",
             Self::Resize => "resize",
             Self::NgspiceRequest => "ngspice_request",
             Self::NgspiceResponse => "ngspice_response",
+            Self::NgspiceProgress => "ngspice_progress",
         };
         write!(f, "{}", out)
     }
@@ -51,9 +58,11 @@ impl std::str::FromStr for Event {
             "keydown" => Ok(Self::KeyDown),
             "click" => Ok(Self::Click),
             "change" => Ok(Self::Change),
+            "focus" => Ok(Self::Focus),
             "resize" => Ok(Self::Resize),
             "ngspice_request" => Ok(Self::NgspiceRequest),
             "ngspice_response" => Ok(Self::NgspiceResponse),
+            "ngspice_progress" => Ok(Self::NgspiceProgress),
             _ => Err(Box::new(error::Internal::Event)),
         }
     }
@@ -202,18 +211,20 @@ pub fn add_events_schema(schema: Rc<RefCell<schema::Schema>>, plots: Rc<RefCell<
         }),
     );
     let p = plots.clone();
-    let s = schema.clone();
-    EventListener::add(
-        &web_sys::window().unwrap(),
-        &Event::NgspiceResponse,
-        Box::new(move |event: web_sys::CustomEvent| {
-            let (x_label, y_labels, series) = plot::parser::parse_spice_output(
-                &s.borrow_mut().probes,
-                &event.detail().as_string().unwrap(),
-            );
-            p.borrow_mut().update_data(series, x_label, y_labels);
-        }),
-    );
+    let s = Rc::downgrade(&schema);
+    schema
+        .borrow_mut()
+        .set_responder(Rc::new(move |id, output| {
+            let schema = match s.upgrade() {
+                Some(schema) => schema,
+                None => return,
+            };
+            let response = schema.borrow_mut().simulation_response(id, output);
+            if let Some((layout, (x_label, y_labels, series))) = response {
+                p.borrow_mut().set_layout(layout);
+                p.borrow_mut().update_data(series, x_label, y_labels);
+            }
+        }));
 
     let p = plots.clone();
     EventListener::add(
@@ -257,6 +268,56 @@ pub fn add_events_schema(schema: Rc<RefCell<schema::Schema>>, plots: Rc<RefCell<
         }),
     );
 
+    // The messages are created each time diagnostics are shown, the clicks are handled by their
+    // container.
+    let s = schema.clone();
+    EventListener::add(
+        &dom::select("#error__messages"),
+        &Event::Click,
+        Box::new(move |event: web_sys::MouseEvent| {
+            let idx = event
+                .target()
+                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                .and_then(|element| element.closest("[data-diagnostic]").ok().flatten())
+                .and_then(|message| message.get_attribute("data-diagnostic"))
+                .and_then(|idx| idx.parse::<usize>().ok());
+            if let Some(idx) = idx {
+                s.borrow_mut().diagnostic_dispatch(idx);
+            }
+        }),
+    );
+
+    // The buttons of the blocks are replaced each time the blocks change, the clicks are caught
+    // by the list of components.
+    let s = schema.clone();
+    EventListener::add(
+        &dom::select("#menu__component-list"),
+        &Event::Click,
+        Box::new(move |event: web_sys::MouseEvent| {
+            let name = event
+                .target()
+                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                .and_then(|element| element.closest("[data-subcircuit]").ok().flatten())
+                .and_then(|button| button.get_attribute("data-subcircuit"));
+            if let Some(name) = name {
+                s.borrow_mut().add_subcircuit(&name);
+            }
+        }),
+    );
+
+    dom::select_all("[data-sources]")
+        .into_iter()
+        .for_each(|element| {
+            for event in [Event::Focus, Event::Change] {
+                let s = schema.clone();
+                EventListener::add(
+                    &element,
+                    &event,
+                    Box::new(move |_: web_sys::Event| s.borrow().sources_dispatch()),
+                );
+            }
+        });
+
     let s = schema.clone();
     EventListener::add(
         &dom::select("[name=\"sim__type\"]"),
@@ -265,6 +326,39 @@ pub fn add_events_schema(schema: Rc<RefCell<schema::Schema>>, plots: Rc<RefCell<
             s.borrow_mut().simulation_selector_dispatch();
         }),
     );
+
+    // The cancel button is replaced each time the jobs are shown, the clicks are handled by
+    // their container.
+    let s = schema.clone();
+    EventListener::add(
+        &dom::select("#sim__jobs"),
+        &Event::Click,
+        Box::new(move |event: web_sys::MouseEvent| {
+            let is_cancel = event
+                .target()
+                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                .and_then(|element| element.closest("#sim__cancel").ok().flatten())
+                .is_some();
+            if is_cancel {
+                s.borrow_mut().cancel_simulation();
+            }
+        }),
+    );
+
+    // The running job is followed periodically: its elapsed time, its progress and its timeout.
+    let s = Rc::downgrade(&schema);
+    let tick = Closure::wrap(Box::new(move || {
+        if let Some(schema) = s.upgrade() {
+            schema.borrow_mut().tick_jobs();
+        }
+    }) as Box<dyn FnMut()>);
+    let _ = web_sys::window()
+        .unwrap()
+        .set_interval_with_callback_and_timeout_and_arguments_0(
+            tick.as_ref().unchecked_ref(),
+            JOBS_INTERVAL,
+        );
+    tick.forget();
 }
 
 // This will add all the event necessary for the editor to work. There is probably a better way to
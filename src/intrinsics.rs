@@ -152,5 +152,106 @@ impl fmt::Debug for Size {
     }
 }
 
+/// A complex number. The results of an AC analysis are complex, the real and the imaginary parts
+/// are returned by ngspice and are converted to a magnitude and a phase to be plotted.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// The magnitude in decibels (`20 * log10(|z|)`).
+    pub fn db(&self) -> f64 {
+        20.0 * self.norm().log10()
+    }
+
+    /// The phase in degrees, between -180 and 180.
+    pub fn phase(&self) -> f64 {
+        self.im.atan2(self.re).to_degrees()
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl std::ops::Div for Complex {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let denominator = other.re * other.re + other.im * other.im;
+        Self::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator,
+        )
+    }
+}
+
+impl std::ops::Neg for Complex {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.im)
+    }
+}
+
+impl fmt::Debug for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(re:{},im:{})", self.re, self.im)
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub struct Color(pub &'static str);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complex_magnitude_and_phase() {
+        let value = Complex::new(3.0, 4.0);
+        assert_eq!(value.norm(), 5.0);
+        assert!((Complex::new(10.0, 0.0).db() - 20.0).abs() < 1e-12);
+        assert!((Complex::new(0.5, -0.5).db() + 10.0 * 2f64.log10()).abs() < 1e-12);
+        assert!((Complex::new(1.0, 1.0).phase() - 45.0).abs() < 1e-12);
+        assert!((Complex::new(0.0, -2.0).phase() + 90.0).abs() < 1e-12);
+        assert!((Complex::new(-1.0, 0.0).phase() - 180.0).abs() < 1e-12);
+        let product = Complex::new(1.0, 2.0) * Complex::new(3.0, -1.0);
+        assert!(product == Complex::new(5.0, 5.0));
+        let quotient = product / Complex::new(3.0, -1.0);
+        assert!((quotient - Complex::new(1.0, 2.0)).norm() < 1e-12);
+    }
+}
@@ -49,9 +49,19 @@ pub fn set_up() {
     // This is for better error message in the console on the web.
     panic::set_hook(Box::new(console_error_panic_hook::hook));
 
+    // A part with an invalid SPICE pattern cannot be simulated, this is reported right away.
+    if let Err(error) = PARTS.validate() {
+        error::show(error);
+    }
     views::parts::load();
     view::generate_toolbar();
     view::generate_simulation();
+    view::generate_dc_simulation();
+    view::generate_noise_simulation();
+    view::generate_tf_simulation();
+    view::generate_pz_simulation();
+    view::generate_temperature();
+    view::generate_montecarlo();
     let schema = Rc::new(RefCell::new(schema::Schema::new().unwrap()));
     let plots = Rc::new(RefCell::new(plot::Plots::new()));
     schema.borrow_mut().update();
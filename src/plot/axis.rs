@@ -1,16 +1,32 @@
 use crate::intrinsics::*;
-use crate::plot;
+use crate::{plot, unit};
 use wasm_bindgen::JsValue;
 
 /// Represent an axis of a plot
 pub struct Axis {
     tick_count: usize,
     pub labels: Vec<String>,
+    /// The values of a logarithmic axis are the exponents (`log10(x)`) of the real values.
+    pub is_logarithmic: bool,
 }
 
 impl Axis {
     pub fn new(tick_count: usize, labels: Vec<String>) -> Self {
-        Self { tick_count, labels }
+        Self {
+            tick_count,
+            labels,
+            is_logarithmic: false,
+        }
+    }
+
+    /// The label of a tick (or a value) of this axis.
+    pub fn format(&self, value: f64) -> String {
+        if self.is_logarithmic {
+            let (value, prefix) = unit::Prefix::engineering(10f64.powf(value));
+            format!("{:.2}{}", value, prefix)
+        } else {
+            format!("{:.2}", value)
+        }
     }
 
     pub fn draw_horizontal_grid(
@@ -32,13 +48,13 @@ impl Axis {
             let x = offset.x + tick as f64 * spacing;
             self.draw_line(x, offset.y, x, y, "#323232", context);
             self.draw_line(x, y, x, y + 5.0, "#CCC", context);
-            let tick_label = format!(
-                "{:.2}",
-                ((max - min) * (tick as f64 / (self.tick_count - 1) as f64)) + min
-            );
+            let tick_label =
+                self.format(((max - min) * (tick as f64 / (self.tick_count - 1) as f64)) + min);
             context.fill_text(&tick_label, x, y + 17.0).unwrap();
         }
-        self.draw_line(zero_pos, offset.y, zero_pos, y, "#555", context);
+        if !self.is_logarithmic {
+            self.draw_line(zero_pos, offset.y, zero_pos, y, "#555", context);
+        }
     }
 
     pub fn draw_horizontal(
@@ -82,12 +98,13 @@ impl Axis {
             let y = size.h + offset.y - tick as f64 * spacing;
             self.draw_line(x, y, size.w + offset.x, y, "#323232", context);
             self.draw_line(x, y, x - 5.0, y, "#CCC", context);
-            let tick_label = ((max - min) * (tick as f64 / (self.tick_count - 1) as f64)) + min;
-            context
-                .fill_text(&format!("{:.2}", tick_label), x - 10.0, y + 5.0)
-                .unwrap();
+            let tick_label =
+                self.format(((max - min) * (tick as f64 / (self.tick_count - 1) as f64)) + min);
+            context.fill_text(&tick_label, x - 10.0, y + 5.0).unwrap();
+        }
+        if !self.is_logarithmic {
+            self.draw_line(x, zero_pos, size.w + offset.x, zero_pos, "#555", context);
         }
-        self.draw_line(x, zero_pos, size.w + offset.x, zero_pos, "#555", context);
     }
 
     pub fn draw_vertical(
@@ -0,0 +1,106 @@
+use super::{Table, COLORS};
+use crate::dom;
+
+const MIN_BINS: usize = 5;
+const MAX_BINS: usize = 30;
+const MARGIN: f64 = 20.0;
+
+/// The distribution of the values measured by a Monte Carlo analysis.
+pub struct Histogram {
+    pub title: String,
+    /// The number of values in each bin, the bins all have the same width.
+    pub bins: Vec<usize>,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub count: usize,
+}
+
+impl Histogram {
+    /// Group the values into bins. The number of bins is the square root of the number of values.
+    /// Returns `None` if there are no values.
+    pub fn compute(title: String, values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let count = values.len();
+        let min = values.iter().cloned().fold(f64::MAX, f64::min);
+        let max = values.iter().cloned().fold(f64::MIN, f64::max);
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / (count.max(2) - 1) as f64;
+        let bin_count = ((count as f64).sqrt().ceil() as usize).clamp(MIN_BINS, MAX_BINS);
+        let mut bins = vec![0; bin_count];
+        let width = (max - min) / bin_count as f64;
+        values.iter().for_each(|value| {
+            let idx = if width > 0.0 {
+                (((value - min) / width) as usize).min(bin_count - 1)
+            } else {
+                bin_count / 2
+            };
+            bins[idx] += 1;
+        });
+        Some(Self {
+            title,
+            bins,
+            min,
+            max,
+            mean,
+            std_dev: variance.sqrt(),
+            count,
+        })
+    }
+
+    /// The statistics of the values.
+    pub fn table(&self) -> Table {
+        let row = |name: &str, value: String| vec![name.to_string(), value];
+        Table {
+            title: self.title.clone(),
+            headers: vec![String::new(), String::from("Valeur")],
+            rows: vec![
+                row("Simulations", self.count.to_string()),
+                row("Moyenne", format!("{:.4e}", self.mean)),
+                row("Écart type", format!("{:.4e}", self.std_dev)),
+                row("Minimum", format!("{:.4e}", self.min)),
+                row("Maximum", format!("{:.4e}", self.max)),
+            ],
+        }
+    }
+
+    /// Draw the bins as bars with the extremums of the values under the bars.
+    pub fn draw(&self, canvas: &web_sys::HtmlCanvasElement) {
+        let context = dom::canvas::context(canvas);
+        let (width, height) = (canvas.width() as f64, canvas.height() as f64);
+        let tallest = self.bins.iter().cloned().max().unwrap_or(1).max(1) as f64;
+        let bar_width = (width - 2.0 * MARGIN) / self.bins.len() as f64;
+        let bar_height = height - 2.0 * MARGIN;
+        context.set_fill_style_str(COLORS[0]);
+        self.bins.iter().enumerate().for_each(|(idx, count)| {
+            let h = *count as f64 / tallest * bar_height;
+            context.fill_rect(
+                MARGIN + idx as f64 * bar_width + 1.0,
+                MARGIN + bar_height - h,
+                bar_width - 2.0,
+                h,
+            );
+        });
+        context.set_stroke_style_str("#B1B1B1");
+        context.begin_path();
+        context.move_to(MARGIN, MARGIN + bar_height);
+        context.line_to(width - MARGIN, MARGIN + bar_height);
+        context.stroke();
+        context.set_fill_style_str("#B1B1B1");
+        context.set_font("10px sans-serif");
+        let bottom = height - MARGIN / 3.0;
+        context.set_text_align("left");
+        let _ = context.fill_text(&format!("{:.3e}", self.min), MARGIN, bottom);
+        context.set_text_align("right");
+        let _ = context.fill_text(&format!("{:.3e}", self.max), width - MARGIN, bottom);
+        context.set_text_align("center");
+        let _ = context.fill_text(&format!("max : {}", tallest), width / 2.0, MARGIN / 1.5);
+    }
+}
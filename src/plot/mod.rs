@@ -1,4 +1,5 @@
 mod axis;
+pub mod histogram;
 pub mod parser;
 pub mod test;
 use crate::intrinsics::*;
@@ -6,11 +7,38 @@ use crate::{clog, dom};
 use wasm_bindgen::{JsCast, JsValue};
 
 const AXIS_WIDTH: f64 = 45.0;
+/// The space between the magnitude and the phase of a bode plot.
+const BODE_GAP: f64 = 45.0;
 pub const CANVAS_ID: &'static str = "#simulation__canvas";
 pub const COLORS: [&'static str; 8] = [
     "#1abc9c", "#e74c3c", "#3498db", "#9b59b6", "#2ecc71", "#f1c40f", "#e67e22", "#95a5a6",
 ];
 
+/// The result of a simulation: the label of the x axis, the label of each series and the series.
+pub type Data = (String, Vec<String>, Vec<Vec<Point>>);
+
+/// A table of results shown next to the plot (ie: the harmonics of a fourier analysis).
+pub struct Table {
+    pub title: String,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// How the series are shown.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// Every series on the same plot with linear axes.
+    Linear,
+    /// The series come in pairs (magnitude then phase). The magnitudes are shown on the top plot
+    /// and the phases on the bottom one. Both plots share a logarithmic frequency axis.
+    Bode,
+    /// Every series on the same plot with logarithmic axes (ie: noise spectral densities).
+    LogLog,
+    /// The series come in pairs (poles then zeros) and are drawn as markers on the complex plane.
+    /// The origin is always shown.
+    ComplexPlane,
+}
+
 /// Contains all the plots created by the user including which one is currently selected. `Plots`
 /// will manage the selected plot, show the correct settings when switching to an other plot and
 /// redrawing the correct plot to the canvas when switching plot.
@@ -24,6 +52,7 @@ pub struct Plots {
     x_axis: axis::Axis,
     y_axis: axis::Axis,
     plot_count: usize,
+    layout: Layout,
 }
 
 impl Plots {
@@ -41,6 +70,7 @@ impl Plots {
             x_axis: axis::Axis::new(5, Vec::new()),
             y_axis: axis::Axis::new(5, Vec::new()),
             plot_count: 0,
+            layout: Layout::Linear,
         };
         plots.resize();
         //plots.add_plot();
@@ -143,7 +173,32 @@ impl Plots {
         }
     }
 
+    /// Change how the next data will be shown. The layout must be set before updating the data.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+        self.x_axis.is_logarithmic = layout == Layout::Bode || layout == Layout::LogLog;
+        self.y_axis.is_logarithmic = layout == Layout::LogLog;
+    }
+
     pub fn update_data(&mut self, series: Vec<Vec<Point>>, x_label: String, y_labels: Vec<String>) {
+        // The points are stored as exponents on a logarithmic axis, values that can not be shown
+        // on a logarithmic axis are removed.
+        let (log_x, log_y) = (self.x_axis.is_logarithmic, self.y_axis.is_logarithmic);
+        let series = series
+            .into_iter()
+            .map(|points| {
+                points
+                    .into_iter()
+                    .filter(|point| (!log_x || point.x > 0.0) && (!log_y || point.y > 0.0))
+                    .map(|point| {
+                        Point::new(
+                            if log_x { point.x.log10() } else { point.x },
+                            if log_y { point.y.log10() } else { point.y },
+                        )
+                    })
+                    .collect::<Vec<Point>>()
+            })
+            .collect::<Vec<Vec<Point>>>();
         let mut series_map = Vec::new();
         'outer: for label in self.y_axis.labels.iter() {
             for (idx, new_label) in y_labels.iter().enumerate() {
@@ -218,32 +273,90 @@ impl Plots {
             let plot = &mut self.plots[idx];
             let size = self.size - Size::new(plot.offset.x, 0.0);
             plot.find_extrema(&self.series);
-            plot.find_scale(size);
-            self.x_axis.draw_horizontal_grid(
-                &context,
-                plot.min.x,
-                plot.max.x,
-                plot.offset + self.offset,
-                size,
-            );
-            self.y_axis.draw_vertical_grid(
-                &context,
-                plot.min.y,
-                plot.max.y,
-                plot.offset + self.offset,
-                size,
-            );
-            plot.draw(&self.series, &context, &self.mouse, &size, self.offset);
-            self.x_axis
-                .draw_horizontal(&context, plot.offset + self.offset, size);
-            self.y_axis.draw_vertical(
-                &context,
-                plot.offset + self.offset,
-                size,
-                &plot.visible_series,
-            );
+            plot.has_markers = self.layout == Layout::ComplexPlane;
+            match self.layout {
+                Layout::Linear | Layout::LogLog => {
+                    plot.find_scale(size);
+                    self.draw_plot(&self.plots[idx], &context, self.offset, size);
+                }
+                Layout::ComplexPlane => {
+                    // The imaginary axis is symmetric because the poles and the zeros are always
+                    // real or complex conjugates. A margin keeps the markers inside the plot.
+                    let limit = plot.max.y.abs().max(plot.min.y.abs());
+                    let (min_x, max_x) = (plot.min.x.min(0.0), plot.max.x.max(0.0));
+                    let span = (max_x - min_x).max(2.0 * limit);
+                    let margin = if span > 0.0 { span * 0.1 } else { 1.0 };
+                    plot.min = Point::new(min_x - margin, -limit - margin);
+                    plot.max = Point::new(max_x + margin, limit + margin);
+                    plot.find_scale(size);
+                    self.draw_plot(&self.plots[idx], &context, self.offset, size);
+                }
+                Layout::Bode => {
+                    // The magnitudes and the phases have their own y axis but they share the
+                    // same x axis.
+                    let (min_x, max_x) = (plot.min.x, plot.max.x);
+                    let size = Size::new(size.w, (size.h - BODE_GAP) / 2.0);
+                    for row in 0..2 {
+                        let visible_series = self.plots[idx]
+                            .visible_series
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, is_visible)| *is_visible && idx % 2 == row)
+                            .collect::<Vec<bool>>();
+                        if !visible_series.contains(&true) {
+                            continue;
+                        }
+                        let mut subplot = Plot2 {
+                            visible_series,
+                            min: Point::default(),
+                            max: Point::default(),
+                            scale: Point::new(1.0, 1.0),
+                            offset: self.plots[idx].offset,
+                            has_markers: false,
+                        };
+                        subplot.find_extrema(&self.series);
+                        subplot.min.x = min_x;
+                        subplot.max.x = max_x;
+                        subplot.find_scale(size);
+                        let offset =
+                            self.offset + Point::new(0.0, row as f64 * (size.h + BODE_GAP));
+                        self.draw_plot(&subplot, &context, offset, size);
+                    }
+                }
+            }
         }
     }
+
+    /// Draw the grid, the series and the axes of a plot.
+    fn draw_plot(
+        &self,
+        plot: &Plot2,
+        context: &web_sys::CanvasRenderingContext2d,
+        offset: Point,
+        size: Size,
+    ) {
+        self.x_axis.draw_horizontal_grid(
+            context,
+            plot.min.x,
+            plot.max.x,
+            plot.offset + offset,
+            size,
+        );
+        self.y_axis
+            .draw_vertical_grid(context, plot.min.y, plot.max.y, plot.offset + offset, size);
+        plot.draw(
+            &self.series,
+            context,
+            &self.mouse,
+            &size,
+            offset,
+            (&self.x_axis, &self.y_axis),
+        );
+        self.x_axis
+            .draw_horizontal(context, plot.offset + offset, size);
+        self.y_axis
+            .draw_vertical(context, plot.offset + offset, size, &plot.visible_series);
+    }
 }
 
 pub struct Plot2 {
@@ -252,6 +365,8 @@ pub struct Plot2 {
     max: Point,
     scale: Point,
     offset: Point,
+    /// The points are drawn as markers instead of lines (ie: the poles and the zeros).
+    has_markers: bool,
 }
 
 impl Plot2 {
@@ -263,6 +378,7 @@ impl Plot2 {
             max: Point::new(0.0, 0.0),
             scale: Point::new(1.0, 1.0),
             offset: Point::new(10.0 * series_count as f64, 0.0),
+            has_markers: false,
         };
         plot.find_extrema(series);
         plot.find_scale(*size);
@@ -325,6 +441,20 @@ impl Plot2 {
         self.offset = Point::new(10.0 * series_count as f64, 0.0);
     }
 
+    /// Draw a cross (a pole) or a circle (a zero) centered on a point.
+    fn draw_marker(context: &web_sys::CanvasRenderingContext2d, x: f64, y: f64, is_cross: bool) {
+        context.begin_path();
+        if is_cross {
+            context.move_to(x - 4.0, y - 4.0);
+            context.line_to(x + 4.0, y + 4.0);
+            context.move_to(x + 4.0, y - 4.0);
+            context.line_to(x - 4.0, y + 4.0);
+        } else {
+            let _ = context.arc(x, y, 4.0, 0.0, std::f64::consts::PI * 2.0);
+        }
+        context.stroke();
+    }
+
     /// Draw a series of point to the graph. When drawing the graph, we have to remember that there
     /// is a margin on each side so that no text or line is cut off.
     pub fn draw(
@@ -334,6 +464,7 @@ impl Plot2 {
         mouse: &Option<Point>,
         size: &Size,
         offset: Point,
+        (x_axis, y_axis): (&axis::Axis, &axis::Axis),
     ) {
         let off = self.offset + offset;
         let mut selected_points = Vec::new();
@@ -361,10 +492,16 @@ impl Plot2 {
                             closest = Some((Point::new(x, y), *point));
                         }
                     }
-                    context.line_to(x, y);
+                    if self.has_markers {
+                        Self::draw_marker(context, x, y, idx % 2 == 0);
+                    } else {
+                        context.line_to(x, y);
+                    }
                 }
                 selected_points.push(closest);
-                context.stroke();
+                if !self.has_markers {
+                    context.stroke();
+                }
             }
         }
         context.set_text_align("right");
@@ -383,7 +520,7 @@ impl Plot2 {
                     context.fill();
                     context
                         .fill_text(
-                            &format!("({:.2}, {:.2})", point.x, point.y),
+                            &format!("({}, {})", x_axis.format(point.x), y_axis.format(point.y)),
                             size.w + off.x - 3.0,
                             off.y + (idx as f64 * 15.0),
                         )
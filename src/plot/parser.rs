@@ -1,5 +1,5 @@
 use crate::intrinsics::*;
-use crate::{error, sim};
+use crate::{error, sim, unit};
 
 /// This parses the spice output and return the labels for each series and the points to plot the
 /// graphs. (x_label, y_labels, series)
@@ -41,3 +41,325 @@ pub fn parse_spice_output(
     }
     (x_label, y_labels, series)
 }
+
+/// A nested sweep is returned by ngspice as a single table where the inner sweep restarts for
+/// each value of the outer sweep. This splits every series in one curve per value of the outer
+/// sweep (each curve has the same number of points), the label of each curve contains the value
+/// of the outer sweep.
+pub fn split_family(
+    y_labels: Vec<String>,
+    series: Vec<Vec<Point>>,
+    device: &str,
+    values: &[f64],
+) -> (Vec<String>, Vec<Vec<Point>>) {
+    let unit = if device.to_lowercase().starts_with('i') {
+        unit::Unit::Ampere
+    } else {
+        unit::Unit::Volt
+    };
+    let mut labels = Vec::new();
+    let mut curves = Vec::new();
+    y_labels.iter().zip(series).for_each(|(label, points)| {
+        let size = (points.len() / values.len().max(1)).max(1);
+        let runs = points.chunks(size).map(|run| run.to_vec());
+        runs.into_iter().enumerate().for_each(|(idx, run)| {
+            let value = match values.get(idx) {
+                Some(value) => unit::format(*value, &unit),
+                None => idx.to_string(),
+            };
+            labels.push(format!("{} ({}={})", label, device, value));
+            curves.push(run);
+        });
+    });
+    (labels, curves)
+}
+
+/// The (x, y) values of each column of a table printed by ngspice.
+type Columns = Vec<Vec<(f64, f64)>>;
+
+/// Parse every column of the tables printed by ngspice. When there are too many columns, ngspice
+/// prints multiple tables one after the other (each with its own header), the columns of every
+/// table are appended. A long table is also split in pages that repeat the same header, the rows
+/// of those pages are appended to the columns of the table instead of adding new columns. Returns
+/// the label of the x axis and the columns (without the x axis).
+fn parse_columns(output: &str) -> Option<(String, Columns)> {
+    let mut x_label = None;
+    let mut columns: Columns = Vec::new();
+    let mut tables: Vec<(Vec<&str>, usize)> = Vec::new();
+    let mut table_offset = 0;
+    output.lines().for_each(|line| {
+        let cols = line.split_whitespace().collect::<Vec<&str>>();
+        if cols.len() > 1 && cols[0] == "Index" {
+            x_label = Some(cols[1].to_string());
+            let names = cols[2..].to_vec();
+            table_offset = match tables.iter().find(|(header, _)| *header == names) {
+                Some((_, offset)) => *offset,
+                None => {
+                    let offset = columns.len();
+                    columns.extend(names.iter().map(|_| Vec::new()));
+                    tables.push((names, offset));
+                    offset
+                }
+            };
+        } else if x_label.is_some() && cols.len() > 1 && cols[0].parse::<usize>().is_ok() {
+            if let Ok(x) = cols[1].parse::<f64>() {
+                for (idx, col) in cols.iter().enumerate().skip(2) {
+                    if let (Some(column), Ok(y)) =
+                        (columns.get_mut(table_offset + idx - 2), col.parse::<f64>())
+                    {
+                        column.push((x, y));
+                    }
+                }
+            }
+        }
+    });
+    x_label.map(|x_label| (x_label, columns))
+}
+
+/// Parse the output of an AC analysis. Every probe is printed as a real and an imaginary column,
+/// they are converted to 2 series: the magnitude (in dB) and the phase (in degrees). The series of
+/// a probe are always next to each other (magnitude then phase).
+pub fn parse_ac_output(
+    probes: &sim::Probes,
+    output: &str,
+) -> (String, Vec<String>, Vec<Vec<Point>>) {
+    let mut y_labels = Vec::new();
+    let mut series = Vec::new();
+    let (x_label, columns) = match parse_columns(output) {
+        Some(table) => table,
+        None => {
+            error::show(Box::new(error::Sim::SpiceNoData));
+            return (String::new(), y_labels, series);
+        }
+    };
+    columns.chunks(2).enumerate().for_each(|(idx, parts)| {
+        if parts.len() != 2 {
+            return;
+        }
+        let values = parts[0]
+            .iter()
+            .zip(parts[1].iter())
+            .map(|((x, re), (_, im))| (*x, Complex::new(*re, *im)))
+            .collect::<Vec<(f64, Complex)>>();
+        let mut phase_offset = 0.0;
+        let mut previous_phase: Option<f64> = None;
+        let phase = values
+            .iter()
+            .map(|(x, value)| {
+                // The phase is unwrapped to avoid the jumps of 360° between -180° and 180°.
+                let raw = value.phase();
+                if let Some(previous) = previous_phase {
+                    let delta = raw + phase_offset - previous;
+                    if delta > 180.0 {
+                        phase_offset -= 360.0;
+                    } else if delta < -180.0 {
+                        phase_offset += 360.0;
+                    }
+                }
+                previous_phase = Some(raw + phase_offset);
+                Point::new(*x, raw + phase_offset)
+            })
+            .collect::<Vec<Point>>();
+        let magnitude = values
+            .iter()
+            .map(|(x, value)| Point::new(*x, value.db()))
+            .collect::<Vec<Point>>();
+        y_labels.push(format!("{} (dB)", probes[idx].name));
+        y_labels.push(format!("{} (°)", probes[idx].name));
+        series.push(magnitude);
+        series.push(phase);
+    });
+    (x_label, y_labels, series)
+}
+
+/// Parse the output of a noise analysis. The output (`onoise_spectrum`) and the input referred
+/// (`inoise_spectrum`) noise spectral densities are returned with the noise integrated over the
+/// frequencies (`sqrt(∫ S² df)`). The total integrated noise is added to the label.
+pub fn parse_noise_output(source: &str, output: &str) -> (String, Vec<String>, Vec<Vec<Point>>) {
+    let mut y_labels = Vec::new();
+    let mut series = Vec::new();
+    let (x_label, columns) = match parse_columns(output) {
+        Some(table) if table.1.len() == 2 => table,
+        _ => {
+            error::show(Box::new(error::Sim::SpiceNoData));
+            return (String::new(), y_labels, series);
+        }
+    };
+    let input_unit = if source.to_lowercase().starts_with('i') {
+        unit::Unit::Ampere
+    } else {
+        unit::Unit::Volt
+    };
+    for (name, column, unit) in [
+        ("onoise", &columns[0], unit::Unit::Volt),
+        ("inoise", &columns[1], input_unit),
+    ] {
+        let mut total = 0.0;
+        let integrated = column
+            .iter()
+            .enumerate()
+            .map(|(idx, (x, y))| {
+                if idx > 0 {
+                    let (previous_x, previous_y) = column[idx - 1];
+                    total += (x - previous_x) * (y * y + previous_y * previous_y) / 2.0;
+                }
+                Point::new(*x, total.sqrt())
+            })
+            .collect::<Vec<Point>>();
+        y_labels.push(format!("{} ({}/√Hz)", name, unit));
+        series.push(column.iter().map(|(x, y)| Point::new(*x, *y)).collect());
+        y_labels.push(format!(
+            "{} total ({})",
+            name,
+            unit::format(total.sqrt(), &unit)
+        ));
+        series.push(integrated);
+    }
+    (x_label, y_labels, series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The x values of the points of a curve.
+    fn xs(points: &[Point]) -> Vec<f64> {
+        points.iter().map(|point| point.x).collect()
+    }
+
+    #[test]
+    fn family_is_split_by_the_outer_values() {
+        // 3 points for the inner sweep, 2 values for the outer sweep.
+        let points = [0.0, 1.0, 2.0, 0.0, 1.0, 2.0]
+            .iter()
+            .enumerate()
+            .map(|(idx, &x)| Point::new(x, idx as f64))
+            .collect();
+        let (labels, curves) = split_family(
+            vec![String::from("v(out)")],
+            vec![points],
+            "VV2",
+            &[1.0, 2.0],
+        );
+        assert_eq!(labels, vec!["v(out) (VV2=1.00 V)", "v(out) (VV2=2.00 V)"]);
+        assert_eq!(xs(&curves[0]), vec![0.0, 1.0, 2.0]);
+        assert_eq!(xs(&curves[1]), vec![0.0, 1.0, 2.0]);
+        assert_eq!(curves[1][0].y, 3.0);
+    }
+
+    #[test]
+    fn family_with_a_single_inner_point() {
+        // The inner sweep does not move (`start == stop`), each outer value is one point.
+        let points = (0..3).map(|idx| Point::new(5.0, idx as f64)).collect();
+        let (labels, curves) = split_family(
+            vec![String::from("i(V1)")],
+            vec![points],
+            "IIin",
+            &[1e-3, 2e-3, 3e-3],
+        );
+        assert_eq!(labels.len(), 3);
+        assert_eq!(
+            curves.iter().map(Vec::len).collect::<Vec<usize>>(),
+            vec![1, 1, 1]
+        );
+        assert_eq!(curves[2][0].y, 2.0);
+        assert!(labels[2].starts_with("i(V1) (IIin="));
+    }
+
+    #[test]
+    fn ac_output_is_a_bode_plot() {
+        let probes = sim::Probes::new(
+            vec![sim::Probe {
+                spice: String::from("v(out)"),
+                name: String::from("out"),
+            }],
+            Vec::new(),
+        );
+        // An RC low-pass filter at 0 Hz, at its cut-off frequency then with a phase that goes
+        // past -180°.
+        let output = "\
+No. of Data Rows : 4
+                              AC Analysis  Sun Jan  1 00:00:00  2023
+--------------------------------------------------------------------------------
+Index   frequency       vr(out)         vi(out)
+--------------------------------------------------------------------------------
+0\t0.000000e+00\t1.000000e+00\t0.000000e+00
+1\t1.591549e+02\t5.000000e-01\t-5.000000e-01
+2\t1.000000e+03\t-1.000000e-01\t-1.000000e-02
+3\t2.000000e+03\t-1.000000e-01\t1.000000e-02
+";
+        let (x_label, y_labels, series) = parse_ac_output(&probes, output);
+        assert_eq!(x_label, "frequency");
+        assert_eq!(y_labels, vec!["out (dB)", "out (°)"]);
+        let (magnitude, phase) = (&series[0], &series[1]);
+        assert_eq!(magnitude.len(), 4);
+        assert!(magnitude[0].y.abs() < 1e-9);
+        assert!((magnitude[1].x - 159.1549).abs() < 1e-9);
+        assert!((magnitude[1].y + 10.0 * 2f64.log10()).abs() < 1e-9);
+        assert!((phase[1].y + 45.0).abs() < 1e-9);
+        // The phase is unwrapped instead of jumping from -174° to 174°.
+        assert!(phase[2].y < -174.0 && phase[2].y > -175.0);
+        assert!(phase[3].y < -185.0 && phase[3].y > -186.0);
+    }
+
+    #[test]
+    fn ac_output_on_two_pages() {
+        let probes = sim::Probes::new(
+            vec![sim::Probe {
+                spice: String::from("v(out)"),
+                name: String::from("out"),
+            }],
+            Vec::new(),
+        );
+        // Without `.options nopage`, the header is repeated at the top of every page.
+        let output = "\
+Index   frequency       vr(out)         vi(out)
+--------------------------------------------------------------------------------
+0\t1.000000e+01\t1.000000e+00\t0.000000e+00
+1\t1.000000e+02\t5.000000e-01\t-5.000000e-01
+\x0c
+                              AC Analysis  Sun Jan  1 00:00:00  2023
+--------------------------------------------------------------------------------
+Index   frequency       vr(out)         vi(out)
+--------------------------------------------------------------------------------
+2\t1.000000e+03\t1.000000e-01\t-3.000000e-01
+";
+        let (_, y_labels, series) = parse_ac_output(&probes, output);
+        assert_eq!(y_labels, vec!["out (dB)", "out (°)"]);
+        assert_eq!(series.len(), 2);
+        assert_eq!(xs(&series[0]), vec![10.0, 100.0, 1000.0]);
+        assert_eq!(xs(&series[1]), vec![10.0, 100.0, 1000.0]);
+    }
+
+    #[test]
+    fn noise_output_is_integrated() {
+        let output = "\
+Index   frequency       onoise_spectrum inoise_spectrum
+--------------------------------------------------------------------------------
+0\t0.000000e+00\t2.000000e+00\t1.000000e+00
+1\t1.000000e+01\t2.000000e+00\t1.000000e+00
+\x0c
+Index   frequency       onoise_spectrum inoise_spectrum
+--------------------------------------------------------------------------------
+2\t2.000000e+01\t4.000000e+00\t1.000000e+00
+";
+        let (x_label, y_labels, series) = parse_noise_output("VVin", output);
+        assert_eq!(x_label, "frequency");
+        assert_eq!(y_labels.len(), 4);
+        assert_eq!(y_labels[0], "onoise (V/√Hz)");
+        assert!(y_labels[1].starts_with("onoise total ("));
+        assert_eq!(y_labels[2], "inoise (V/√Hz)");
+        assert_eq!(xs(&series[0]), vec![0.0, 10.0, 20.0]);
+        assert_eq!(
+            series[0].iter().map(|point| point.y).collect::<Vec<f64>>(),
+            vec![2.0, 2.0, 4.0]
+        );
+        // onoise: 10 * (2² + 2²) / 2 + 10 * (2² + 4²) / 2 = 140
+        let onoise = &series[1];
+        assert_eq!(onoise[0].y, 0.0);
+        assert!((onoise[1].y - 40f64.sqrt()).abs() < 1e-9);
+        assert!((onoise[2].y - 140f64.sqrt()).abs() < 1e-9);
+        // inoise: 20 * 1² = 20
+        assert!((series[3][2].y - 20f64.sqrt()).abs() < 1e-9);
+    }
+}
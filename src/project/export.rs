@@ -1,17 +1,34 @@
 use crate::schema::{parts, wires};
+use crate::sim::settings;
 
-pub fn to_oregano(wires: &Vec<wires::Wire>, parts: &Vec<parts::Part>) -> String {
+pub fn to_oregano(
+    wires: &[wires::Wire],
+    parts: &[parts::Part],
+    subcircuits: &[parts::Subcircuit],
+    settings: &settings::Values,
+) -> String {
     let wires_section = wires
         .iter()
         .fold(String::from("[WIRES]:::"), |mut acc, wire| {
             acc.push_str(&format!("{:?}:::", wire));
             acc
         });
+    // The blocks are before the parts, the instances are created from them.
+    let subcircuits_section =
+        subcircuits
+            .iter()
+            .fold(String::from("[SUBCIRCUITS]:::"), |mut acc, subcircuit| {
+                acc.push_str(&format!("{:?}:::", subcircuit));
+                acc
+            });
     let parts_section = parts
         .iter()
         .fold(String::from("[PARTS]:::"), |mut acc, part| {
             acc.push_str(&format!("{:?}:::", part));
             acc
         });
-    format!("{}{}[ANALYSIS]:::{}", wires_section, parts_section, "")
+    format!(
+        "{}{}{}[ANALYSIS]:::{:?}",
+        wires_section, subcircuits_section, parts_section, settings
+    )
 }
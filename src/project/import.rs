@@ -1,37 +1,99 @@
+use crate::project::spice;
 use crate::schema::{parts, wires};
+use crate::sim::settings;
 use crate::{clog, error};
 
 enum FileLocation {
     Start,
     Wires,
+    Subcircuits,
     Parts,
     Analysis,
 }
 
-pub fn from_oregano(input: &str) -> Result<(Vec<wires::Wire>, Vec<parts::Part>), error::Error> {
+/// A project: its wires, its parts, its blocks and the settings of its simulation. The projects
+/// saved before the settings were saved have no settings.
+pub type Project = (
+    Vec<wires::Wire>,
+    Vec<parts::Part>,
+    Vec<parts::Subcircuit>,
+    Option<settings::Values>,
+);
+
+/// Import a project saved by `export::to_oregano` (it starts with its wires) or a SPICE netlist.
+/// The cards of a netlist that could not be imported are returned as warnings.
+pub fn from_text(input: &str) -> Result<(Project, Vec<error::Diagnostic>), error::Error> {
+    if input.starts_with("[WIRES]") {
+        Ok((from_oregano(input)?, Vec::new()))
+    } else {
+        spice::from_spice(input)
+    }
+}
+
+pub fn from_oregano(input: &str) -> Result<Project, error::Error> {
     let mut wires = Vec::new();
     let mut parts = Vec::new();
+    let mut definitions = Vec::new();
+    let mut subcircuits = Vec::new();
+    let mut settings = None;
     let mut file_location = FileLocation::Start;
     input
         .split(":::")
         .map(|line| {
             match line {
                 "[WIRES]" => file_location = FileLocation::Wires,
-                "[PARTS]" => file_location = FileLocation::Parts,
+                "[SUBCIRCUITS]" => file_location = FileLocation::Subcircuits,
+                "[PARTS]" => {
+                    subcircuits = read_subcircuits(&definitions)?;
+                    file_location = FileLocation::Parts;
+                }
                 "[ANALYSIS]" => file_location = FileLocation::Analysis,
                 _ => match file_location {
                     FileLocation::Wires => {
                         wires.push(line.parse::<wires::Wire>()?);
                     }
+                    FileLocation::Subcircuits if !line.is_empty() => {
+                        definitions.push(line);
+                    }
                     FileLocation::Parts => {
-                        parts.push(line.parse::<parts::Part>()?);
+                        parts.push(parts::Part::from_line(line, &subcircuits)?);
+                    }
+                    FileLocation::Analysis if !line.is_empty() => {
+                        settings = Some(line.parse::<settings::Values>()?);
                     }
-                    FileLocation::Analysis => {}
                     _ => {}
                 },
             };
             Ok(())
         })
         .collect::<Result<(), error::Error>>()?;
-    Ok((wires, parts))
+    Ok((wires, parts, subcircuits, settings))
+}
+
+/// A block can only be read once the blocks it instantiates are read. The blocks are read in as
+/// many passes as needed, a block that can never be read instantiates a missing block (or
+/// itself).
+fn read_subcircuits(definitions: &[&str]) -> Result<Vec<parts::Subcircuit>, error::Error> {
+    let mut subcircuits = Vec::new();
+    let mut pending = definitions.to_vec();
+    while !pending.is_empty() {
+        let mut unread = Vec::new();
+        let mut last_error = None;
+        for definition in pending.iter() {
+            match parts::Subcircuit::from_line(definition, &subcircuits) {
+                Ok(subcircuit) => subcircuits.push(subcircuit),
+                Err(error) => {
+                    unread.push(*definition);
+                    last_error = Some(error);
+                }
+            }
+        }
+        if unread.len() == pending.len() {
+            if let Some(error) = last_error {
+                return Err(error);
+            }
+        }
+        pending = unread;
+    }
+    Ok(subcircuits)
 }
@@ -0,0 +1,632 @@
+use crate::intrinsics::*;
+use crate::project::import;
+use crate::schema::{parts, properties, wires};
+use crate::sim::native::transient::Method;
+use crate::sim::netlist::{cards, number, Card};
+use crate::sim::{settings, temperature};
+use crate::{error, sim, unit, PARTS};
+use std::collections::HashMap;
+
+/// The horizontal space between the parts.
+const COLUMN_GAP: f64 = 40.0;
+/// The vertical space between the tracks (see `route`).
+const TRACK_GAP: f64 = 20.0;
+
+/// A model defined by a `.model` card: its type (ie: `d`) and its parameters.
+struct Model {
+    typ: String,
+    params: Vec<(String, String)>,
+}
+
+/// A part created from a card and the nodes its connectors are connected to.
+struct Element {
+    part: parts::Part,
+    nodes: Vec<String>,
+}
+
+/// Import a SPICE netlist (like `spice-test/test.cir`). Each element card becomes a part placed
+/// on a row and the nodes are routed with wires (see `route`), the analysis cards become the
+/// settings of the simulation. The cards that cannot be imported are skipped and returned as
+/// warnings.
+pub fn from_spice(input: &str) -> Result<(import::Project, Vec<error::Diagnostic>), error::Error> {
+    let cards = cards(input);
+    let models = cards
+        .iter()
+        .filter(|card| card.keyword() == ".model" && card.words.len() >= 3)
+        .map(|card| {
+            let model = Model {
+                typ: card.words[2].to_lowercase(),
+                params: card.params().to_vec(),
+            };
+            (card.words[1].to_lowercase(), model)
+        })
+        .collect::<HashMap<String, Model>>();
+    let mut elements = Vec::new();
+    let mut analyses = Vec::new();
+    let mut temperature = None;
+    let mut warnings = Vec::new();
+    for card in cards.iter() {
+        let imported = match &card.keyword()[..] {
+            ".model" => Ok(()),
+            // The analyses refer to the elements, which can be after them.
+            ".tran" | ".ac" | ".dc" | ".op" => {
+                analyses.push(card);
+                Ok(())
+            }
+            ".temp" => card
+                .word(1)
+                .and_then(|word| temperature::Settings::parse(word, "").map_err(|_| card.invalid()))
+                .map(|settings| temperature = Some(settings)),
+            _ => element(card, &models).map(|(element, is_partial)| {
+                if is_partial {
+                    warnings.push(error::Diagnostic::warning(
+                        error::Import::PartialCard(card.line.clone()),
+                        Vec::new(),
+                    ));
+                }
+                elements.push(element);
+            }),
+        };
+        if let Err(error) = imported {
+            warnings.push(error::Diagnostic::warning(error, Vec::new()));
+        }
+    }
+    if elements.is_empty() {
+        return Err(Box::new(error::Import::EmptyNetlist));
+    }
+    // The first analysis is selected.
+    let mut selected = None;
+    for card in analyses {
+        match analysis(card, &elements) {
+            Ok(analysis) if selected.is_none() => selected = Some(analysis),
+            Ok(_) => {}
+            Err(error) => warnings.push(error::Diagnostic::warning(error, Vec::new())),
+        }
+    }
+    let settings = selected.map(|analysis| {
+        let mut values = settings::Values::default();
+        values.set_analysis(&analysis);
+        if let Some(temperature) = &temperature {
+            values.set_temperature(temperature);
+        }
+        values
+    });
+    let (wires, parts) = route(elements)?;
+    Ok(((wires, parts, Vec::new(), settings), warnings))
+}
+
+/// Create the part of an element card. Also returns if a part of the card was ignored (like a
+/// waveform that is not supported by the sources).
+fn element(card: &Card, models: &HashMap<String, Model>) -> Result<(Element, bool), error::Import> {
+    let name = card.word(0)?;
+    let two_nodes = || -> Result<Vec<String>, error::Import> {
+        Ok(vec![card.word(1)?.to_string(), card.word(2)?.to_string()])
+    };
+    let mut is_partial = false;
+    let (part, nodes) = match &card.keyword()[..] {
+        "R" => {
+            let mut part = part("lumped.resistor", name)?;
+            set_property(&mut part, "value", card.word(3)?).map_err(|_| card.invalid())?;
+            for key in ["tc1", "tc2", "temp"] {
+                if let Some(value) = card.param(key) {
+                    set_property(&mut part, key, value).map_err(|_| card.invalid())?;
+                }
+            }
+            (part, two_nodes()?)
+        }
+        letter @ ("C" | "L") => {
+            let typ = match letter {
+                "C" => "lumped.capacitor",
+                _ => "lumped.inductor",
+            };
+            let mut part = part(typ, name)?;
+            set_property(&mut part, "value", card.word(3)?).map_err(|_| card.invalid())?;
+            if let Some(value) = card.param("ic") {
+                set_property(&mut part, "init_cond", value).map_err(|_| card.invalid())?;
+            }
+            (part, two_nodes()?)
+        }
+        letter @ ("V" | "I") => {
+            let (part, partial) = source(card, letter, name)?;
+            is_partial = partial;
+            (part, two_nodes()?)
+        }
+        "D" => {
+            let model = card.word(3)?;
+            // The models of the library are parts (ie: `non_linear.diode-1N4148`).
+            let library = PARTS
+                .get(&format!("non_linear.{}", model))
+                .ok()
+                .filter(|part| part.typ.starts_with("non_linear.diode"));
+            let part = match library {
+                Some(part) => named(part, name),
+                None => {
+                    let mut part = part("non_linear.diode", name)?;
+                    if let Some(model) = models
+                        .get(&model.to_lowercase())
+                        .filter(|model| model.typ == "d")
+                    {
+                        for (key, value) in model.params.iter() {
+                            let key = match &key[..] {
+                                "cj0" => String::from("diode_cjo"),
+                                key => format!("diode_{}", key),
+                            };
+                            if set_property(&mut part, &key, value).is_err() {
+                                is_partial = true;
+                            }
+                        }
+                    } else {
+                        is_partial = true;
+                    }
+                    part
+                }
+            };
+            (part, two_nodes()?)
+        }
+        "Q" => {
+            // The polarity is the type of the model, a library model keeps its parameters. Like
+            // ngspice, the 4th word is the substrate node when it is not a model, the model is
+            // then the 5th word. The substrate node and the instance parameters (ie: `area=2` or
+            // `off`) are not supported.
+            let polarity = |model: &str| match models.get(model) {
+                _ if model == "transistor-pnp" || model == "transistor-npn" => {
+                    Some(model.to_string())
+                }
+                Some(Model { typ, .. }) if typ == "pnp" || typ == "npn" => {
+                    Some(format!("transistor-{}", typ))
+                }
+                _ => None,
+            };
+            let mut model = card.word(4)?.to_lowercase();
+            if polarity(&model).is_none() {
+                if let Ok(word) = card.word(5) {
+                    model = word.to_lowercase();
+                }
+            }
+            let typ = match polarity(&model) {
+                Some(typ) => typ,
+                None => return Err(error::Import::UnknownModel(card.line.clone(), model)),
+            };
+            let part = part(&format!("non_linear.{}", typ), name)?;
+            is_partial = card.words.len() > 5 || !card.params().is_empty();
+            let nodes = (1..=3)
+                .map(|idx| card.word(idx).map(|node| node.to_string()))
+                .collect::<Result<Vec<String>, error::Import>>()?;
+            (part, nodes)
+        }
+        _ => return Err(error::Import::UnsupportedCard(card.line.clone())),
+    };
+    Ok((Element { part, nodes }, is_partial))
+}
+
+/// Create an independent source. A source with a `sin` waveform is an AC source, any other source
+/// is a DC source (the other waveforms are not supported).
+fn source(card: &Card, letter: &str, name: &str) -> Result<(parts::Part, bool), error::Import> {
+    let words = card
+        .words
+        .iter()
+        .skip(3)
+        .map(|word| &word[..])
+        .collect::<Vec<&str>>();
+    let mut dc = None;
+    let mut sin = None;
+    let mut is_partial = false;
+    let mut idx = 0;
+    while idx < words.len() {
+        let numbers = words[idx + 1..]
+            .iter()
+            .take_while(|word| number(word).is_some())
+            .count();
+        match &words[idx].to_lowercase()[..] {
+            "dc" => {
+                dc = words.get(idx + 1).copied();
+                idx += 2;
+            }
+            // The magnitude of the AC analysis is always 1.
+            "ac" => idx += 1 + numbers.min(2),
+            "sin" => {
+                sin = Some(&words[idx + 1..idx + 1 + numbers]);
+                idx += 1 + numbers;
+            }
+            word if number(word).is_some() && dc.is_none() => {
+                dc = Some(words[idx]);
+                idx += 1;
+            }
+            _ => {
+                is_partial = true;
+                idx += 1 + numbers;
+            }
+        }
+    }
+    let (voltage, current) = match sin {
+        Some(_) => ("source.voltage_ac", "source.current_ac"),
+        None => ("source.voltage_dc", "source.current_dc"),
+    };
+    let mut part = part(if letter == "V" { voltage } else { current }, name)?;
+    match sin {
+        Some(values) => {
+            let keys = [
+                "offset",
+                "amplitude",
+                "frequency",
+                "delay",
+                "damping_factor",
+                "phase",
+            ];
+            for (key, value) in keys.iter().zip(values.iter()) {
+                set_property(&mut part, key, value).map_err(|_| card.invalid())?;
+            }
+        }
+        None => set_property(&mut part, "value", dc.unwrap_or("0")).map_err(|_| card.invalid())?,
+    }
+    Ok((part, is_partial))
+}
+
+/// Read an analysis card (`.tran`, `.ac`, `.dc` or `.op`). The swept sources of `.dc` are among
+/// the imported `elements`.
+fn analysis(card: &Card, elements: &[Element]) -> Result<settings::Analysis, error::Import> {
+    let setting = |idx: usize, unit: unit::Unit| {
+        let (value, prefix) = number(card.word(idx)?).ok_or_else(|| card.invalid())?;
+        Ok(properties::Value::Unit(value, unit, prefix, None))
+    };
+    match &card.keyword()[..] {
+        ".tran" => Ok(settings::Analysis::Transient(settings::Transient {
+            step: setting(1, unit::Unit::Second)?,
+            stop: setting(2, unit::Unit::Second)?,
+            method: Method::Trapezoidal,
+            fourier: None,
+        })),
+        ".ac" => Ok(settings::Analysis::Frequency(settings::Frequency {
+            variation: card.word(1)?.to_lowercase(),
+            points: card.word(2)?.to_string(),
+            start: setting(3, unit::Unit::Hertz)?,
+            stop: setting(4, unit::Unit::Hertz)?,
+        })),
+        ".dc" => {
+            // A second source can be swept (`.dc src start stop step src2 start2 stop2 step2`).
+            let mut sweeps = Vec::new();
+            for offset in [1, 5] {
+                if card.words.len() <= offset {
+                    continue;
+                }
+                // The names of SPICE are case insensitive, the source is the device of the part
+                // imported from its card (`vin` is the part `Vin` whose device is `VVin`).
+                let source = card.word(offset)?.to_lowercase();
+                let device = elements
+                    .iter()
+                    .find(|element| {
+                        element
+                            .part
+                            .properties
+                            .get("name")
+                            .is_ok_and(|name| name.value.to_string().to_lowercase() == source)
+                    })
+                    .and_then(|element| element.part.device())
+                    .ok_or_else(|| card.invalid())?;
+                sweeps.push(sim::DcSweep {
+                    source: device,
+                    start: setting(offset + 1, unit::Unit::Volt)?,
+                    stop: setting(offset + 2, unit::Unit::Volt)?,
+                    step: setting(offset + 3, unit::Unit::Volt)?,
+                });
+            }
+            Ok(settings::Analysis::Dc(sweeps))
+        }
+        _ => Ok(settings::Analysis::OperatingPoint),
+    }
+}
+
+/// A part of the library with its name.
+fn part(typ: &str, name: &str) -> Result<parts::Part, error::Import> {
+    PARTS
+        .get(typ)
+        .map(|part| named(part, name))
+        .map_err(|_| error::Import::PartNotFound)
+}
+
+fn named(mut part: parts::Part, name: &str) -> parts::Part {
+    if let Some(property) = part.properties.properties.get_mut("name") {
+        property.value = properties::Value::from(name);
+    }
+    part
+}
+
+/// Replace the value of a property by a value written in SPICE (ie: `4.7k`). The numbers keep the
+/// unit of the property.
+fn set_property(part: &mut parts::Part, key: &str, text: &str) -> Result<(), error::Import> {
+    let property = part
+        .properties
+        .properties
+        .get_mut(key)
+        .ok_or(error::Import::UnexpectedValue)?;
+    property.value = match &property.value {
+        properties::Value::Unit(_, unit, _, _) => {
+            let (value, prefix) = number(text).ok_or(error::Import::UnexpectedValue)?;
+            properties::Value::Unit(value, unit.clone(), prefix, None)
+        }
+        properties::Value::F64(_) => {
+            let (value, prefix) = number(text).ok_or(error::Import::UnexpectedValue)?;
+            properties::Value::F64(value * prefix.factor())
+        }
+        _ => properties::Value::from(text),
+    };
+    Ok(())
+}
+
+/// Place the parts on a row and connect the nodes with wires. Each node is named with a node (or
+/// a ground for the node `0`) added at the end of the row.
+///
+/// The connectors in the top half of a part go up to a track (a horizontal wire) of their node
+/// above the row, the other connectors go down to a track under the row. Each node has its own
+/// tracks and a node with connectors going up and down has a riser (a vertical wire) on the left
+/// of the row between its tracks. Every wire ends on a wire of its own node and the wires of
+/// different nodes only cross each other, so no nodes are merged.
+fn route(mut elements: Vec<Element>) -> Result<(Vec<wires::Wire>, Vec<parts::Part>), error::Error> {
+    let mut nodes: Vec<String> = Vec::new();
+    elements
+        .iter_mut()
+        .flat_map(|element| element.nodes.iter_mut())
+        .for_each(|node| {
+            if node == "0" || node.to_lowercase() == "gnd" {
+                *node = String::from("0");
+            }
+            if !nodes.contains(node) {
+                nodes.push(node.clone());
+            }
+        });
+    for node in nodes.iter() {
+        let part = match &node[..] {
+            "0" => PARTS.get("lumped.ground")?,
+            node => named(PARTS.get("lumped.node")?, node),
+        };
+        elements.push(Element {
+            part,
+            nodes: vec![node.clone()],
+        });
+    }
+
+    let bottom = elements
+        .iter()
+        .map(|element| element.part.layout.size.h)
+        .fold(0.0, f64::max);
+    let bottom = (bottom / Point::GRID_SIZE).ceil() * Point::GRID_SIZE;
+    let track = |node: usize, is_up: bool| {
+        let offset = TRACK_GAP * (node + 1) as f64;
+        if is_up {
+            -offset
+        } else {
+            bottom + offset
+        }
+    };
+    // The connectors of each node: their position and if they go up.
+    let mut stubs: Vec<Vec<(Point, bool)>> = vec![Vec::new(); nodes.len()];
+    let mut x = 0.0;
+    let mut parts = Vec::new();
+    for element in elements.into_iter() {
+        let mut part = element.part;
+        part.layout.origin = Point::new(x, 0.0);
+        for (connector, node) in part.layout.connectors.iter().zip(element.nodes.iter()) {
+            let idx = nodes.iter().position(|name| name == node).unwrap();
+            let position = part.layout.origin + connector.origin;
+            stubs[idx].push((position, connector.origin.y < part.layout.size.h / 2.0));
+        }
+        x += ((part.layout.size.w + COLUMN_GAP) / Point::GRID_SIZE).ceil() * Point::GRID_SIZE;
+        parts.push(part);
+    }
+
+    let mut wires = Vec::new();
+    for (idx, stubs) in stubs.iter().enumerate() {
+        if stubs.len() < 2 {
+            continue;
+        }
+        let has_up = stubs.iter().any(|(_, is_up)| *is_up);
+        let has_down = stubs.iter().any(|(_, is_up)| !is_up);
+        let riser = -TRACK_GAP * (idx + 1) as f64;
+        if has_up && has_down {
+            wires.push(wires::Wire::new(
+                Point::new(riser, track(idx, true)),
+                Point::new(riser, track(idx, false)),
+            ));
+        }
+        for is_up in [true, false] {
+            let y = track(idx, is_up);
+            let mut xs = stubs
+                .iter()
+                .filter(|(_, up)| *up == is_up)
+                .map(|(position, _)| {
+                    wires.push(wires::Wire::new(*position, Point::new(position.x, y)));
+                    position.x
+                })
+                .collect::<Vec<f64>>();
+            if has_up && has_down {
+                xs.push(riser);
+            }
+            let min = xs.iter().cloned().fold(f64::MAX, f64::min);
+            let max = xs.iter().cloned().fold(f64::MIN, f64::max);
+            if xs.len() > 1 && min < max {
+                wires.push(wires::Wire::new(Point::new(min, y), Point::new(max, y)));
+            }
+        }
+    }
+    Ok((wires, parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::circuit;
+    use std::collections::BTreeMap;
+
+    /// The type of each imported part by name and the lines of the cards imported partially (or
+    /// skipped for their model).
+    fn imported(netlist: &str) -> (BTreeMap<String, String>, Vec<String>) {
+        let ((_, parts, _, _), warnings) = from_spice(netlist).unwrap();
+        let parts = parts
+            .into_iter()
+            .filter(|part| part.device().is_some())
+            .map(|part| {
+                let name = part.properties.get("name").unwrap().value.to_string();
+                (name, part.typ)
+            })
+            .collect();
+        let warnings = warnings
+            .into_iter()
+            .map(
+                |warning| match warning.error.downcast_ref::<error::Import>() {
+                    Some(error::Import::PartialCard(line)) => line.clone(),
+                    Some(error::Import::UnknownModel(line, _)) => line.clone(),
+                    _ => panic!("unexpected warning: {}", warning),
+                },
+            )
+            .collect();
+        (parts, warnings)
+    }
+
+    #[test]
+    fn cards_to_parts() {
+        let (parts, partial) = imported(
+            "Title\n\
+             R1 in out 4.7k\n\
+             C1 out 0 10uF ic=1\n\
+             L1 in mid 1m\n\
+             Vin in 0 dc 5\n\
+             Vsin mid 0 sin(0 1 1k)\n\
+             Iin mid out 1m\n\
+             D1 out 0 diode-1N4148\n\
+             D2 out mid custom\n\
+             .model custom D(IS=1e-14 N=1.5)\n\
+             Qp out mid 0 pmodel\n\
+             Qn out mid 0 nmodel\n\
+             Qs out mid 0 0 transistor-npn\n\
+             Qx out mid 0 xmodel\n\
+             Qa out mid 0 nmodel area=2\n\
+             Qo out mid 0 nmodel 2 off\n\
+             Qy out mid 0 0 nmodel area=2\n\
+             .model pmodel PNP(BF=50)\n\
+             .model nmodel NPN(BF=50)\n\
+             .end",
+        );
+        let typ = |name: &str| &parts[name][..];
+        assert_eq!(typ("R1"), "lumped.resistor");
+        assert_eq!(typ("C1"), "lumped.capacitor");
+        assert_eq!(typ("L1"), "lumped.inductor");
+        assert_eq!(typ("Vin"), "source.voltage_dc");
+        assert_eq!(typ("Vsin"), "source.voltage_ac");
+        assert_eq!(typ("Iin"), "source.current_dc");
+        assert_eq!(typ("D1"), "non_linear.diode-1N4148");
+        assert_eq!(typ("D2"), "non_linear.diode");
+        assert_eq!(typ("Qp"), "non_linear.transistor-pnp");
+        assert_eq!(typ("Qn"), "non_linear.transistor-npn");
+        assert_eq!(typ("Qs"), "non_linear.transistor-npn");
+        // The instance parameters after the model are ignored.
+        assert_eq!(typ("Qa"), "non_linear.transistor-npn");
+        assert_eq!(typ("Qo"), "non_linear.transistor-npn");
+        assert_eq!(typ("Qy"), "non_linear.transistor-npn");
+        // The transistor without model is skipped, its polarity is not known.
+        assert!(!parts.contains_key("Qx"));
+        assert_eq!(
+            partial,
+            vec![
+                "Qs out mid 0 0 transistor-npn",
+                "Qx out mid 0 xmodel",
+                "Qa out mid 0 nmodel area=2",
+                "Qo out mid 0 nmodel 2 off",
+                "Qy out mid 0 0 nmodel area=2",
+            ]
+        );
+    }
+
+    /// The nodes of each element of a netlist by device name (ie: `RR1` for `R1`, like the
+    /// parts name their device), the nodes of the analysis cards are skipped.
+    fn nodes(netlist: &str, is_imported: bool) -> BTreeMap<String, Vec<String>> {
+        cards(netlist)
+            .into_iter()
+            .filter(|card| !card.keyword().starts_with('.'))
+            .map(|card| {
+                let count = if card.keyword() == "Q" { 3 } else { 2 };
+                let device = match is_imported {
+                    true => format!("{}{}", card.keyword(), card.words[0]),
+                    false => card.words[0].clone(),
+                };
+                // `gnd` is the ground, like `0`.
+                let nodes = card.words[1..=count]
+                    .iter()
+                    .map(|node| match &node.to_lowercase()[..] {
+                        "gnd" => String::from("0"),
+                        _ => node.clone(),
+                    })
+                    .collect();
+                (device, nodes)
+            })
+            .collect()
+    }
+
+    /// Import a netlist and check that the netlist of the imported circuit connects the same
+    /// connectors together.
+    fn assert_same_partition(netlist: &str) {
+        let ((wires, parts, subcircuits, _), _) = from_spice(netlist).unwrap();
+        let circuit = circuit::Circuit::new(wires, parts, subcircuits).unwrap();
+        let simulation = circuit
+            .to_string(&settings::Settings {
+                analysis: settings::Analysis::OperatingPoint,
+                temperature: temperature::Settings {
+                    temperatures: vec![temperature::NOMINAL],
+                },
+                montecarlo: None,
+                engine: sim::Engine::Native,
+                timeout: None,
+            })
+            .unwrap();
+        let expected = nodes(netlist, true);
+        let routed = nodes(&simulation.runs[0].netlist, false);
+        assert_eq!(
+            expected.keys().collect::<Vec<&String>>(),
+            routed.keys().collect::<Vec<&String>>()
+        );
+        let flat = |nodes: &BTreeMap<String, Vec<String>>| {
+            nodes.values().flatten().cloned().collect::<Vec<String>>()
+        };
+        let (expected, routed) = (flat(&expected), flat(&routed));
+        // The same connectors are grounded and the same pairs of connectors are connected.
+        for a in 0..expected.len() {
+            assert_eq!(expected[a] == "0", routed[a] == "0");
+            for b in 0..expected.len() {
+                assert_eq!(expected[a] == expected[b], routed[a] == routed[b]);
+            }
+        }
+    }
+
+    #[test]
+    fn routed_nodes() {
+        assert_same_partition(include_str!("../../spice-test/test.cir"));
+        assert_same_partition(
+            "Title\n\
+             V1 in 0 dc 5\n\
+             R1 in mid 1k\n\
+             R2 mid out 2k\n\
+             C1 out 0 1u\n\
+             L1 mid gnd 1m\n\
+             D1 out in diode-1N4148\n\
+             R3 in out 10k\n\
+             .op\n\
+             .end",
+        );
+    }
+
+    #[test]
+    fn swept_sources() {
+        // The letter of a non-ASCII source name is not a single byte.
+        let ((_, _, _, values), _) =
+            from_spice("Title\nVω 1 0 1\nR1 1 0 1k\n.dc vω 0 1 0.1\n").unwrap();
+        assert_eq!(values.unwrap().text("sim__dc-source").unwrap(), "VVω");
+        // The analysis is before the source and does not write its name the same way.
+        let ((_, _, _, values), _) =
+            from_spice("Title\n.dc VIN 0 1 0.1\nVin 1 0 1\nR1 1 0 1k\n").unwrap();
+        assert_eq!(values.unwrap().text("sim__dc-source").unwrap(), "VVin");
+        // An unknown source is not imported.
+        let ((_, _, _, values), warnings) =
+            from_spice("Title\nVin 1 0 1\nR1 1 0 1k\n.dc V2 0 1 0.1\n").unwrap();
+        assert!(values.is_none());
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -2,7 +2,7 @@ use crate::error;
 use crate::schema::{parts, shape};
 use serde::Deserialize;
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub struct Layouts {
     pub layouts: HashMap<String, LayoutsResource>,
@@ -108,15 +108,29 @@ impl Parts {
         }
     }
 
-    pub fn models(&self) -> String {
-        self.parts.iter().fold(String::new(), |mut acc, (_, lib)| {
-            if let Some(models) = &lib.models {
-                models
-                    .iter()
-                    .for_each(|model| acc.push_str(&format!(".model {} \n", model)));
-            }
-            acc
-        })
+    /// Check the SPICE pattern of every part of the libraries and that the models they use
+    /// exist.
+    pub fn validate(&self) -> Result<(), error::Error> {
+        self.parts
+            .values()
+            .flat_map(|lib| lib.parts.values())
+            .try_for_each(|part| {
+                part.validate()?;
+                match part.models.iter().find(|model| self.model(model).is_none()) {
+                    Some(model) => {
+                        Err(Box::new(error::Sim::MissingModel(model.clone())) as error::Error)
+                    }
+                    None => Ok(()),
+                }
+            })
+    }
+
+    /// The `.model` card of a model of the libraries.
+    pub fn model(&self, name: &str) -> Option<String> {
+        self.parts
+            .values()
+            .find_map(|lib| lib.models.get(name))
+            .map(|model| format!(".model {} {}", name, model))
     }
 }
 
@@ -124,7 +138,9 @@ impl Parts {
 pub struct PartsResource {
     pub title: String,
     pub parts: HashMap<String, parts::Part>,
-    pub models: Option<Vec<String>>,
+    /// The models used by the parts, by name (ie: `"diode-1N4148": "d(IS=35p ...)"`).
+    #[serde(default)]
+    pub models: BTreeMap<String, String>,
 }
 
 impl PartsResource {
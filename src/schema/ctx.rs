@@ -102,6 +102,14 @@ impl Ctx {
         self.ctx.stroke();
     }
 
+    /// Draw a text where the size of the font will be the same no matter the scale of the current
+    /// screen.
+    pub fn fill_text_const(&self, text: &str, origin: Point, font_size: f64) {
+        self.ctx
+            .set_font(&format!("{}px Roboto", font_size / self.scale));
+        let _ = self.ctx.fill_text(text, origin.x, origin.y);
+    }
+
     pub fn stroke_points(&self, points: &[Point]) {
         if points.len() > 0 {
             self.begin_path();
@@ -13,8 +13,9 @@ use crate::dom::form::{checkbox, radio, select};
 use crate::intrinsics::*;
 use crate::project;
 use crate::schema::parts::part;
-use crate::{clog, dom, error, events, sim, sim::circuit};
-use wasm_bindgen::JsValue;
+use crate::sim::backend;
+use crate::{clog, dom, error, plot, sim, sim::circuit, view, views};
+use std::rc::Rc;
 
 pub struct Schema {
     ctx: ctx::Ctx,
@@ -22,9 +23,33 @@ pub struct Schema {
     wires: wires::Wires,
     pub mouse: mouse::Mouse,
     scene: scene::Scene,
-    pub probes: sim::Probes,
+    simulation: Option<sim::Simulation>,
+    annotations: Option<sim::op::Annotations>,
+    /// The diagnostics of the last simulation, their entities are outlined on the schema.
+    diagnostics: Vec<error::Diagnostic>,
+    /// The blocks of the project, their instances are parts of the schema or of other blocks.
+    subcircuits: Vec<parts::Subcircuit>,
+    /// The schemas above the block being edited, from the root. Each level is the name of the
+    /// block that was entered with the wires and the parts of the schema it was entered from.
+    hierarchy: Vec<(String, Vec<wires::Wire>, Vec<part::Part>)>,
+    backends: backend::Backends,
+    /// The request of the run being simulated and the backend it was sent to. The outputs of
+    /// the other requests are stale, they are discarded.
+    request: Option<(Rc<dyn backend::SimulatorBackend>, backend::RequestId)>,
+    /// Receives the output of the requests (see `events::add_events_schema`).
+    responder: Option<Responder>,
+    /// The runs of the simulations and how they ended.
+    jobs: sim::jobs::Jobs,
 }
 
+/// The current time in milliseconds, the time of the jobs.
+fn now() -> f64 {
+    js_sys::Date::now()
+}
+
+/// A function that receives the output of a request once the backend responds.
+pub type Responder = Rc<dyn Fn(backend::RequestId, backend::Output)>;
+
 impl Schema {
     pub fn new() -> Result<Self, error::Error> {
         Ok(Self {
@@ -33,7 +58,15 @@ impl Schema {
             wires: wires::Wires::new(),
             mouse: mouse::Mouse::new(),
             scene: scene::Scene::new(),
-            probes: sim::Probes::new(vec![], vec![]),
+            simulation: None,
+            annotations: None,
+            diagnostics: Vec::new(),
+            subcircuits: Vec::new(),
+            hierarchy: Vec::new(),
+            backends: backend::Backends::default(),
+            request: None,
+            responder: None,
+            jobs: sim::jobs::Jobs::default(),
         })
     }
 
@@ -49,6 +82,11 @@ impl Schema {
     }
 
     pub fn keyboard_dispatch(&mut self, event: &web_sys::KeyboardEvent) {
+        if ["w", "r", "Delete", "i"].contains(&&event.key()[..])
+            || (["v", "g"].contains(&&event.key()[..]) && event.ctrl_key())
+        {
+            self.circuit_changed();
+        }
         match &event.key()[..] {
             "w" => self.add_wire(),
             "r" => self.parts.rotate(),
@@ -59,12 +97,16 @@ impl Schema {
             "i" => self.import(),
             "c" if event.ctrl_key() => self.copy(),
             "v" if event.ctrl_key() => self.paste(),
+            "g" if event.ctrl_key() => self.collapse(),
+            "ArrowDown" if event.ctrl_key() => self.descend(),
+            "ArrowUp" if event.ctrl_key() => self.ascend(),
             _ => {}
         }
         self.update();
     }
 
     pub fn properties_dispatch(&mut self) {
+        self.circuit_changed();
         self.parts.update_selected();
         self.update();
     }
@@ -108,6 +150,7 @@ impl Schema {
             let _ =
                 dom::select(&format!("#menu__simulations-type-{}", typ)).set_attribute("class", "");
         }
+        self.sources_dispatch();
     }
 
     fn copy(&mut self) {
@@ -129,11 +172,203 @@ impl Schema {
 
     pub fn add_part(&mut self, part: Result<part::Part, error::Error>) {
         if let Ok(part) = part {
+            self.circuit_changed();
             self.parts.add(part);
             self.update();
         }
     }
 
+    /// Place a new instance of a block. A block cannot be placed in itself, even through the
+    /// blocks it instantiates.
+    pub fn add_subcircuit(&mut self, name: &str) {
+        let subcircuit = match self.subcircuits.iter().find(|s| s.name == name) {
+            Some(subcircuit) => subcircuit,
+            None => return,
+        };
+        let is_recursive = self
+            .hierarchy
+            .iter()
+            .any(|(edited, _, _)| edited == name || subcircuit.contains(edited, &self.subcircuits));
+        if is_recursive {
+            error::show(Box::new(error::Sim::RecursiveSubcircuit(name.to_string())));
+            return;
+        }
+        let typ = subcircuit.typ();
+        let count = self.parts.iter().filter(|part| part.typ == typ).count();
+        let instance = subcircuit.instance(&format!("{}{}", name, count + 1));
+        self.add_part(Ok(instance));
+    }
+
+    /// Replace the selected parts and wires by an instance of a new block made of them. The named
+    /// nodes of the selection are the ports of the block, they are wired to the pins of the
+    /// instance so the rest of the schema stays connected.
+    fn collapse(&mut self) {
+        if self.parts.selected.is_empty() {
+            return;
+        }
+        let selected_parts = std::mem::take(&mut self.parts.selected);
+        let selected_wires = std::mem::take(&mut self.wires.selected);
+        let (block_parts, mut parts) = Self::split(
+            std::mem::take(&mut self.parts.parts),
+            &selected_parts,
+            |part: &mut part::Part| part.state = utils::State::None,
+        );
+        let (block_wires, mut wires) = Self::split(
+            std::mem::take(&mut self.wires.wires),
+            &selected_wires,
+            |wire: &mut wires::Wire| wire.state = utils::State::None,
+        );
+        let subcircuit = parts::Subcircuit::new("", block_wires, block_parts);
+        let ports = subcircuit.ports();
+        let name = if ports.is_empty() {
+            error::show(Box::new(error::Sim::SubcircuitWithoutPort));
+            None
+        } else {
+            self.ask_subcircuit_name()
+        };
+        let name = match name {
+            Some(name) => name,
+            None => {
+                // The selection is put back as it was.
+                parts.extend(subcircuit.parts);
+                wires.extend(subcircuit.wires);
+                self.parts.parts = parts;
+                self.wires.wires = wires;
+                return;
+            }
+        };
+        let subcircuit = parts::Subcircuit { name, ..subcircuit };
+        let mut instance = subcircuit.instance(&format!("{}1", subcircuit.name));
+        wires.extend(subcircuit.place(&mut instance));
+        parts.push(instance);
+        self.subcircuits.push(subcircuit);
+        views::parts::update_subcircuits(&self.subcircuits);
+        self.load(wires, parts);
+    }
+
+    /// Split the entities in the selected ones (updated by `unselect`) and the others.
+    fn split<T>(
+        entities: Vec<T>,
+        selected: &[usize],
+        unselect: impl Fn(&mut T),
+    ) -> (Vec<T>, Vec<T>) {
+        let mut split = (Vec::new(), Vec::new());
+        entities
+            .into_iter()
+            .enumerate()
+            .for_each(|(idx, mut entity)| {
+                if selected.contains(&idx) {
+                    unselect(&mut entity);
+                    split.0.push(entity);
+                } else {
+                    split.1.push(entity);
+                }
+            });
+        split
+    }
+
+    /// Ask the name of a new block. The name must be valid and not already used.
+    fn ask_subcircuit_name(&self) -> Option<String> {
+        let default = (1..)
+            .map(|idx| format!("bloc{}", idx))
+            .find(|name| self.subcircuits.iter().all(|s| s.name != *name))?;
+        let name = web_sys::window()?
+            .prompt_with_message_and_default("Nom du sous-circuit", &default)
+            .ok()??;
+        let name = name.trim().to_string();
+        if parts::Subcircuit::is_valid_name(&name)
+            && self.subcircuits.iter().all(|s| s.name != name)
+        {
+            Some(name)
+        } else {
+            error::show(Box::new(error::Sim::InvalidSubcircuitName(name)));
+            None
+        }
+    }
+
+    /// Edit the block of the selected instance. The schema is replaced by the content of the block
+    /// until `ascend` is called.
+    fn descend(&mut self) {
+        if self.parts.selected.len() != 1 {
+            return;
+        }
+        let name = match parts::Subcircuit::of(&self.parts.parts[self.parts.selected[0]]) {
+            Some(name) => name.to_string(),
+            None => return,
+        };
+        let (wires, parts) = match self.subcircuits.iter().find(|s| s.name == name) {
+            Some(subcircuit) => (subcircuit.wires.clone(), subcircuit.parts.clone()),
+            None => return,
+        };
+        self.unselect();
+        let level = (
+            name,
+            std::mem::take(&mut self.wires.wires),
+            std::mem::take(&mut self.parts.parts),
+        );
+        self.hierarchy.push(level);
+        self.load(wires, parts);
+    }
+
+    /// Go back to the schema above the block being edited. The block is saved and every instance
+    /// of it is rebuilt since its ports might have changed.
+    fn ascend(&mut self) {
+        let (name, wires, mut parts) = match self.hierarchy.pop() {
+            Some(level) => level,
+            None => return,
+        };
+        self.unselect();
+        let subcircuit = match self.subcircuits.iter_mut().find(|s| s.name == name) {
+            Some(subcircuit) => subcircuit,
+            None => return,
+        };
+        subcircuit.wires = std::mem::take(&mut self.wires.wires);
+        subcircuit.parts = std::mem::take(&mut self.parts.parts);
+        let subcircuit = subcircuit.clone();
+        let typ = subcircuit.typ();
+        let instances = parts
+            .iter_mut()
+            .chain(
+                self.hierarchy
+                    .iter_mut()
+                    .flat_map(|(_, _, parts)| parts.iter_mut()),
+            )
+            .chain(
+                self.subcircuits
+                    .iter_mut()
+                    .flat_map(|subcircuit| subcircuit.parts.iter_mut()),
+            )
+            .filter(|part| part.typ == typ);
+        for instance in instances {
+            if let Err(error) = subcircuit.refresh(instance) {
+                error::show(error);
+            }
+        }
+        parts
+            .iter_mut()
+            .for_each(|part| part.state = utils::State::None);
+        views::parts::update_subcircuits(&self.subcircuits);
+        self.load(wires, parts);
+    }
+
+    /// Go back to the root of the project (ie: before simulating or exporting it).
+    fn ascend_to_root(&mut self) {
+        while !self.hierarchy.is_empty() {
+            self.ascend();
+        }
+    }
+
+    /// Replace the wires and the parts of the schema. Nothing stays selected or copied.
+    fn load(&mut self, wires: Vec<wires::Wire>, parts: Vec<part::Part>) {
+        self.wires = wires::Wires::new();
+        self.wires.wires = wires;
+        self.parts = parts::Parts::new();
+        self.parts.parts = parts;
+        self.circuit_changed();
+        self.sources_dispatch();
+        self.update();
+    }
+
     pub fn unselect(&mut self) {
         self.mouse.action.set(mouse::Action::None);
         self.wires.unselect(&mut self.mouse);
@@ -147,7 +382,70 @@ impl Schema {
         self.update();
     }
 
+    /// Everything that was computed from the previous state of the circuit is not valid anymore
+    /// (like the results of an operating point analysis).
+    fn circuit_changed(&mut self) {
+        self.annotations = None;
+        self.diagnostics = Vec::new();
+    }
+
+    /// Show the diagnostics and keep them to outline their entities.
+    fn show_diagnostics(&mut self, diagnostics: Vec<error::Diagnostic>) {
+        error::show_diagnostics(&diagnostics);
+        self.diagnostics = diagnostics;
+        self.draw();
+    }
+
+    /// A message of the diagnostics was clicked, its entities are selected and the view is
+    /// zoomed on them.
+    pub fn diagnostic_dispatch(&mut self, idx: usize) {
+        let entities = match self.diagnostics.get(idx) {
+            Some(diagnostic) => diagnostic.entities.clone(),
+            None => return,
+        };
+        let _ = dom::select("#error__container").set_attribute("class", "hide");
+        self.unselect();
+        let mut corners = Vec::new();
+        let mut parts = Vec::new();
+        let mut wires = Vec::new();
+        entities.iter().for_each(|entity| match entity {
+            error::Entity::Part(idx) if *idx < self.parts.parts.len() => {
+                let layout = &self.parts.parts[*idx].layout;
+                corners.push(layout.origin);
+                corners.push(layout.origin + Point::new(layout.size.w, layout.size.h));
+                parts.push(*idx);
+            }
+            error::Entity::Wire(idx) if *idx < self.wires.wires.len() => {
+                corners.extend(self.wires.wires[*idx].layout.shape.points.iter().cloned());
+                wires.push(*idx);
+            }
+            _ => {}
+        });
+        self.parts.select_only(parts);
+        self.wires.select_only(wires);
+        if let Some(first) = corners.first() {
+            let (min, max) = corners.iter().fold((*first, *first), |(min, max), corner| {
+                (
+                    Point::new(min.x.min(corner.x), min.y.min(corner.y)),
+                    Point::new(max.x.max(corner.x), max.y.max(corner.y)),
+                )
+            });
+            self.scene
+                .zoom_to(min, Size::new(max.x - min.x, max.y - min.y));
+        }
+        self.draw();
+    }
+
     pub fn update(&mut self) {
+        if [
+            mouse::Action::MoveEntity,
+            mouse::Action::EditWire,
+            mouse::Action::DrawWire,
+        ]
+        .contains(&self.mouse.action)
+        {
+            self.circuit_changed();
+        }
         let keep_selected = self.do_keep_selected();
         self.parts.update(&mut self.mouse, keep_selected);
         self.wires
@@ -170,43 +468,309 @@ impl Schema {
         self.ctx.set_stroke_round();
         self.parts.iter().for_each(|part| part.draw(&self.ctx));
         self.wires.iter().for_each(|wire| wire.draw(&self.ctx));
+        self.wires.draw_junctions(&self.ctx, &self.parts);
+        if let Some(annotations) = &self.annotations {
+            annotations.draw(&self.ctx);
+        }
+        self.draw_diagnostics();
 
         self.ctx.translate(self.scene.offset); // - Point::new(0.5, 0.5));
-        self.ctx.scale(1.0 / self.scene.scale)
+        self.ctx.scale(1.0 / self.scene.scale);
+        // The path of the block being edited.
+        if !self.hierarchy.is_empty() {
+            let path = self
+                .hierarchy
+                .iter()
+                .map(|(name, _, _)| &name[..])
+                .collect::<Vec<&str>>()
+                .join(" / ");
+            self.ctx.set_fill_style("#CCCCCC");
+            self.ctx.fill_text_const(
+                &format!("Sous-circuit : {} (Ctrl+↑ pour remonter)", path),
+                Point::new(10.0, 20.0),
+                13.0,
+            );
+        }
+    }
+
+    /// Outline in red the parts and the wires pointed at by the diagnostics.
+    fn draw_diagnostics(&self) {
+        self.ctx.set_stroke_style_const(2.0, "#ff3f34");
+        self.diagnostics
+            .iter()
+            .flat_map(|diagnostic| diagnostic.entities.iter())
+            .for_each(|entity| match entity {
+                error::Entity::Part(idx) => {
+                    if let Some(part) = self.parts.parts.get(*idx) {
+                        part.layout.draw_bounding(&self.ctx);
+                    }
+                }
+                error::Entity::Wire(idx) => {
+                    if let Some(wire) = self.wires.wires.get(*idx) {
+                        self.ctx.stroke_points(&wire.layout.shape.points);
+                    }
+                }
+                error::Entity::Node(_) => {}
+            });
+    }
+
+    pub fn set_responder(&mut self, responder: Responder) {
+        self.responder = Some(responder);
     }
 
     /// This function take the circuit, convert it to a string that can be interpreted by ngspice
-    /// (with the `src/simulation/circuit.rs` mod) and finally send it to ngspice (via a nodejs
-    /// server). Ngspice will respond back and trigger an event (in `src/events.rs`).
+    /// (with the `src/simulation/circuit.rs` mod) and finally send it to the simulator (see
+    /// `sim::backend`). The output is received by `simulation_response`.
     pub fn to_spice(&mut self) {
-        let circuit =
-            match circuit::Circuit::new(self.wires.wires.clone(), self.parts.parts.clone()) {
-                Ok(circuit) => circuit,
-                Err(error) => {
-                    error::show_multiple(error);
-                    return;
-                }
-            };
-        let (spice_string, probes) = match circuit.to_string() {
+        self.ascend_to_root();
+        let mut circuit = match circuit::Circuit::new(
+            self.wires.wires.clone(),
+            self.parts.parts.clone(),
+            self.subcircuits.clone(),
+        ) {
             Ok(circuit) => circuit,
+            Err(diagnostics) => {
+                self.show_diagnostics(diagnostics);
+                return;
+            }
+        };
+        let mut diagnostics = std::mem::take(&mut circuit.diagnostics);
+        let settings = match sim::settings::Settings::parse(&view::read_settings()) {
+            Ok(settings) => settings,
             Err(error) => {
-                error::show(error);
+                diagnostics.push(error::Diagnostic::from(error));
+                self.show_diagnostics(diagnostics);
                 return;
             }
         };
-        self.probes = probes;
+        self.annotations = match settings.analysis {
+            sim::settings::Analysis::OperatingPoint => Some(circuit.annotations()),
+            _ => None,
+        };
+        let simulation = match circuit.to_string(&settings) {
+            Ok(simulation) => simulation,
+            Err(error) => {
+                diagnostics.push(error::Diagnostic::from(error));
+                self.show_diagnostics(diagnostics);
+                return;
+            }
+        };
+        // The warnings do not prevent the simulation.
+        if !diagnostics.is_empty() {
+            self.show_diagnostics(diagnostics);
+        }
 
-        clog!("{}", spice_string);
+        view::update_tables(&[]);
+        let labels = simulation
+            .runs
+            .iter()
+            .map(|run| {
+                run.label
+                    .clone()
+                    .unwrap_or_else(|| String::from("Simulation"))
+            })
+            .collect();
+        self.jobs.queue(labels, settings.timeout, now());
+        self.simulation = Some(simulation);
+        self.submit();
+    }
 
-        let mut event_data = web_sys::CustomEventInit::new();
-        event_data.detail(&JsValue::from_str(&spice_string));
-        let event =
-            web_sys::CustomEvent::new_with_event_init_dict("ngspice_call", &event_data).unwrap();
-        web_sys::window().unwrap().dispatch_event(&event).unwrap();
+    /// Stop the simulation: the request of the running run is cancelled and the next runs are
+    /// not sent. The output of the cancelled request is discarded.
+    pub fn cancel_simulation(&mut self) {
+        if let Some((backend, id)) = self.request.take() {
+            backend.cancel(id);
+        }
+        self.jobs.cancel(now());
+        view::update_jobs(&self.jobs, now());
     }
 
-    pub fn export(&self) {
-        let export = project::export::to_oregano(&self.wires.wires, &self.parts.parts);
+    /// Follow the running job: its progress is read from its backend and its request is
+    /// cancelled once it exceeds the timeout (see `events::add_events_schema`).
+    pub fn tick_jobs(&mut self) {
+        if !self.jobs.is_active() {
+            return;
+        }
+        let now = now();
+        if let Some((backend, id)) = &self.request {
+            if let Some(progress) = backend.progress(*id) {
+                self.jobs.report(*id, progress);
+            }
+        }
+        if let Some(id) = self.jobs.check_timeout(now) {
+            if let Some((backend, _)) = self.request.take() {
+                backend.cancel(id);
+            }
+            if let Some(timeout) = self.jobs.timeout() {
+                error::show(Box::new(error::Sim::BackendTimeout(timeout)));
+            }
+        }
+        view::update_jobs(&self.jobs, now);
+    }
+
+    /// Send the netlist of the current run to the backend of the simulation. A request still
+    /// running is cancelled, its output would be stale.
+    fn submit(&mut self) {
+        if let Some((backend, id)) = self.request.take() {
+            backend.cancel(id);
+        }
+        let simulation = match &self.simulation {
+            Some(simulation) => simulation,
+            None => return,
+        };
+        let netlist = match simulation.netlist() {
+            Some(netlist) => netlist,
+            None => return,
+        };
+        clog!("{}", netlist);
+        let backend = self.backends.get(&simulation.engine);
+        let id = backend.submit(netlist);
+        let output = backend.result(id);
+        self.request = Some((backend, id));
+        self.jobs.start(id, now());
+        view::update_jobs(&self.jobs, now());
+        // The output is received once the schema is not borrowed anymore (the responder
+        // borrows it).
+        if let Some(responder) = self.responder.clone() {
+            wasm_bindgen_futures::spawn_local(async move { responder(id, output.await) });
+        }
+    }
+
+    /// Handle the output of a request. The results of an operating point analysis are drawn on
+    /// the schema, every other analysis returns how the series must be plotted and the series
+    /// (layout, (x_label, y_labels, series)). When a property is swept, the next run is sent to
+    /// the simulator and the series are only returned once every run is done. The output of a
+    /// request that is not the one of the current run is discarded.
+    pub fn simulation_response(
+        &mut self,
+        id: backend::RequestId,
+        output: backend::Output,
+    ) -> Option<(plot::Layout, plot::Data)> {
+        match &self.request {
+            Some((_, request)) if *request == id => self.request = None,
+            _ => return None,
+        }
+        let error = output.as_ref().err().map(|error| error.to_string());
+        self.jobs.finish(id, error, now());
+        view::update_jobs(&self.jobs, now());
+        let output = match output {
+            Ok(output) => output,
+            Err(error) => {
+                error::show(error);
+                return None;
+            }
+        };
+        let output = &output[..];
+        if let Some(sim::Output::OperatingPoint) = self.simulation.as_ref().map(|s| &s.output) {
+            if let Some(annotations) = &mut self.annotations {
+                annotations.values = sim::op::OperatingPoint::parse(output);
+                if annotations.values.is_none() {
+                    error::show(Box::new(error::Sim::SpiceNoData));
+                }
+            }
+            self.draw();
+            return None;
+        }
+        let simulation = self.simulation.as_mut()?;
+        let (x_label, y_labels, series) = match &simulation.output {
+            sim::Output::Series | sim::Output::OperatingPoint => {
+                plot::parser::parse_spice_output(&simulation.probes, output)
+            }
+            sim::Output::Family(device, values) => {
+                let (x_label, y_labels, series) =
+                    plot::parser::parse_spice_output(&simulation.probes, output);
+                let (y_labels, series) =
+                    plot::parser::split_family(y_labels, series, device, values);
+                (x_label, y_labels, series)
+            }
+            sim::Output::Bode => plot::parser::parse_ac_output(&simulation.probes, output),
+            sim::Output::Noise(source) => plot::parser::parse_noise_output(source, output),
+            sim::Output::TransferFunction => {
+                match sim::tf::TransferFunction::parse(output) {
+                    Some(tf) => simulation.push_table(tf.table()),
+                    None => error::show(Box::new(error::Sim::SpiceNoData)),
+                }
+                (String::new(), Vec::new(), Vec::new())
+            }
+            sim::Output::PoleZero => match sim::pz::PoleZero::parse(output) {
+                Some(pz) => {
+                    simulation.push_table(pz.table());
+                    pz.series()
+                }
+                None => {
+                    error::show(Box::new(error::Sim::SpiceNoData));
+                    (String::new(), Vec::new(), Vec::new())
+                }
+            },
+        };
+        if let Err(error) = simulation.analyse_fourier(&y_labels, &series) {
+            error::show(error);
+        }
+        simulation.measure(&y_labels, &series);
+        let layout = match simulation.output {
+            sim::Output::Bode => plot::Layout::Bode,
+            sim::Output::Noise(_) => plot::Layout::LogLog,
+            sim::Output::PoleZero => plot::Layout::ComplexPlane,
+            _ => plot::Layout::Linear,
+        };
+        match simulation.push_results(x_label, y_labels, series) {
+            Some(data) => {
+                let histogram = simulation.histogram();
+                if let Ok(Some(histogram)) = &histogram {
+                    simulation.tables.push(histogram.table());
+                }
+                view::update_tables(&simulation.tables);
+                match histogram {
+                    Ok(Some(histogram)) => view::update_histogram(&histogram),
+                    Err(error) => error::show(error),
+                    _ => {}
+                }
+                // Some analyses only have tables of results.
+                if data.2.is_empty() {
+                    None
+                } else {
+                    Some((layout, data))
+                }
+            }
+            None => {
+                self.submit();
+                None
+            }
+        }
+    }
+
+    /// Update the lists of sources and probes that can be selected in the simulation settings.
+    pub fn sources_dispatch(&self) {
+        let sources = self
+            .parts
+            .iter()
+            .filter(|part| part.typ.starts_with("source."))
+            .filter_map(|part| part.device())
+            .collect::<Vec<String>>();
+        let probes = |typ: &str| {
+            self.parts
+                .iter()
+                .filter(|part| part.typ == typ)
+                .filter_map(|part| part.properties.get("name").ok())
+                .map(|property| property.value.to_string())
+                .collect::<Vec<String>>()
+        };
+        let voltmeters = probes("probe.voltmeter");
+        let mut all_probes = probes("probe.ampermeter");
+        all_probes.extend(voltmeters.iter().cloned());
+        all_probes.extend(probes("lumped.node"));
+        view::update_part_lists(&sources, &voltmeters, &all_probes);
+        view::update_sources(sources);
+    }
+
+    pub fn export(&mut self) {
+        self.ascend_to_root();
+        let export = project::export::to_oregano(
+            &self.wires.wires,
+            &self.parts.parts,
+            &self.subcircuits,
+            &view::read_settings(),
+        );
 
         web_sys::window()
             .unwrap()
@@ -226,10 +790,23 @@ impl Schema {
             .get_item("circuit")
             .unwrap()
             .unwrap();
-        match project::import::from_oregano(&input) {
-            Ok((wires, parts)) => {
+        match project::import::from_text(&input) {
+            Ok(((wires, parts, subcircuits, settings), warnings)) => {
+                self.hierarchy = Vec::new();
                 self.wires.wires = wires;
                 self.parts.parts = parts;
+                self.subcircuits = subcircuits;
+                views::parts::update_subcircuits(&self.subcircuits);
+                // The lists of sources and probes must contain the parts of the project before
+                // the settings can select them.
+                self.sources_dispatch();
+                if let Some(settings) = settings {
+                    view::write_settings(&settings);
+                    self.simulation_selector_dispatch();
+                }
+                if !warnings.is_empty() {
+                    self.show_diagnostics(warnings);
+                }
             }
             Err(error) => error::show(error),
         }
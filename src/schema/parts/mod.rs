@@ -1,11 +1,14 @@
 mod layout;
 pub mod part;
 mod spice;
+mod subcircuit;
+mod template;
 use crate::schema::{mouse, utils};
 use crate::{intrinsics::*, views};
 pub use layout::*;
 pub use part::*;
 pub use spice::*;
+pub use subcircuit::*;
 
 pub struct Parts {
     pub parts: Vec<Part>,
@@ -86,6 +89,19 @@ impl Parts {
         self.selected = Vec::new();
     }
 
+    /// Select only the given parts. The properties of the part are shown if there is only one.
+    pub fn select_only(&mut self, selected: Vec<usize>) {
+        self.parts.iter_mut().enumerate().for_each(|(idx, part)| {
+            part.state.set_selected(selected.contains(&idx));
+        });
+        self.selected = selected;
+        if self.selected.len() == 1 {
+            views::properties::update(&self.parts[self.selected[0]]);
+        } else {
+            views::properties::empty();
+        }
+    }
+
     pub fn delete(&mut self) {
         self.parts = self
             .parts
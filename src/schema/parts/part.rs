@@ -16,6 +16,9 @@ pub struct Part {
     pub properties: properties::Properties,
     colliding: utils::Colliding,
     pub spice: parts::Spice,
+    /// The models of the libraries used by the SPICE pattern of the part.
+    #[serde(default)]
+    pub models: Vec<String>,
 }
 
 impl Part {
@@ -35,6 +38,7 @@ impl Part {
             properties,
             colliding: utils::Colliding::None,
             spice,
+            models: Vec::new(),
         }
     }
 
@@ -49,6 +53,11 @@ impl Part {
     pub fn draw(&self, ctx: &ctx::Ctx) {
         ctx.set_stroke_style(1.0, Self::color());
         self.layout.draw(ctx);
+        // The instances of a block show the name of their block.
+        if let Some(name) = parts::Subcircuit::of(self) {
+            ctx.set_fill_style(Self::color());
+            ctx.fill_text_const(name, self.layout.origin + Point::new(14.0, 14.0), 11.0);
+        }
         if self.state.is_selected() {
             ctx.set_line_dash_const(vec![2.5, 1.25]);
             ctx.set_stroke_style_const(0.5, "#CCCCCC");
@@ -97,21 +106,44 @@ impl Part {
             .iter()
             .map(|conn| match &conn.connected_to {
                 Some(name) => Ok(name.clone()),
-                _ => Err(Box::new(error::Sim::MissingConnectionPart(0))),
+                _ => Err(Box::new(error::Sim::MissingConnectionPart)),
             })
             .collect::<Result<Vec<String>, Box<error::Sim>>>()
     }
 
+    /// The name of the part in the netlist (for example `VVdc` for a voltage source named `Vdc`).
+    /// Parts that are not in the netlist (like the ground) have no device name.
+    pub fn device(&self) -> Option<String> {
+        self.spice.device(&self.properties)
+    }
+
     pub fn to_spice(&self) -> Result<String, error::Error> {
-        self.spice.to_spice(&self.properties, self.connectors()?)
+        self.spice
+            .to_spice(&self.typ, &self.properties, self.connectors()?)
+    }
+
+    /// Check that the SPICE pattern of the part is valid for its properties and its connectors.
+    pub fn validate(&self) -> Result<(), error::Error> {
+        self.spice
+            .validate(&self.typ, &self.properties, self.layout.connectors.len())
     }
 }
 
-impl std::str::FromStr for Part {
-    type Err = error::Error;
-    fn from_str(s: &str) -> Result<Part, error::Error> {
+impl Part {
+    /// Read a part saved by `export::to_oregano`. The instances of blocks are created from the
+    /// blocks of the project, the other parts from the library.
+    pub fn from_line(s: &str, subcircuits: &[parts::Subcircuit]) -> Result<Part, error::Error> {
         let data = s.split(",").collect::<Vec<&str>>();
-        let mut part = PARTS.get(data[0])?;
+        let mut part = match data[0].strip_prefix("subcircuit.") {
+            Some(name) => match subcircuits
+                .iter()
+                .find(|subcircuit| subcircuit.name == name)
+            {
+                Some(subcircuit) => subcircuit.instance(""),
+                None => return Err(Box::new(error::Import::PartNotFound)),
+            },
+            None => PARTS.get(data[0])?,
+        };
         part.layout.update_from_str(data[1])?;
         let properties = data[2].parse::<properties::Properties>()?;
         part.properties = properties;
@@ -119,6 +151,13 @@ impl std::str::FromStr for Part {
     }
 }
 
+impl std::str::FromStr for Part {
+    type Err = error::Error;
+    fn from_str(s: &str) -> Result<Part, error::Error> {
+        Part::from_line(s, &[])
+    }
+}
+
 impl fmt::Display for Part {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.typ)
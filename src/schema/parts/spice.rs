@@ -1,77 +1,60 @@
 use crate::error;
+use crate::schema::parts::template;
 use crate::schema::properties;
 use serde::Deserialize;
 
+/// The pattern of the line of a part in the netlist (see `template::Template` for its syntax). The
+/// pattern is parsed once, a syntax error is reported each time the part is used.
 #[derive(Clone, Deserialize)]
-pub struct Spice(String);
+#[serde(from = "String")]
+pub struct Spice {
+    template: Result<template::Template, template::SyntaxError>,
+}
 
 impl Spice {
-    fn replace_connectors(&self, mut pattern: String, connectors: &Vec<String>) -> String {
-        connectors.iter().enumerate().for_each(|(idx, conn)| {
-            pattern = pattern.replace(&format!("{{conn:{}}}", idx), &conn);
-        });
-        pattern
-    }
-
-    fn replace_props(
-        &self,
-        pattern: String,
-        properties: &properties::Properties,
-    ) -> Result<String, error::Error> {
-        pattern
-            .split("{prop")
-            .fold(Ok(String::new()), |acc, split| match acc {
-                Ok(curr) => match (&split[0..1], split.find("}")) {
-                    (sep @ (":" | "?"), Some(end)) => {
-                        let key = &split[1..end];
-                        match (sep, properties.get(key)) {
-                            (_, Ok(prop)) => {
-                                Ok(format!("{}{}{}", curr, prop.value, &split[end + 1..]))
-                            }
-                            ("?", _) => Ok(format!("{}{}", curr, &split[end + 1..].to_string())),
-                            (_, Err(error)) => Err(error),
-                        }
-                    }
-                    _ => Ok(format!("{}{}", curr, split)),
-                },
-                Err(error) => Err(error),
-            })
+    fn template(&self, typ: &str) -> Result<&template::Template, error::Template> {
+        self.template.as_ref().map_err(|(column, syntax)| {
+            error::Template::Syntax(typ.to_string(), *column, syntax.clone())
+        })
     }
 
-    fn replace_conditional(&self, pattern: String, properties: &properties::Properties) -> String {
-        pattern.split("{?").fold(String::new(), |acc, split| {
-            match (split.find(":"), split.find("}")) {
-                (Some(sep), Some(end)) => {
-                    if properties.properties.contains_key(&split[0..sep]) {
-                        format!("{}{}{}", acc, &split[sep + 1..end], &split[end + 1..])
-                    } else {
-                        acc
-                    }
-                }
-                _ => format!("{}{}", acc, split),
-            }
-        })
+    /// The first element of the pattern is the name of the device.
+    pub fn device(&self, properties: &properties::Properties) -> Option<String> {
+        self.template.as_ref().ok()?.device(properties)
     }
 
     pub fn to_spice(
         &self,
+        typ: &str,
         properties: &properties::Properties,
         connectors: Vec<String>,
     ) -> Result<String, error::Error> {
-        let mut pattern = self.0.clone();
-        if pattern.len() == 0 {
-            return Ok(pattern);
+        Ok(self.template(typ)?.render(typ, properties, &connectors)?)
+    }
+
+    /// Check the syntax of the pattern and that it only uses the properties and the connectors of
+    /// its part.
+    pub fn validate(
+        &self,
+        typ: &str,
+        properties: &properties::Properties,
+        connectors: usize,
+    ) -> Result<(), error::Error> {
+        Ok(self.template(typ)?.check(typ, properties, connectors)?)
+    }
+}
+
+impl From<String> for Spice {
+    fn from(pattern: String) -> Self {
+        Spice {
+            template: template::Template::parse(&pattern),
         }
-        pattern = self.replace_connectors(pattern, &connectors);
-        pattern = self.replace_props(pattern, &properties)?;
-        pattern = self.replace_conditional(pattern, &properties);
-        Ok(pattern)
     }
 }
 
 impl std::str::FromStr for Spice {
     type Err = error::Error;
     fn from_str(s: &str) -> Result<Self, error::Error> {
-        Ok(Spice(s.to_string()))
+        Ok(Spice::from(s.to_string()))
     }
 }
@@ -0,0 +1,423 @@
+use crate::error;
+use crate::intrinsics::*;
+use crate::schema::{parts, properties, shape, wires};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The length of the pins of the symbol of a block.
+const PIN: f64 = 10.0;
+/// The distance between two pins on the same side of the symbol.
+const PITCH: f64 = 20.0;
+/// The width of the body of the symbol.
+const WIDTH: f64 = 60.0;
+/// The distance between the lanes of the wires that connect the ports to an instance.
+const LANE: f64 = 20.0;
+
+/// The name of a port and the position of the connector of its named node in the block.
+type Port = (String, Point);
+
+/// A block of parts and wires that is placed as a single part (an instance). The named nodes
+/// (`lumped.node`) of the block are its ports: each port is a pin of the instances and a node of
+/// the `.subckt` definition. The instances only keep the name of their block, editing the block
+/// changes every instance.
+#[derive(Clone)]
+pub struct Subcircuit {
+    pub name: String,
+    pub wires: Vec<wires::Wire>,
+    pub parts: Vec<parts::Part>,
+}
+
+impl Subcircuit {
+    pub fn new(name: &str, wires: Vec<wires::Wire>, parts: Vec<parts::Part>) -> Self {
+        Self {
+            name: name.to_string(),
+            wires,
+            parts,
+        }
+    }
+
+    /// The type of the instances of the block (ie: `subcircuit.filter`).
+    pub fn typ(&self) -> String {
+        format!("subcircuit.{}", self.name)
+    }
+
+    /// The name of the block of a part if the part is an instance.
+    pub fn of(part: &parts::Part) -> Option<&str> {
+        part.typ.strip_prefix("subcircuit.")
+    }
+
+    /// The name of a block is used in the netlist and in the type of its instances, it must be a
+    /// single word.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// The ports of the block. The ports on the left half of the block are the pins on the left of the symbol, the others are on
+    /// its right. Each side is sorted from top to bottom.
+    fn sides(&self) -> (Vec<Port>, Vec<Port>) {
+        let mut ports: Vec<Port> = Vec::new();
+        self.parts
+            .iter()
+            .filter(|part| part.typ == "lumped.node")
+            .for_each(|part| {
+                let name = match part.properties.get("name") {
+                    Ok(property) => property.value.to_string(),
+                    Err(_) => return,
+                };
+                // Many named nodes can be the same port.
+                if !ports.iter().any(|(port, _)| *port == name) {
+                    if let Some(connector) = part.layout.connectors.first() {
+                        ports.push((name, part.layout.origin + connector.origin));
+                    }
+                }
+            });
+        let (min, max) = ports
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(min, max), (_, position)| {
+                (min.min(position.x), max.max(position.x))
+            });
+        let center = (min + max) / 2.0;
+        let (mut left, mut right): (Vec<Port>, Vec<Port>) = ports
+            .into_iter()
+            .partition(|(_, position)| position.x < center);
+        for side in [&mut left, &mut right] {
+            side.sort_by(|(_, a), (_, b)| {
+                a.y.partial_cmp(&b.y).unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+        (left, right)
+    }
+
+    /// The ports in the order of the pins of the instances (the left side, then the right side).
+    pub fn ports(&self) -> Vec<Port> {
+        let (mut left, right) = self.sides();
+        left.extend(right);
+        left
+    }
+
+    /// The symbol of the instances: a rectangle with the pins of the ports on its sides.
+    pub fn layout(&self) -> parts::Layout {
+        let (left, right) = self.sides();
+        let height = left.len().max(right.len()).max(1) as f64 * PITCH;
+        let mut polys = vec![vec![
+            Point::new(PIN, 0.0),
+            Point::new(PIN + WIDTH, 0.0),
+            Point::new(PIN + WIDTH, height),
+            Point::new(PIN, height),
+            Point::new(PIN, 0.0),
+        ]];
+        let mut connectors = Vec::new();
+        let sides = [(left, 0.0, PIN), (right, 2.0 * PIN + WIDTH, PIN + WIDTH)];
+        for (ports, end, body) in sides.iter() {
+            (0..ports.len()).for_each(|idx| {
+                let y = PITCH / 2.0 + idx as f64 * PITCH;
+                polys.push(vec![Point::new(*end, y), Point::new(*body, y)]);
+                connectors.push(Point::new(*end, y));
+            });
+        }
+        let mut shape = shape::Shape::new(polys, Vec::new());
+        // A symbol without pins on its left starts at its body.
+        let offset = -shape.bounding().0;
+        shape.translate(offset);
+        shape.size();
+        let connectors = connectors
+            .into_iter()
+            .map(|connector| connector + offset)
+            .collect::<Vec<Point>>();
+        parts::Layout::new(Point::new(0.0, 0.0), shape, connectors)
+    }
+
+    /// A new instance of the block. Its line in the netlist calls the `.subckt` definition with
+    /// the nodes connected to its pins, in the order of the ports.
+    pub fn instance(&self, name: &str) -> parts::Part {
+        let spice = parts::Spice::from(format!("X{{prop:name}} {{conns}} {}", self.name));
+        let mut values = HashMap::new();
+        values.insert(
+            "name".to_string(),
+            properties::Property::new(properties::Value::String(name.to_string()), true, 0, false),
+        );
+        let mut part = parts::Part::new(
+            self.typ(),
+            self.layout(),
+            properties::Properties::new(values),
+            spice,
+        );
+        part.name = Some(self.name.clone());
+        part
+    }
+
+    /// Place an instance that replaces the parts of the block in a schema and return the wires
+    /// that connect its pins to the ports (the wires of the schema that reached the block end at
+    /// the ports). The instance is placed below the ports. Each port goes down (or up when another
+    /// port is below it) to its own row, then to its own column beside the instance and then to
+    /// its pin, so the wires of two ports never overlap. A port with other ports above and below
+    /// it goes straight to its column.
+    pub fn place(&self, instance: &mut parts::Part) -> Vec<wires::Wire> {
+        let (left, right) = self.sides();
+        let ports = left.iter().chain(right.iter()).collect::<Vec<&Port>>();
+        let (min, max) = ports.iter().fold(
+            (
+                Point::new(f64::MAX, f64::MAX),
+                Point::new(f64::MIN, f64::MIN),
+            ),
+            |(min, max), (_, port)| {
+                (
+                    Point::new(min.x.min(port.x), min.y.min(port.y)),
+                    Point::new(max.x.max(port.x), max.y.max(port.y)),
+                )
+            },
+        );
+        let rows = ports.len() as f64 + 1.0;
+        instance.layout.origin = Point::new(min.x, max.y + rows * LANE);
+        instance.layout.snap_to_grid();
+        let pins = instance
+            .layout
+            .connectors
+            .iter()
+            .map(|pin| instance.layout.origin + pin.origin)
+            .collect::<Vec<Point>>();
+        // The columns are on the outer side of the pins and never on a port.
+        let mut columns = Vec::new();
+        for (side, direction) in [(0..left.len(), -1.0), (left.len()..pins.len(), 1.0)] {
+            let mut x = pins.get(side.start).map_or(0.0, |pin| pin.x);
+            for _ in side {
+                x += direction * LANE;
+                while ports.iter().any(|(_, port)| port.x == x) {
+                    x += direction * LANE;
+                }
+                columns.push(x);
+            }
+        }
+        ports
+            .iter()
+            .zip(pins.iter().zip(columns))
+            .enumerate()
+            .map(|(idx, ((_, port), (pin, column)))| {
+                let is_below = |other: &Point| other.x == port.x && other.y > port.y;
+                let is_above = |other: &Point| other.x == port.x && other.y < port.y;
+                let lane = idx as f64 + 1.0;
+                let row = if !ports.iter().any(|(_, other)| is_below(other)) {
+                    Some(max.y + lane * LANE)
+                } else if !ports.iter().any(|(_, other)| is_above(other)) {
+                    Some(min.y - lane * LANE)
+                } else {
+                    None
+                };
+                let mut points = vec![*port];
+                if let Some(row) = row {
+                    points.push(Point::new(port.x, row));
+                    points.push(Point::new(column, row));
+                } else {
+                    points.push(Point::new(column, port.y));
+                }
+                points.push(Point::new(column, pin.y));
+                points.push(*pin);
+                points.into_iter().collect::<wires::Wire>()
+            })
+            .collect()
+    }
+
+    /// Rebuild an instance after its block was edited (its ports might have changed). The
+    /// position, the orientation and the properties of the instance are kept.
+    pub fn refresh(&self, instance: &mut parts::Part) -> Result<(), error::Error> {
+        let mut refreshed = self.instance("");
+        refreshed
+            .layout
+            .update_from_str(&format!("{:?}", instance.layout))?;
+        refreshed.properties = instance.properties.clone();
+        *instance = refreshed;
+        Ok(())
+    }
+
+    /// Whether the block instantiates the block `name`, directly or through its own blocks.
+    pub fn contains(&self, name: &str, subcircuits: &[Subcircuit]) -> bool {
+        self.parts.iter().filter_map(Subcircuit::of).any(|block| {
+            block == name
+                || matches!(
+                    subcircuits.iter().find(|subcircuit| subcircuit.name == block),
+                    Some(subcircuit) if subcircuit.contains(name, subcircuits)
+                )
+        })
+    }
+
+    /// Read a block saved by `export::to_oregano`. The instances of the block must be instances
+    /// of the blocks that were already read.
+    /// The format is the following one: `name#wire&wire&#part&part&`
+    pub fn from_line(s: &str, subcircuits: &[Subcircuit]) -> Result<Self, error::Error> {
+        let data = s.split('#').collect::<Vec<&str>>();
+        if data.len() != 3 {
+            return Err(Box::new(error::Import::MissingToken));
+        }
+        let wires = data[1]
+            .split('&')
+            .filter(|wire| !wire.is_empty())
+            .map(|wire| wire.parse::<wires::Wire>())
+            .collect::<Result<Vec<wires::Wire>, error::Error>>()?;
+        let parts = data[2]
+            .split('&')
+            .filter(|part| !part.is_empty())
+            .map(|part| parts::Part::from_line(part, subcircuits))
+            .collect::<Result<Vec<parts::Part>, error::Error>>()?;
+        Ok(Self::new(data[0], wires, parts))
+    }
+}
+
+impl fmt::Debug for Subcircuit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#", self.name)?;
+        self.wires
+            .iter()
+            .try_for_each(|wire| write!(f, "{:?}&", wire))?;
+        write!(f, "#")?;
+        self.parts
+            .iter()
+            .try_for_each(|part| write!(f, "{:?}&", part))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{circuit, settings};
+    use crate::PARTS;
+
+    /// A part of the library with its name, placed so its first connector is at `at`.
+    fn part(typ: &str, name: &str, at: Point) -> parts::Part {
+        let mut part = PARTS.get(typ).unwrap();
+        if let Some(property) = part.properties.properties.get_mut("name") {
+            property.value = properties::Value::from(name);
+        }
+        part.layout.origin = at - part.layout.connectors[0].origin;
+        part
+    }
+
+    fn pins(part: &parts::Part) -> Vec<Point> {
+        part.layout
+            .connectors
+            .iter()
+            .map(|connector| part.layout.origin + connector.origin)
+            .collect()
+    }
+
+    fn wire(points: &[Point]) -> wires::Wire {
+        points.iter().cloned().collect()
+    }
+
+    /// The saved form of the wires and of the parts of a block without the properties, which are
+    /// not saved in a fixed order, but with the names of the parts.
+    fn saved(subcircuit: &Subcircuit) -> (Vec<String>, Vec<String>) {
+        let wires = subcircuit
+            .wires
+            .iter()
+            .map(|wire| format!("{:?}", wire))
+            .collect();
+        let parts = subcircuit
+            .parts
+            .iter()
+            .map(|part| {
+                let name = part.properties.get("name").unwrap().value.to_string();
+                format!("{},{:?},{}", part.typ, part.layout, name)
+            })
+            .collect();
+        (wires, parts)
+    }
+
+    /// A resistor between the ports `a` and `b`, the ports are at the same x so a wire going
+    /// straight down from `a` would reach `b`.
+    fn block() -> Subcircuit {
+        let (a, b) = (Point::new(0.0, 0.0), Point::new(0.0, 40.0));
+        let resistor = part("lumped.resistor", "1", Point::new(100.0, 0.0));
+        let ends = pins(&resistor);
+        Subcircuit::new(
+            "divider",
+            vec![
+                wire(&[a, ends[0]]),
+                wire(&[b, Point::new(ends[1].x, b.y), ends[1]]),
+            ],
+            vec![
+                part("lumped.node", "b", b),
+                resistor,
+                part("lumped.node", "a", a),
+                part("lumped.node", "b", b),
+            ],
+        )
+    }
+
+    #[test]
+    fn ports() {
+        let subcircuit = Subcircuit::new(
+            "ports",
+            Vec::new(),
+            vec![
+                part("lumped.node", "out", Point::new(100.0, 20.0)),
+                part("lumped.node", "in2", Point::new(0.0, 40.0)),
+                part("lumped.node", "in1", Point::new(0.0, 0.0)),
+                part("lumped.node", "in1", Point::new(0.0, 60.0)),
+            ],
+        );
+        let names = subcircuit
+            .ports()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<String>>();
+        assert_eq!(names, vec!["in1", "in2", "out"]);
+        assert_eq!(subcircuit.layout().connectors.len(), 3);
+    }
+
+    #[test]
+    fn line_round_trip() {
+        let subcircuit = block();
+        let line = format!("{:?}", subcircuit);
+        let read = Subcircuit::from_line(&line, &[]).unwrap();
+        assert_eq!(read.name, "divider");
+        assert_eq!(saved(&read), saved(&subcircuit));
+        // An instance of a block is read with the blocks read before it.
+        let mut parent = Subcircuit::new("parent", Vec::new(), vec![subcircuit.instance("d1")]);
+        parent.parts[0].layout.origin = Point::new(30.0, 50.0);
+        let line = format!("{:?}", parent);
+        assert!(Subcircuit::from_line(&line, &[]).is_err());
+        let read = Subcircuit::from_line(&line, &[subcircuit]).unwrap();
+        assert_eq!(read.parts[0].typ, "subcircuit.divider");
+        assert_eq!(saved(&read), saved(&parent));
+    }
+
+    #[test]
+    fn collapsed_ports_stay_distinct() {
+        let subcircuit = block();
+        // The rest of the schema: the ground is wired to `b` and a named node to `a`.
+        let mut wires = vec![
+            wire(&[Point::new(-60.0, 0.0), Point::new(0.0, 0.0)]),
+            wire(&[Point::new(-60.0, 40.0), Point::new(0.0, 40.0)]),
+        ];
+        let mut parts = vec![
+            part("lumped.node", "out", Point::new(-60.0, 0.0)),
+            part("lumped.ground", "0", Point::new(-60.0, 40.0)),
+        ];
+        let mut instance = subcircuit.instance("divider1");
+        wires.extend(subcircuit.place(&mut instance));
+        parts.push(instance);
+        let circuit = circuit::Circuit::new(wires, parts, vec![subcircuit]).unwrap();
+        let simulation = circuit
+            .to_string(&settings::Settings {
+                analysis: settings::Analysis::OperatingPoint,
+                temperature: crate::sim::temperature::Settings {
+                    temperatures: vec![27.0],
+                },
+                montecarlo: None,
+                engine: crate::sim::Engine::Native,
+                timeout: None,
+            })
+            .unwrap();
+        let netlist = &simulation.runs[0].netlist;
+        let instance = netlist
+            .lines()
+            .find(|line| line.starts_with("Xdivider1"))
+            .unwrap()
+            .split_whitespace()
+            .collect::<Vec<&str>>();
+        assert_eq!(instance.len(), 4);
+        assert_ne!(instance[1], instance[2]);
+        assert_eq!(instance[2], "0");
+        assert!(netlist.contains(".subckt divider a b\n"));
+    }
+}
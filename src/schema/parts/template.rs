@@ -0,0 +1,632 @@
+use crate::schema::properties;
+use crate::{error, unit};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Symbol {
+    Open,
+    Close,
+    Pipe,
+    Char(char),
+}
+
+/// A symbol of a template with its column (starting at 1).
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Token {
+    symbol: Symbol,
+    column: usize,
+}
+
+/// A syntax error with the column where it was found.
+pub type SyntaxError = (usize, error::Syntax);
+
+fn tokenize(pattern: &str) -> Result<Vec<Token>, SyntaxError> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars().enumerate();
+    while let Some((idx, c)) = chars.next() {
+        let column = idx + 1;
+        let symbol = match c {
+            '{' => Symbol::Open,
+            '}' => Symbol::Close,
+            '|' => Symbol::Pipe,
+            '\\' => match chars.next() {
+                Some((_, escaped @ ('{' | '}' | '|' | '\\'))) => Symbol::Char(escaped),
+                Some((_, escaped)) => return Err((column, error::Syntax::UnknownEscape(escaped))),
+                None => return Err((column, error::Syntax::TrailingEscape)),
+            },
+            c => Symbol::Char(c),
+        };
+        tokens.push(Token { symbol, column });
+    }
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    /// The value with a SPICE suffix (`10k`, `1Meg`, `4.7u`).
+    Spice,
+    /// The value without prefix (`10000`).
+    Si,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Condition {
+    Set(String),
+    Unset(String),
+    Equals(String, String),
+    Differs(String, String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Property {
+        key: String,
+        is_optional: bool,
+        format: Format,
+        default: Option<Template>,
+        column: usize,
+    },
+    Connector {
+        idx: usize,
+        column: usize,
+    },
+    Connectors,
+    Conditional {
+        condition: Condition,
+        then: Template,
+        otherwise: Template,
+    },
+}
+
+/// The SPICE template of a part. A template is the line of a part in the netlist where the
+/// tags are replaced by the properties and the nodes of the part:
+///
+/// - `{prop:key}` is the value of a property, `{prop?key}` is empty if the property is missing,
+///   `{prop:key|default}` is `default` if the property is missing or empty. The value is
+///   formatted with `@spice` (the default, ie: `10k`) or `@si` (ie: `10000`):
+///   `{prop:value@si|0}`.
+/// - `{conn:0}` is the node of the first connector, `{conns}` is the node of every connector
+///   separated by spaces.
+/// - `{?key:text}` is `text` if the property is set (not missing and not empty), `{?!key:text}`
+///   if it is not. `{?key=value:text}` and `{?key!=value:text}` compare the value of the
+///   property. The text is a template and can have an alternative: `{?key:text|otherwise}`.
+///
+/// The characters `{`, `}`, `|` and `\` are escaped by a `\`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template(Vec<Node>);
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// The column after the last character, where the unclosed tags are reported.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<Symbol> {
+        self.tokens.get(self.position).map(|token| token.symbol)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |token| token.column)
+    }
+
+    fn error<T>(&self, syntax: error::Syntax) -> Result<T, SyntaxError> {
+        Err((self.column(), syntax))
+    }
+
+    /// Consume a character if it is the next one.
+    fn accept(&mut self, c: char) -> bool {
+        if self.peek() == Some(Symbol::Char(c)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: Symbol, expected: &'static str) -> Result<(), SyntaxError> {
+        if self.peek() == Some(symbol) {
+            self.position += 1;
+            Ok(())
+        } else {
+            self.unexpected(expected)
+        }
+    }
+
+    /// The next symbol is not the `expected` one, a tag that reaches the end is not closed.
+    fn unexpected<T>(&self, expected: &'static str) -> Result<T, SyntaxError> {
+        match self.peek() {
+            None => self.error(error::Syntax::Unclosed),
+            _ => self.error(error::Syntax::Expected(expected)),
+        }
+    }
+
+    /// The characters of a name (a tag or the key of a property).
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(Symbol::Char(c)) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                word.push(c);
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        word
+    }
+
+    fn key(&mut self) -> Result<String, SyntaxError> {
+        match self.word() {
+            key if key.is_empty() => self.error(error::Syntax::MissingKey),
+            key => Ok(key),
+        }
+    }
+
+    /// The nodes until the end of the template. A nested template (in a tag) stops before the `}`
+    /// or the `|` that ends it, a `|` is a character anywhere else.
+    fn template(&mut self, is_nested: bool) -> Result<Template, SyntaxError> {
+        let mut nodes: Vec<Node> = Vec::new();
+        while let Some(symbol) = self.peek() {
+            let c = match symbol {
+                Symbol::Close | Symbol::Pipe if is_nested => break,
+                Symbol::Close => return self.error(error::Syntax::UnexpectedClose),
+                Symbol::Open => {
+                    nodes.push(self.tag()?);
+                    continue;
+                }
+                Symbol::Pipe => '|',
+                Symbol::Char(c) => c,
+            };
+            self.position += 1;
+            match nodes.last_mut() {
+                Some(Node::Text(text)) => text.push(c),
+                _ => nodes.push(Node::Text(c.to_string())),
+            }
+        }
+        Ok(Template(nodes))
+    }
+
+    fn tag(&mut self) -> Result<Node, SyntaxError> {
+        let open = self.column();
+        self.expect(Symbol::Open, "{")?;
+        // A tag that is never closed is reported at its `{`.
+        self.tag_body(open)
+            .map_err(|(column, syntax)| match syntax {
+                error::Syntax::Unclosed if column == self.end => (open, syntax),
+                syntax => (column, syntax),
+            })
+    }
+
+    fn tag_body(&mut self, open: usize) -> Result<Node, SyntaxError> {
+        if self.accept('?') {
+            return self.conditional();
+        }
+        let column = self.column();
+        let node = match &self.word()[..] {
+            "prop" => self.property(open)?,
+            "conn" => {
+                self.expect(Symbol::Char(':'), ":")?;
+                let column = self.column();
+                let idx = self.word();
+                match idx.parse::<usize>() {
+                    Ok(idx) => Node::Connector { idx, column: open },
+                    Err(_) => return Err((column, error::Syntax::InvalidConnector(idx))),
+                }
+            }
+            "conns" => Node::Connectors,
+            tag => return Err((column, error::Syntax::UnknownTag(tag.to_string()))),
+        };
+        self.expect(Symbol::Close, "}")?;
+        Ok(node)
+    }
+
+    fn property(&mut self, column: usize) -> Result<Node, SyntaxError> {
+        let is_optional = if self.accept(':') {
+            false
+        } else if self.accept('?') {
+            true
+        } else {
+            return self.unexpected(":");
+        };
+        let key = self.key()?;
+        let format = if self.accept('@') {
+            let column = self.column();
+            match &self.word()[..] {
+                "spice" => Format::Spice,
+                "si" => Format::Si,
+                format => return Err((column, error::Syntax::UnknownFormat(format.to_string()))),
+            }
+        } else {
+            Format::Spice
+        };
+        let default = if self.peek() == Some(Symbol::Pipe) {
+            self.position += 1;
+            Some(self.template(true)?)
+        } else {
+            None
+        };
+        Ok(Node::Property {
+            key,
+            is_optional,
+            format,
+            default,
+            column,
+        })
+    }
+
+    fn conditional(&mut self) -> Result<Node, SyntaxError> {
+        let is_negated = self.accept('!');
+        let key = self.key()?;
+        let condition = if is_negated {
+            Condition::Unset(key)
+        } else if self.accept('=') {
+            Condition::Equals(key, self.value())
+        } else if self.accept('!') {
+            self.expect(Symbol::Char('='), "=")?;
+            Condition::Differs(key, self.value())
+        } else {
+            Condition::Set(key)
+        };
+        self.expect(Symbol::Char(':'), ":")?;
+        let then = self.template(true)?;
+        let otherwise = if self.peek() == Some(Symbol::Pipe) {
+            self.position += 1;
+            self.template(true)?
+        } else {
+            Template(Vec::new())
+        };
+        self.expect(Symbol::Close, "}")?;
+        Ok(Node::Conditional {
+            condition,
+            then,
+            otherwise,
+        })
+    }
+
+    /// The value compared by a condition, it ends at the `:` of the condition.
+    fn value(&mut self) -> String {
+        let mut value = String::new();
+        while let Some(Symbol::Char(c)) = self.peek() {
+            if c == ':' {
+                break;
+            }
+            value.push(c);
+            self.position += 1;
+        }
+        value
+    }
+}
+
+impl Template {
+    pub fn parse(pattern: &str) -> Result<Self, SyntaxError> {
+        let tokens = tokenize(pattern)?;
+        let mut parser = Parser {
+            end: pattern.chars().count() + 1,
+            tokens,
+            position: 0,
+        };
+        parser.template(false)
+    }
+
+    /// The value of a property, a missing property and an empty value are not set.
+    fn value<'a>(
+        properties: &'a properties::Properties,
+        key: &str,
+    ) -> Option<&'a properties::Value> {
+        properties
+            .properties
+            .get(key)
+            .map(|property| &property.value)
+            .filter(|value| !value.to_string().is_empty())
+    }
+
+    fn format(
+        value: &properties::Value,
+        format: Format,
+        typ: &str,
+        key: &str,
+        column: usize,
+    ) -> Result<String, error::Template> {
+        // A sweep is replaced by each of its values before generating the netlist, the first value
+        // is used when it is not.
+        let value = match value.sweep_values() {
+            Some(values) if !values.is_empty() => values[0].clone(),
+            _ => value.clone(),
+        };
+        match (format, &value) {
+            // The deci and centi prefixes are not SPICE suffixes.
+            (
+                Format::Spice,
+                properties::Value::Unit(_, _, unit::Prefix::Deci | unit::Prefix::Centi, _),
+            )
+            | (Format::Si, _) => match value.as_f64() {
+                Some(number) => Ok(properties::Value::F64(number).to_string()),
+                None => Err(error::Template::NotANumber(
+                    typ.to_string(),
+                    column,
+                    key.to_string(),
+                )),
+            },
+            (Format::Spice, _) => Ok(value.to_string()),
+        }
+    }
+
+    fn is_true(condition: &Condition, properties: &properties::Properties) -> bool {
+        let text = |key: &str| Self::value(properties, key).map(|value| value.to_string());
+        match condition {
+            Condition::Set(key) => text(key).is_some(),
+            Condition::Unset(key) => text(key).is_none(),
+            Condition::Equals(key, value) => text(key).unwrap_or_default() == *value,
+            Condition::Differs(key, value) => text(key).unwrap_or_default() != *value,
+        }
+    }
+
+    /// Replace the tags of the template. `typ` is the type of the part, it is only used by the
+    /// errors.
+    pub fn render(
+        &self,
+        typ: &str,
+        properties: &properties::Properties,
+        connectors: &[String],
+    ) -> Result<String, error::Template> {
+        let mut output = String::new();
+        for node in self.0.iter() {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Property {
+                    key,
+                    is_optional,
+                    format,
+                    default,
+                    column,
+                } => match (Self::value(properties, key), default) {
+                    (Some(value), _) => {
+                        output.push_str(&Self::format(value, *format, typ, key, *column)?)
+                    }
+                    (None, Some(default)) => {
+                        output.push_str(&default.render(typ, properties, connectors)?)
+                    }
+                    // An empty value is kept empty.
+                    (None, None) if *is_optional || properties.properties.contains_key(key) => {}
+                    (None, None) => {
+                        return Err(error::Template::MissingProperty(
+                            typ.to_string(),
+                            *column,
+                            key.to_string(),
+                        ))
+                    }
+                },
+                Node::Connector { idx, column } => match connectors.get(*idx) {
+                    Some(connector) => output.push_str(connector),
+                    None => {
+                        return Err(error::Template::MissingConnector(
+                            typ.to_string(),
+                            *column,
+                            *idx,
+                        ))
+                    }
+                },
+                Node::Connectors => output.push_str(&connectors.join(" ")),
+                Node::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let branch = if Self::is_true(condition, properties) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    output.push_str(&branch.render(typ, properties, connectors)?);
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// The first word of the template (the name of the device), if it does not depend on the
+    /// connectors.
+    pub fn device(&self, properties: &properties::Properties) -> Option<String> {
+        let mut device = String::new();
+        for node in self.0.iter() {
+            let text = match node {
+                Node::Text(text) => text.clone(),
+                Node::Connector { .. } | Node::Connectors => break,
+                node => Template(vec![node.clone()])
+                    .render("", properties, &[])
+                    .ok()?,
+            };
+            match text.find(char::is_whitespace) {
+                Some(end) => {
+                    device.push_str(&text[..end]);
+                    break;
+                }
+                None => device.push_str(&text),
+            }
+        }
+        Some(device).filter(|device| !device.is_empty())
+    }
+
+    /// Check that the template only uses the properties and the connectors of its part. The
+    /// optional properties, the properties with a default and the conditions are not checked.
+    pub fn check(
+        &self,
+        typ: &str,
+        properties: &properties::Properties,
+        connectors: usize,
+    ) -> Result<(), error::Template> {
+        self.0.iter().try_for_each(|node| match node {
+            Node::Property {
+                key,
+                is_optional: false,
+                default: None,
+                column,
+                ..
+            } if !properties.properties.contains_key(key) => Err(error::Template::MissingProperty(
+                typ.to_string(),
+                *column,
+                key.to_string(),
+            )),
+            Node::Property {
+                default: Some(default),
+                ..
+            } => default.check(typ, properties, connectors),
+            Node::Connector { idx, column } if *idx >= connectors => Err(
+                error::Template::MissingConnector(typ.to_string(), *column, *idx),
+            ),
+            Node::Conditional {
+                then, otherwise, ..
+            } => {
+                then.check(typ, properties, connectors)?;
+                otherwise.check(typ, properties, connectors)
+            }
+            _ => Ok(()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PARTS;
+
+    fn properties(s: &str) -> properties::Properties {
+        s.parse::<properties::Properties>().unwrap()
+    }
+
+    fn render(pattern: &str, properties: &properties::Properties) -> String {
+        Template::parse(pattern)
+            .unwrap()
+            .render("test.part", properties, &["a".to_string(), "b".to_string()])
+            .unwrap()
+    }
+
+    fn syntax_error(pattern: &str) -> SyntaxError {
+        Template::parse(pattern).unwrap_err()
+    }
+
+    #[test]
+    fn every_library_pattern_is_valid() {
+        PARTS.validate().unwrap();
+        PARTS
+            .parts
+            .values()
+            .flat_map(|lib| lib.parts.values())
+            .for_each(|part| {
+                let connectors = vec!["n".to_string(); part.layout.connectors.len()];
+                part.spice
+                    .to_spice(&part.typ, &part.properties, connectors)
+                    .unwrap();
+            });
+    }
+
+    #[test]
+    fn properties_and_connectors_are_replaced() {
+        let props = properties("name<string[R0];true;false;0>!value<unit[10|Ω|k];true;false;1>!");
+        assert_eq!(
+            render("R{prop:name} {conn:0} {conn:1} {prop:value}", &props),
+            "RR0 a b 10k"
+        );
+        assert_eq!(
+            render("X{prop:name} {conns} block", &props),
+            "XR0 a b block"
+        );
+    }
+
+    #[test]
+    fn defaults_and_optional_properties() {
+        let props = properties("name<string[C1];true;false;0>!init_cond<string[];true;false;1>!");
+        assert_eq!(render("{prop?temp}", &props), "");
+        assert_eq!(render("{prop:temp|27}", &props), "27");
+        assert_eq!(render("{prop:init_cond|0}", &props), "0");
+        assert_eq!(render("{prop:name|{prop:temp|x}}", &props), "C1");
+    }
+
+    #[test]
+    fn conditionals_on_presence_and_value() {
+        let props = properties("name<string[Q1];true;false;0>!temp<string[85];true;false;1>!");
+        assert_eq!(render("{?temp: temp={prop:temp}}", &props), " temp=85");
+        assert_eq!(render("{?tc1: tc1={prop:tc1}}", &props), "");
+        assert_eq!(render("{?!tc1:none}", &props), "none");
+        assert_eq!(render("{?temp=85:hot|cold}", &props), "hot");
+        assert_eq!(render("{?temp!=85:cold|hot}", &props), "hot");
+        assert_eq!(render("{?name=Q2:a|b}", &props), "b");
+    }
+
+    #[test]
+    fn units_are_formatted() {
+        let props = properties(
+            "value<unit[4.7|F|u];true;false;0>!big<unit[2|Ω|Meg];true;false;1>!d<unit[5|F|d];true;false;2>!",
+        );
+        assert_eq!(render("{prop:value}", &props), "4.7u");
+        assert_eq!(render("{prop:big}", &props), "2Meg");
+        assert_eq!(render("{prop:big@si}", &props), "2000000");
+        // The deci prefix is not a SPICE suffix.
+        assert_eq!(render("{prop:d}", &props), "0.5");
+    }
+
+    #[test]
+    fn escaped_characters_are_text() {
+        let props = properties("");
+        assert_eq!(render("\\{a\\} \\| \\\\ |", &props), "{a} | \\ |");
+    }
+
+    #[test]
+    fn device_is_the_first_word() {
+        let props = properties("name<string[1];true;false;0>!");
+        let template = Template::parse("D{prop:name} {conn:0} {conn:1} model").unwrap();
+        assert_eq!(template.device(&props), Some("D1".to_string()));
+        assert_eq!(Template::parse("").unwrap().device(&props), None);
+    }
+
+    #[test]
+    fn syntax_errors_have_a_column() {
+        assert_eq!(syntax_error("R{prop:name"), (2, error::Syntax::Unclosed));
+        assert_eq!(syntax_error("R}"), (2, error::Syntax::UnexpectedClose));
+        assert_eq!(
+            syntax_error("R{name}"),
+            (3, error::Syntax::UnknownTag("name".to_string()))
+        );
+        assert_eq!(
+            syntax_error("V {conn:x}"),
+            (9, error::Syntax::InvalidConnector("x".to_string()))
+        );
+        assert_eq!(syntax_error("{prop:}"), (7, error::Syntax::MissingKey));
+        assert_eq!(
+            syntax_error("{prop:value@eng}"),
+            (13, error::Syntax::UnknownFormat("eng".to_string()))
+        );
+        assert_eq!(syntax_error("a\\n"), (2, error::Syntax::UnknownEscape('n')));
+        assert_eq!(
+            syntax_error("{?temp temp}"),
+            (7, error::Syntax::Expected(":"))
+        );
+    }
+
+    #[test]
+    fn errors_name_the_part_and_the_column() {
+        let spice = crate::schema::parts::Spice::from("R{prop:name".to_string());
+        let error = spice
+            .to_spice("lumped.resistor", &properties(""), vec![])
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("lumped.resistor") && error.contains("colonne 2"),
+            "{}",
+            error
+        );
+        let template = Template::parse("R{prop:name} {conn:2}").unwrap();
+        let props = properties("name<string[R1];true;false;0>!");
+        assert!(matches!(
+            template.render("lumped.resistor", &properties(""), &[]),
+            Err(error::Template::MissingProperty(_, 2, _))
+        ));
+        assert!(matches!(
+            template.check("lumped.resistor", &props, 2),
+            Err(error::Template::MissingConnector(_, 14, 2))
+        ));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::dom::form::text_input;
 use crate::schema::props;
-use crate::{dom, error, unit};
+use crate::{dom, error, sim, unit};
 use serde::Deserialize;
 use serde_json;
 use std::collections::HashMap;
@@ -25,13 +25,136 @@ pub struct PropertyData {
     pub description: Option<String>,
 }
 
+/// Multiple values that a property takes one after the other, the analysis is run once per
+/// value. It is either a list of values (`1,10,100`) or a range (`start:stop:step`).
+#[derive(Clone)]
+pub enum Sweep {
+    List(Vec<f64>),
+    Range(f64, f64, f64),
+}
+
+impl Sweep {
+    pub fn values(&self) -> Vec<f64> {
+        match self {
+            Sweep::List(values) => values.clone(),
+            Sweep::Range(start, stop, step) => sim::sweep_values(*start, *stop, *step),
+        }
+    }
+
+    /// The number of values taken by the sweep.
+    pub fn count(&self) -> usize {
+        match self {
+            Sweep::List(values) => values.len(),
+            Sweep::Range(start, stop, step) => sim::sweep_count(*start, *stop, *step),
+        }
+    }
+}
+
+impl str::FromStr for Sweep {
+    type Err = error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |separator: char| {
+            s.split(separator)
+                .map(|value| value.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, std::num::ParseFloatError>>()
+        };
+        if s.contains(':') {
+            match parse(':')?[..] {
+                [start, stop, step] => Ok(Sweep::Range(start, stop, step)),
+                _ => Err(Box::new(error::Import::UnexpectedValue)),
+            }
+        } else if s.contains(',') {
+            Ok(Sweep::List(parse(',')?))
+        } else {
+            Err(Box::new(error::Import::MissingToken))
+        }
+    }
+}
+
+impl fmt::Display for Sweep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sweep::List(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<String>>();
+                write!(f, "{}", values.join(","))
+            }
+            Sweep::Range(start, stop, step) => write!(f, "{}:{}:{}", start, stop, step),
+        }
+    }
+}
+
+/// How the random values of a property with a tolerance are distributed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Distribution {
+    Uniform,
+    /// The tolerance is 3 standard deviations.
+    Gaussian,
+}
+
+/// The tolerance of a value (in %) used by a Monte Carlo analysis, ie: `5%` for a 5% resistor.
+#[derive(Clone, Copy)]
+pub struct Tolerance {
+    pub percent: f64,
+    pub distribution: Distribution,
+}
+
+impl Tolerance {
+    /// A random value around `value` that follows the tolerance.
+    pub fn randomize(&self, value: f64) -> f64 {
+        let deviation = self.percent / 100.0;
+        let delta = match self.distribution {
+            Distribution::Uniform => sim::montecarlo::uniform(-deviation, deviation),
+            Distribution::Gaussian => sim::montecarlo::gaussian(0.0, deviation / 3.0),
+        };
+        value * (1.0 + delta)
+    }
+}
+
+impl str::FromStr for Tolerance {
+    type Err = error::Error;
+
+    /// The format is `5%u` for a uniform distribution and `5%g` for a gaussian one.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data = s.split('%').collect::<Vec<&str>>();
+        if data.len() != 2 {
+            return Err(Box::new(error::Import::MissingToken));
+        }
+        let distribution = match data[1] {
+            "u" => Distribution::Uniform,
+            "g" => Distribution::Gaussian,
+            _ => return Err(Box::new(error::Import::UnexpectedValue)),
+        };
+        Ok(Self {
+            percent: data[0].parse::<f64>()?,
+            distribution,
+        })
+    }
+}
+
+impl fmt::Display for Tolerance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let distribution = match self.distribution {
+            Distribution::Uniform => "u",
+            Distribution::Gaussian => "g",
+        };
+        write!(f, "{}%{}", self.percent, distribution)
+    }
+}
+
 /// A value which is stored inside a property of a part. A property can be anything like a string,
 /// a number or a more complexe data structure (like a number with a unit (Unit)).
 #[derive(Clone)]
 pub enum Value {
     String(String),
     F64(f64),
-    Unit(f64, unit::Unit, unit::Prefix),
+    /// A number with a unit and an optional tolerance.
+    Unit(f64, unit::Unit, unit::Prefix, Option<Tolerance>),
+    /// A number (with or without a unit) that takes multiple values.
+    Sweep(Sweep, Option<(unit::Unit, unit::Prefix)>),
 }
 
 impl Value {
@@ -51,10 +174,10 @@ impl Value {
         }
         let value_input = text_input::new(attributes);
 
-        if let Value::Unit(_, unit, prefix) = &self {
+        if let Value::Unit(_, unit, prefix, _) | Value::Sweep(_, Some((unit, prefix))) = &self {
             let unit_string = unit.to_string();
             let hidden_name = format!("property__{}-unit", key);
-            vec![
+            let mut inputs = vec![
                 text_input::hidden(dom::attributes! {
                     "class" => "",
                     "value" => &unit_string,
@@ -66,17 +189,88 @@ impl Value {
                     &unit,
                     prefix.index(),
                 ),
-            ]
+            ];
+            if let Value::Unit(_, _, _, tolerance) = &self {
+                inputs.append(&mut Self::tolerance_inputs(key, tolerance));
+            }
+            inputs
         } else {
             vec![value_input]
         }
     }
 
+    /// The inputs of the tolerance of a value (in %) and its distribution. An empty tolerance
+    /// means that the value is exact.
+    fn tolerance_inputs(key: &str, tolerance: &Option<Tolerance>) -> Vec<web_sys::Element> {
+        let name = format!("property__{}-tolerance", key);
+        let percent = tolerance.map_or(String::new(), |tolerance| tolerance.percent.to_string());
+        let is_gaussian = matches!(
+            tolerance,
+            Some(Tolerance {
+                distribution: Distribution::Gaussian,
+                ..
+            })
+        );
+        vec![
+            text_input::new(dom::attributes! {
+                "class" => "",
+                "name" => &name[..],
+                "value" => &percent[..],
+                "placeholder" => "±%",
+            }),
+            dom::form::select::create(
+                &format!("{}-distribution", name),
+                vec![
+                    ("u".to_string(), "Uniforme".to_string()),
+                    ("g".to_string(), "Gaussienne".to_string()),
+                ],
+                if is_gaussian { 1 } else { 0 },
+            ),
+        ]
+    }
+
+    /// Read the tolerance of the input named `name`. There is no tolerance if the input does not
+    /// exist or if it is empty.
+    fn tolerance_from_input(name: &str) -> Option<Tolerance> {
+        let input = dom::select_all(&format!("[name=\"{}-tolerance\"]", name)).pop()?;
+        let percent = text_input::value::<f64>(input).ok()?;
+        let distribution = dom::select_all(&format!("[name=\"{}-tolerance-distribution\"]", name))
+            .pop()
+            .and_then(|input| dom::form::select::value::<String>(input).ok());
+        let distribution = match distribution.as_deref() {
+            Some("g") => Distribution::Gaussian,
+            _ => Distribution::Uniform,
+        };
+        if percent > 0.0 {
+            Some(Tolerance {
+                percent,
+                distribution,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// A copy of the value where the number is replaced by a random value that follows its
+    /// tolerance. A value without tolerance is returned as is.
+    pub fn randomize(&self) -> Value {
+        match self {
+            Value::Unit(value, unit, prefix, Some(tolerance)) => Value::Unit(
+                tolerance.randomize(*value),
+                unit.clone(),
+                *prefix,
+                Some(*tolerance),
+            ),
+            _ => self.clone(),
+        }
+    }
+
     pub fn typ(&self) -> &'static str {
         match self {
             Self::String(_) => "string",
             Self::F64(_) => "f64",
-            Self::Unit(_, _, _) => "unit",
+            Self::Unit(_, _, _, _) | Self::Sweep(_, Some(_)) => "unit",
+            Self::Sweep(_, None) => "f64",
         }
     }
 
@@ -84,7 +278,47 @@ impl Value {
         match self {
             Value::String(value) => value.clone(),
             Value::F64(value) => value.to_string(),
-            Value::Unit(value, _, _) => value.to_string(),
+            Value::Unit(value, _, _, _) => value.to_string(),
+            Value::Sweep(sweep, _) => sweep.to_string(),
+        }
+    }
+
+    /// Every value taken by a sweep, each one is a single value with the same unit. A value that
+    /// is not a sweep has no values.
+    pub fn sweep_values(&self) -> Option<Vec<Value>> {
+        if let Value::Sweep(sweep, unit) = self {
+            Some(
+                sweep
+                    .values()
+                    .into_iter()
+                    .map(|value| match unit {
+                        Some((unit, prefix)) => Value::Unit(value, unit.clone(), *prefix, None),
+                        None => Value::F64(value),
+                    })
+                    .collect::<Vec<Value>>(),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// The numerical value without any prefix, if the value is a number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::String(value) => value.parse::<f64>().ok(),
+            Value::F64(value) => Some(*value),
+            Value::Unit(value, _, prefix, _) => Some(value * prefix.factor()),
+            Value::Sweep(_, _) => None,
+        }
+    }
+
+    /// The number written in an input or, if the input accepts it, the sweep that replaces the
+    /// number (`1,10,100` or `start:stop:step`) for a parametric analysis.
+    fn parse_number(text: &str, is_swept: bool) -> Option<Result<f64, Sweep>> {
+        match (text.parse::<f64>(), text.parse::<Sweep>()) {
+            (Ok(value), _) => Some(Ok(value)),
+            (_, Ok(sweep)) if is_swept => Some(Err(sweep)),
+            _ => None,
         }
     }
 
@@ -111,13 +345,16 @@ impl From<f64> for Value {
 
 impl From<web_sys::Element> for Value {
     fn from(input: web_sys::Element) -> Self {
-        if let (Ok(f64_value), Some(typ)) = (
-            text_input::value::<f64>(input.clone()),
-            input.get_attribute("data-property-type"),
-        ) {
+        let text = text_input::value::<String>(input.clone()).expect("`value` is a string");
+        // Only the properties of the parts (not the simulation settings) can be swept.
+        let number = Self::parse_number(&text, input.has_attribute("data-property"));
+        if let (Some(number), Some(typ)) = (number, input.get_attribute("data-property-type")) {
             match &typ[..] {
                 "f64" => {
-                    return Value::F64(f64_value);
+                    return match number {
+                        Ok(value) => Value::F64(value),
+                        Err(sweep) => Value::Sweep(sweep, None),
+                    };
                 }
                 "unit" => {
                     if let Some(name) = input.get_attribute("name") {
@@ -126,17 +363,20 @@ impl From<web_sys::Element> for Value {
                             text_input::value::<String>(unit_input).expect("`value` is a string");
                         let prefix_value = dom::select(&format!("[name=\"{}-unit-prefix\"]", name));
                         let prefix = dom::form::select::value::<usize>(prefix_value).unwrap();
-                        return Value::Unit(
-                            f64_value,
-                            unit.parse::<unit::Unit>().unwrap(),
-                            unit::Prefix::from(prefix),
-                        );
+                        let unit = unit.parse::<unit::Unit>().unwrap();
+                        let prefix = unit::Prefix::from(prefix);
+                        return match number {
+                            Ok(value) => {
+                                Value::Unit(value, unit, prefix, Self::tolerance_from_input(&name))
+                            }
+                            Err(sweep) => Value::Sweep(sweep, Some((unit, prefix))),
+                        };
                     }
                 }
                 _ => {}
             }
         }
-        Value::String(text_input::value::<String>(input).expect("`value` is a string"))
+        Value::String(text)
     }
 }
 
@@ -145,17 +385,30 @@ impl str::FromStr for Value {
 
     // This function is used when importing a file. Take a string and converts it to the struct.
     // The format is the following one:
-    // `string[text]` or `unit[value|suffix|unit]`
+    // `string[text]`, `unit[value|suffix|unit]` (or `unit[value|suffix|unit|tolerance]`) or
+    // `sweep[values]` and `sweep[values|unit|prefix]`
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let (Some(start_idx), Some(end_idx)) = (s.find('['), s.find(']')) {
             let data = s[start_idx + 1..end_idx].split("|").collect::<Vec<&str>>();
             match &s[..start_idx] {
                 "string" if data.len() == 1 => Ok(Value::String(data[0].to_string())),
                 "f64" if data.len() == 1 => Ok(Value::F64(data[0].parse::<f64>()?)),
-                "unit" if data.len() == 3 => Ok(Value::Unit(
+                "unit" if data.len() == 3 || data.len() == 4 => Ok(Value::Unit(
                     data[0].parse::<f64>()?,
                     data[1].parse::<unit::Unit>()?,
                     data[2].parse::<unit::Prefix>()?,
+                    match data.get(3) {
+                        Some(tolerance) => Some(tolerance.parse::<Tolerance>()?),
+                        None => None,
+                    },
+                )),
+                "sweep" if data.len() == 1 => Ok(Value::Sweep(data[0].parse::<Sweep>()?, None)),
+                "sweep" if data.len() == 3 => Ok(Value::Sweep(
+                    data[0].parse::<Sweep>()?,
+                    Some((
+                        data[1].parse::<unit::Unit>()?,
+                        data[2].parse::<unit::Prefix>()?,
+                    )),
                 )),
                 _ => Err(Box::new(error::Import::UnexpectedValue)),
             }
@@ -170,9 +423,15 @@ impl fmt::Display for Value {
         match self {
             Value::String(value) => write!(f, "{}", value),
             Value::F64(value) => write!(f, "{}", self.format_infinity(*value)),
-            Value::Unit(value, _, prefix) => {
+            Value::Unit(value, _, prefix, _) => {
                 write!(f, "{}{:?}", self.format_infinity(*value), prefix)
             }
+            // A sweep is replaced by each of its values before generating the netlist, the first
+            // value is used when it is not.
+            Value::Sweep(_, _) => match self.sweep_values() {
+                Some(values) if !values.is_empty() => write!(f, "{}", values[0]),
+                _ => write!(f, "0"),
+            },
         }
     }
 }
@@ -182,7 +441,16 @@ impl fmt::Debug for Value {
         match self {
             Value::String(value) => write!(f, "string[{}]", value),
             Value::F64(value) => write!(f, "f64[{}]", value),
-            Value::Unit(value, unit, prefix) => write!(f, "unit[{}|{}|{:?}]", value, unit, prefix),
+            Value::Unit(value, unit, prefix, None) => {
+                write!(f, "unit[{}|{}|{:?}]", value, unit, prefix)
+            }
+            Value::Unit(value, unit, prefix, Some(tolerance)) => {
+                write!(f, "unit[{}|{}|{:?}|{}]", value, unit, prefix, tolerance)
+            }
+            Value::Sweep(sweep, None) => write!(f, "sweep[{}]", sweep),
+            Value::Sweep(sweep, Some((unit, prefix))) => {
+                write!(f, "sweep[{}|{}|{:?}]", sweep, unit, prefix)
+            }
         }
     }
 }
@@ -300,7 +568,7 @@ impl Properties {
     pub fn get(&self, key: &str) -> Result<&Property, error::Error> {
         match self.properties.get(key) {
             Some(property) => Ok(property),
-            _ => Err(Box::new(error::Sim::PropertyNotFound(0, key.to_string()))),
+            _ => Err(Box::new(error::Sim::PropertyNotFound(key.to_string()))),
         }
     }
 }
@@ -354,3 +622,24 @@ impl fmt::Debug for Properties {
         write!(f, "{}", output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_parts_are_swept() {
+        let number = |text: &str, is_swept: bool| match Value::parse_number(text, is_swept) {
+            Some(Ok(value)) => Some(value.to_string()),
+            Some(Err(sweep)) => Some(sweep.to_string()),
+            None => None,
+        };
+        assert_eq!(number("4.7", true), Some(String::from("4.7")));
+        assert_eq!(number("1,10,100", true), Some(String::from("1,10,100")));
+        assert_eq!(number("1:2:0.5", true), Some(String::from("1:2:0.5")));
+        // A simulation setting is a single number, the text is kept as is.
+        assert_eq!(number("4.7", false), Some(String::from("4.7")));
+        assert_eq!(number("1,10,100", false), None);
+        assert_eq!(number("1:2:0.5", false), None);
+    }
+}
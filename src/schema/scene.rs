@@ -2,6 +2,11 @@ use crate::intrinsics::*;
 use crate::schema::{ctx, mouse};
 use crate::{dom, error};
 
+const MIN_SCALE: f64 = 0.3;
+const MAX_SCALE: f64 = 11.0;
+/// The space (in pixels of the screen) kept around a region when zooming to it.
+const ZOOM_MARGIN: f64 = 60.0;
+
 pub struct Scene {
     pub offset: Point,
     pub scale: f64,
@@ -31,8 +36,8 @@ impl Scene {
     pub fn compute_scale(&mut self, delta: f64, mouse: &mouse::Mouse) {
         let zoom = 1.0 + (delta / 300.0) / 2.0;
         self.scale *= zoom;
-        self.scale = self.scale.max(0.3);
-        self.scale = self.scale.min(11.0);
+        self.scale = self.scale.max(MIN_SCALE);
+        self.scale = self.scale.min(MAX_SCALE);
         let new_offset = Point::new(
             mouse.screen_pos.x / self.scale,
             mouse.screen_pos.y / self.scale,
@@ -40,6 +45,19 @@ impl Scene {
         self.offset = self.offset + mouse.scene_pos - new_offset;
     }
 
+    /// Center the view on a region of the scene and zoom until the region fills the view.
+    pub fn zoom_to(&mut self, origin: Point, size: Size) {
+        let scale_w = (self.size.w - 2.0 * ZOOM_MARGIN).max(1.0) / size.w.max(1.0);
+        let scale_h = (self.size.h - 2.0 * ZOOM_MARGIN).max(1.0) / size.h.max(1.0);
+        self.scale = scale_w.min(scale_h).clamp(MIN_SCALE, MAX_SCALE);
+        let center = origin + Point::new(size.w / 2.0, size.h / 2.0);
+        self.offset = center
+            - Point::new(
+                self.size.w / self.scale / 2.0,
+                self.size.h / self.scale / 2.0,
+            );
+    }
+
     pub fn draw_grid(&self, ctx: &ctx::Ctx) {
         for (color, step_by) in [
             ("#111111", Point::GRID_SIZE as usize),
@@ -1,8 +1,10 @@
 mod layout;
 pub mod wire;
 use crate::intrinsics::*;
-use crate::schema::{mouse, parts, utils};
+use crate::schema::{ctx, mouse, parts, shape, utils};
+use crate::sim::nodes;
 pub use layout::*;
+use std::collections::HashSet;
 pub use wire::*;
 
 pub struct Wires {
@@ -56,6 +58,14 @@ impl Wires {
         self.selected = selected;
     }
 
+    /// Select only the given wires.
+    pub fn select_only(&mut self, selected: Vec<usize>) {
+        self.wires.iter_mut().enumerate().for_each(|(idx, wire)| {
+            wire.state.set_selected(selected.contains(&idx));
+        });
+        self.selected = selected;
+    }
+
     pub fn unselect(&mut self, mouse: &mut mouse::Mouse) {
         self.end_wire(mouse, true);
         for idx in self.selected.iter() {
@@ -123,6 +133,72 @@ impl Wires {
         return false;
     }
 
+    /// The points where 3 connections or more meet. A connection is the end of a wire or the
+    /// connector of a part, a wire that goes through the point counts as 2 connections (ie: a
+    /// wire that ends in the middle of another one). Wires crossing each other are not connected
+    /// so there is no junction where they cross.
+    pub fn junctions(&self, parts: &parts::Parts) -> Vec<Point> {
+        // Only the wires and the connectors near an extremity can touch it (see `nodes::Grid`).
+        let wires = nodes::Grid::wires(&self.wires);
+        let connectors =
+            nodes::Grid::new(parts.parts.iter().enumerate().flat_map(|(idx, part)| {
+                let origin = part.layout.origin;
+                part.layout.connectors.iter().map(move |connector| {
+                    (idx, origin + connector.origin, origin + connector.origin)
+                })
+            }));
+        let is_drawn = |wire: &Wire| wire.state != utils::State::Floating;
+        let mut extremities = HashSet::new();
+        let mut junctions = Vec::new();
+        for wire in self.wires.iter().filter(|wire| is_drawn(wire)) {
+            for extremity in wire.layout.extremities() {
+                if !extremities.insert((extremity.x.to_bits(), extremity.y.to_bits())) {
+                    continue;
+                }
+                let connections = wires
+                    .near(extremity)
+                    .iter()
+                    .map(|&idx| &self.wires[idx])
+                    .filter(|other| is_drawn(other))
+                    .map(|other| match other.layout.collide_with_point(extremity) {
+                        utils::Colliding::None => 0,
+                        utils::Colliding::Connector(idx)
+                            if idx == 0 || idx == other.layout.shape.points.len() - 1 =>
+                        {
+                            1
+                        }
+                        _ => 2,
+                    })
+                    .sum::<usize>()
+                    + connectors
+                        .near(extremity)
+                        .iter()
+                        .filter(|&&idx| {
+                            matches!(
+                                parts.parts[idx].layout.collide_with_point(extremity),
+                                utils::Colliding::Connector(_)
+                            )
+                        })
+                        .count();
+                if connections >= 3 {
+                    junctions.push(extremity);
+                }
+            }
+        }
+        junctions
+    }
+
+    /// Draw a dot on every junction.
+    pub fn draw_junctions(&self, ctx: &ctx::Ctx, parts: &parts::Parts) {
+        ctx.set_fill_style(Wire::color());
+        self.junctions(parts).into_iter().for_each(|junction| {
+            ctx.fill_arc(
+                junction,
+                &shape::Arc::new(Point::new(0.0, 0.0), 3.0, 0.0, 360.0),
+            );
+        });
+    }
+
     pub fn iter(&self) -> std::slice::Iter<'_, Wire> {
         self.wires.iter()
     }
@@ -137,3 +213,56 @@ impl Wires {
         collisions
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PARTS;
+
+    fn wires(wires: Vec<Wire>) -> Wires {
+        Wires {
+            wires,
+            selected: Vec::new(),
+            copied: Vec::new(),
+        }
+    }
+
+    fn wire(x1: f64, y1: f64, x2: f64, y2: f64) -> Wire {
+        Wire::new(Point::new(x1, y1), Point::new(x2, y2))
+    }
+
+    #[test]
+    fn junctions() {
+        let parts = parts::Parts::new();
+        // A wire that ends in the middle of another one, and two wires that cross.
+        let t = wires(vec![
+            wire(0.0, 0.0, 100.0, 0.0),
+            wire(50.0, 0.0, 50.0, 50.0),
+        ]);
+        assert!(t.junctions(&parts) == vec![Point::new(50.0, 0.0)]);
+        let crossing = wires(vec![
+            wire(0.0, 0.0, 100.0, 0.0),
+            wire(50.0, -50.0, 50.0, 50.0),
+        ]);
+        assert!(crossing.junctions(&parts).is_empty());
+        // Two wires that end on the connector of a part.
+        let mut parts = parts::Parts::new();
+        parts.parts.push(PARTS.get("lumped.resistor").unwrap());
+        let layout = &parts.parts[0].layout;
+        let connector = layout.origin + layout.connectors[0].origin;
+        let (x, y) = (connector.x, connector.y);
+        let ending = wires(vec![wire(x - 50.0, y, x, y), wire(x, y, x, y - 50.0)]);
+        assert!(ending.junctions(&parts) == vec![connector]);
+    }
+
+    #[test]
+    fn junctions_of_a_large_schema() {
+        let parts = parts::Parts::new();
+        let chain = wires(
+            (0..20_000)
+                .map(|idx| wire(idx as f64 * 10.0, 0.0, (idx + 1) as f64 * 10.0, 0.0))
+                .collect(),
+        );
+        assert!(chain.junctions(&parts).is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::schema::{ctx, mouse, parts::part, utils, wires};
+use crate::schema::{ctx, mouse, utils, wires};
 use crate::{error, intrinsics::*};
 use std::fmt;
 
@@ -114,42 +114,16 @@ impl Wire {
         self.layout.trace(mouse);
     }
 
+    /// Two wires are connected when an extremity of one of them is anywhere on the other one (at
+    /// an extremity, at a corner or in the middle of a segment, which is a T-junction). Wires
+    /// that only cross each other are not connected.
     pub fn collide_with_wire(&self, other: &Wire) -> bool {
-        let collisions_count_self = self
-            .layout
-            .extremities()
-            .into_iter()
-            .filter(|extremity| {
-                other.layout.collide_with_point(*extremity) != utils::Colliding::None
-            })
-            .count();
-        let collisions_count_other = self
-            .layout
-            .extremities()
-            .into_iter()
-            .filter(|extremity| {
-                other.layout.collide_with_point(*extremity) != utils::Colliding::None
+        let touches = |wire: &Wire, other: &Wire| {
+            wire.layout.extremities().into_iter().any(|extremity| {
+                other.layout.collide_with_point(extremity) != utils::Colliding::None
             })
-            .count();
-        collisions_count_self > 0 || collisions_count_other > 0
-    }
-
-    pub fn collide_with_part(&self, part: &part::Part) -> Vec<usize> {
-        let mut connectors = Vec::new();
-        part.layout
-            .connectors
-            .iter()
-            .enumerate()
-            .for_each(|(idx, connector)| {
-                if self
-                    .layout
-                    .collide_with_point(connector.origin + part.layout.origin)
-                    != utils::Colliding::None
-                {
-                    connectors.push(idx);
-                }
-            });
-        connectors
+        };
+        touches(self, other) || touches(other, self)
     }
 }
 
@@ -0,0 +1,93 @@
+use crate::events;
+use crate::sim::backend::{js_error, Pending, RequestId, Requests, SimulatorBackend};
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+
+/// ngspice reached through the JavaScript of the page (see `www/static/index.js`). A netlist is
+/// sent with the `ngspice_call` event (`{ id, netlist }`), the output is received with the
+/// `ngspice_response` event (`{ id, output }` or `{ id, error }`) and a request is cancelled
+/// with the `ngspice_cancel` event (`{ id }`). The lines printed by ngspice while it simulates
+/// can be sent with the `ngspice_progress` event (`{ id, text }`), their percentage is the
+/// progress of the request.
+pub struct Bridge {
+    requests: Rc<Requests>,
+}
+
+/// The request of the detail of an event.
+fn request(detail: &JsValue) -> Option<RequestId> {
+    field(detail, "id").as_f64().map(|id| RequestId(id as u64))
+}
+
+/// A field of the detail of an event.
+fn field(detail: &JsValue, key: &str) -> JsValue {
+    js_sys::Reflect::get(detail, &JsValue::from_str(key)).unwrap_or(JsValue::UNDEFINED)
+}
+
+fn dispatch(name: &str, id: RequestId, netlist: Option<&str>) {
+    let detail = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&detail, &"id".into(), &JsValue::from_f64(id.0 as f64));
+    if let Some(netlist) = netlist {
+        let _ = js_sys::Reflect::set(&detail, &"netlist".into(), &netlist.into());
+    }
+    let event_data = web_sys::CustomEventInit::new();
+    event_data.set_detail(&detail);
+    let event = web_sys::CustomEvent::new_with_event_init_dict(name, &event_data).unwrap();
+    web_sys::window().unwrap().dispatch_event(&event).unwrap();
+}
+
+impl Bridge {
+    pub fn new() -> Self {
+        let requests = Rc::new(Requests::default());
+        let r = requests.clone();
+        events::EventListener::add(
+            &web_sys::window().unwrap(),
+            &events::Event::NgspiceResponse,
+            Box::new(move |event: web_sys::CustomEvent| {
+                let detail = event.detail();
+                let id = match request(&detail) {
+                    Some(id) => id,
+                    None => return,
+                };
+                let output = field(&detail, "output")
+                    .as_string()
+                    .ok_or_else(|| js_error(&field(&detail, "error")));
+                r.resolve(id, output);
+            }),
+        );
+        let r = requests.clone();
+        events::EventListener::add(
+            &web_sys::window().unwrap(),
+            &events::Event::NgspiceProgress,
+            Box::new(move |event: web_sys::CustomEvent| {
+                let detail = event.detail();
+                if let (Some(id), Some(text)) =
+                    (request(&detail), field(&detail, "text").as_string())
+                {
+                    r.report(id, &text);
+                }
+            }),
+        );
+        Self { requests }
+    }
+}
+
+impl SimulatorBackend for Bridge {
+    fn submit(&self, netlist: &str) -> RequestId {
+        let id = self.requests.create();
+        dispatch("ngspice_call", id, Some(netlist));
+        id
+    }
+
+    fn result(&self, id: RequestId) -> Pending {
+        Requests::wait(&self.requests, id)
+    }
+
+    fn cancel(&self, id: RequestId) {
+        self.requests.cancel(id);
+        dispatch("ngspice_cancel", id, None);
+    }
+
+    fn progress(&self, id: RequestId) -> Option<f64> {
+        self.requests.progress(id)
+    }
+}
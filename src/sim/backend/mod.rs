@@ -0,0 +1,375 @@
+mod bridge;
+mod native;
+mod remote;
+#[cfg(test)]
+mod replay;
+
+pub use bridge::Bridge;
+pub use native::Native;
+pub use remote::{Http, WebSocket};
+#[cfg(test)]
+pub use replay::Replay;
+
+use crate::error;
+use crate::sim::{jobs, Engine};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll, Waker};
+
+/// The identifier of a request sent to a backend. The output of a request is always returned
+/// with its identifier, the output of a request that is not awaited anymore is discarded. The
+/// identifiers are unique among every backend, the output of a request sent to another backend
+/// is never taken for the output of the current request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RequestId(pub u64);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The output of a simulator for a netlist, printed like ngspice prints it (see
+/// `plot::parser`).
+pub type Output = Result<String, error::Error>;
+
+/// The output of a request once the simulator responds.
+pub type Pending = Pin<Box<dyn Future<Output = Output>>>;
+
+/// A simulator that runs netlists. A netlist is submitted without waiting for the simulator, its
+/// output is then awaited with the identifier of the request. Many requests can be pending at
+/// the same time, their outputs can arrive in any order.
+pub trait SimulatorBackend {
+    /// Send a netlist to the simulator.
+    fn submit(&self, netlist: &str) -> RequestId;
+
+    /// The output of a request. The output of a cancelled request is an error.
+    fn result(&self, id: RequestId) -> Pending;
+
+    /// Stop waiting for a request. Its output is discarded and the simulator is told to stop
+    /// when it can be.
+    fn cancel(&self, id: RequestId);
+
+    /// The progress of a pending request between 0 and 1, when the simulator reports it.
+    fn progress(&self, id: RequestId) -> Option<f64>;
+}
+
+/// A request waiting for its output.
+#[derive(Default)]
+struct Slot {
+    output: Option<Output>,
+    waker: Option<Waker>,
+    progress: Option<f64>,
+}
+
+/// The identifier of the next request, shared by every backend.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The pending requests of a backend, shared with the futures of their outputs.
+#[derive(Default)]
+pub struct Requests {
+    slots: RefCell<HashMap<RequestId, Slot>>,
+}
+
+impl Requests {
+    /// A new pending request.
+    pub fn create(&self) -> RequestId {
+        let id = RequestId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        self.slots.borrow_mut().insert(id, Slot::default());
+        id
+    }
+
+    /// Give its output to a request. The output of a request that is not pending (it was
+    /// cancelled) is discarded, `false` is then returned.
+    pub fn resolve(&self, id: RequestId, output: Output) -> bool {
+        let waker = match self.slots.borrow_mut().get_mut(&id) {
+            Some(slot) => {
+                slot.output = Some(output);
+                slot.waker.take()
+            }
+            None => return false,
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        true
+    }
+
+    /// Forget a request, the future of its output returns an error.
+    pub fn cancel(&self, id: RequestId) {
+        let slot = self.slots.borrow_mut().remove(&id);
+        if let Some(waker) = slot.and_then(|slot| slot.waker) {
+            waker.wake();
+        }
+    }
+
+    /// Update the progress of a request from a line printed by the simulator (see
+    /// `jobs::parse_progress`). A line without a percentage is ignored.
+    pub fn report(&self, id: RequestId, text: &str) {
+        if let Some(progress) = jobs::parse_progress(text) {
+            self.update(id, progress);
+        }
+    }
+
+    /// Update the progress of a request. Returns `false` if the request is not pending (it was
+    /// cancelled).
+    pub fn update(&self, id: RequestId, progress: f64) -> bool {
+        match self.slots.borrow_mut().get_mut(&id) {
+            Some(slot) => {
+                slot.progress = Some(progress);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn progress(&self, id: RequestId) -> Option<f64> {
+        self.slots.borrow().get(&id).and_then(|slot| slot.progress)
+    }
+
+    /// The requests that have not received their output yet.
+    pub fn pending(&self) -> Vec<RequestId> {
+        self.slots
+            .borrow()
+            .iter()
+            .filter(|(_, slot)| slot.output.is_none())
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// The future of the output of a request.
+    pub fn wait(requests: &Rc<Self>, id: RequestId) -> Pending {
+        Box::pin(Wait {
+            requests: requests.clone(),
+            id,
+        })
+    }
+}
+
+struct Wait {
+    requests: Rc<Requests>,
+    id: RequestId,
+}
+
+impl Future for Wait {
+    type Output = Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Output> {
+        let mut slots = self.requests.slots.borrow_mut();
+        let slot = match slots.get_mut(&self.id) {
+            Some(slot) => slot,
+            None => return Poll::Ready(Err(Box::new(error::Sim::BackendCancelled(self.id.0)))),
+        };
+        match slot.output.take() {
+            Some(output) => {
+                slots.remove(&self.id);
+                Poll::Ready(output)
+            }
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The error of the transport of a request.
+fn transport_error(message: &str) -> error::Error {
+    Box::new(error::Sim::BackendTransport(message.to_string()))
+}
+
+/// The error of the transport of a request from a JavaScript exception.
+fn js_error(value: &wasm_bindgen::JsValue) -> error::Error {
+    transport_error(&value.as_string().unwrap_or_else(|| format!("{:?}", value)))
+}
+
+/// The backend of an engine.
+pub fn create(engine: &Engine) -> Rc<dyn SimulatorBackend> {
+    match engine {
+        Engine::Ngspice => Rc::new(Bridge::new()),
+        Engine::Native => Rc::new(Native::default()),
+        Engine::Http(url) => Rc::new(Http::new(url)),
+        Engine::WebSocket(url) => Rc::new(WebSocket::new(url)),
+    }
+}
+
+/// The backends created so far. A backend is kept for the next simulations, the bridge listens
+/// to the responses of ngspice once and the WebSocket stays connected.
+#[derive(Default)]
+pub struct Backends {
+    backends: Vec<(Engine, Rc<dyn SimulatorBackend>)>,
+}
+
+impl Backends {
+    pub fn get(&mut self, engine: &Engine) -> Rc<dyn SimulatorBackend> {
+        if let Some((_, backend)) = self.backends.iter().find(|(e, _)| e == engine) {
+            return backend.clone();
+        }
+        let backend = create(engine);
+        self.backends.push((engine.clone(), backend.clone()));
+        backend
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::task::Waker;
+
+    /// Poll a future once.
+    fn poll(future: &mut Pending) -> Poll<Output> {
+        future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn outputs_are_matched_by_request() {
+        let requests = Rc::new(Requests::default());
+        let (first, second) = (requests.create(), requests.create());
+        let (mut first_output, mut second_output) = (
+            Requests::wait(&requests, first),
+            Requests::wait(&requests, second),
+        );
+        assert!(poll(&mut first_output).is_pending());
+        // The outputs arrive in the reverse order.
+        assert!(requests.resolve(second, Ok(String::from("second"))));
+        assert!(poll(&mut first_output).is_pending());
+        assert!(requests.resolve(first, Ok(String::from("first"))));
+        assert!(matches!(poll(&mut first_output), Poll::Ready(Ok(output)) if output == "first"));
+        assert!(matches!(poll(&mut second_output), Poll::Ready(Ok(output)) if output == "second"));
+        assert!(requests.pending().is_empty());
+    }
+
+    #[test]
+    fn ids_are_unique_among_backends() {
+        let (first, second) = (Requests::default(), Requests::default());
+        let id = first.create();
+        assert_ne!(second.create(), id);
+        assert!(!second.resolve(id, Ok(String::from("other backend"))));
+    }
+
+    #[test]
+    fn progress_is_reported() {
+        let requests = Rc::new(Requests::default());
+        let id = requests.create();
+        assert_eq!(requests.progress(id), None);
+        requests.report(id, "tran: 25.0%");
+        requests.report(id, "Reference value : 1.0e-03");
+        assert_eq!(requests.progress(id), Some(0.25));
+        requests.cancel(id);
+        requests.report(id, "tran: 50.0%");
+        assert_eq!(requests.progress(id), None);
+    }
+
+    #[test]
+    fn cancelled_outputs_are_discarded() {
+        let requests = Rc::new(Requests::default());
+        let id = requests.create();
+        let mut output = Requests::wait(&requests, id);
+        requests.cancel(id);
+        assert!(matches!(poll(&mut output), Poll::Ready(Err(_))));
+        assert!(!requests.resolve(id, Ok(String::from("stale"))));
+    }
+
+    #[test]
+    fn replay() {
+        let mut replay = Replay::default();
+        replay.record("A Circuit\n.op\n.end", "a\t1.0e0\n");
+        let id = replay.submit("A Circuit\n.op\n.end\n");
+        assert!(
+            matches!(poll(&mut replay.result(id)), Poll::Ready(Ok(output)) if output == "a\t1.0e0\n")
+        );
+        let id = replay.submit("Another Circuit\n.op\n.end");
+        assert!(matches!(poll(&mut replay.result(id)), Poll::Ready(Err(_))));
+        assert_eq!(replay.submitted().len(), 2);
+    }
+
+    /// A transient analysis long enough to be paused.
+    const TRANSIENT: &str = "A Circuit\nV1 a 0 dc 2\nR1 a b 1k\nC1 b 0 1u\n.tran 1u 10m\n\
+                             .print tran v(b)\n.end";
+
+    #[test]
+    fn native() {
+        let requests = Rc::new(Requests::default());
+        let id = requests.create();
+        let netlist = "A Circuit\nV1 a 0 dc 2\nR1 a b 1k\nR2 b 0 1k\n.op\n.end";
+        let mut output = Requests::wait(&requests, id);
+        let mut run = Box::pin(native::run(
+            requests.clone(),
+            id,
+            netlist.to_string(),
+            || std::future::ready(()),
+        ));
+        assert!(run
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+            .is_ready());
+        assert!(
+            matches!(poll(&mut output), Poll::Ready(Ok(output)) if output.contains("\na\t2e0\n"))
+        );
+    }
+
+    #[test]
+    fn native_progress() {
+        let requests = Rc::new(Requests::default());
+        let id = requests.create();
+        let progress = RefCell::new(Vec::new());
+        let idle = || {
+            progress.borrow_mut().extend(requests.progress(id));
+            std::future::ready(())
+        };
+        let mut run = Box::pin(native::run(
+            requests.clone(),
+            id,
+            TRANSIENT.to_string(),
+            idle,
+        ));
+        assert!(run
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+            .is_ready());
+        drop(run);
+        let progress = progress.into_inner();
+        assert!(!progress.is_empty());
+        assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(progress
+            .iter()
+            .all(|&progress| progress > 0.0 && progress <= 1.0));
+        assert!(matches!(
+            poll(&mut Requests::wait(&requests, id)),
+            Poll::Ready(Ok(_))
+        ));
+    }
+
+    #[test]
+    fn native_is_cancelled() {
+        let requests = Rc::new(Requests::default());
+        let id = requests.create();
+        let pauses = Cell::new(0);
+        // The request is cancelled during the first pause of the simulation.
+        let idle = || {
+            pauses.set(pauses.get() + 1);
+            requests.cancel(id);
+            std::future::ready(())
+        };
+        let mut run = Box::pin(native::run(
+            requests.clone(),
+            id,
+            TRANSIENT.to_string(),
+            idle,
+        ));
+        assert!(run
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+            .is_ready());
+        assert_eq!(pauses.get(), 1);
+        assert!(requests.pending().is_empty());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::error;
+use crate::sim;
+use crate::sim::backend::{Pending, RequestId, Requests, SimulatorBackend};
+use std::future::Future;
+use std::rc::Rc;
+use wasm_bindgen_futures::JsFuture;
+
+/// The simulator of the crate (see `sim::native::simulate_in_chunks`). A netlist is simulated in
+/// the page, a transient analysis is run in chunks so the page is not frozen while it runs: its
+/// progress is reported and it stops once its request is cancelled.
+#[derive(Default)]
+pub struct Native {
+    requests: Rc<Requests>,
+}
+
+/// Let the browser handle its events (ie: draw the page or cancel a request) before the
+/// simulation continues.
+async fn yield_to_browser() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = web_sys::window()
+            .unwrap()
+            .set_timeout_with_callback(&resolve);
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+/// Simulate the netlist of a request and give it its output. The simulation awaits `idle`
+/// between its chunks, then it reports its progress or it stops if the request was cancelled.
+pub(super) async fn run<I, F>(requests: Rc<Requests>, id: RequestId, netlist: String, idle: I)
+where
+    I: Fn() -> F,
+    F: Future<Output = ()>,
+{
+    let pause = |progress: f64| {
+        let idle = idle();
+        let requests = requests.clone();
+        async move {
+            idle.await;
+            match requests.update(id, progress) {
+                true => Ok(()),
+                false => Err(Box::new(error::Sim::BackendCancelled(id.0)) as error::Error),
+            }
+        }
+    };
+    let output = sim::native::simulate_in_chunks(&netlist, pause).await;
+    requests.resolve(id, output);
+}
+
+impl SimulatorBackend for Native {
+    fn submit(&self, netlist: &str) -> RequestId {
+        let id = self.requests.create();
+        wasm_bindgen_futures::spawn_local(run(
+            self.requests.clone(),
+            id,
+            netlist.to_string(),
+            yield_to_browser,
+        ));
+        id
+    }
+
+    fn result(&self, id: RequestId) -> Pending {
+        Requests::wait(&self.requests, id)
+    }
+
+    fn cancel(&self, id: RequestId) {
+        self.requests.cancel(id);
+    }
+
+    fn progress(&self, id: RequestId) -> Option<f64> {
+        self.requests.progress(id)
+    }
+}
@@ -0,0 +1,301 @@
+use crate::sim::backend::{
+    js_error, transport_error, Output, Pending, RequestId, Requests, SimulatorBackend,
+};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+/// The header that carries the identifier of a request sent with HTTP.
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// A local ngspice server reached with HTTP. A netlist is the body of a `POST` request and the
+/// output is the body of the response. A cancelled request is aborted.
+pub struct Http {
+    url: String,
+    requests: Rc<Requests>,
+    controllers: Rc<RefCell<HashMap<RequestId, web_sys::AbortController>>>,
+}
+
+impl Http {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            requests: Rc::new(Requests::default()),
+            controllers: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    fn request(
+        &self,
+        id: RequestId,
+        netlist: &str,
+        controller: Option<&web_sys::AbortController>,
+    ) -> Result<web_sys::Request, JsValue> {
+        let init = web_sys::RequestInit::new();
+        init.set_method("POST");
+        init.set_body(&JsValue::from_str(netlist));
+        if let Some(controller) = controller {
+            init.set_signal(Some(&controller.signal()));
+        }
+        let request = web_sys::Request::new_with_str_and_init(&self.url, &init)?;
+        request.headers().set(REQUEST_ID_HEADER, &id.to_string())?;
+        Ok(request)
+    }
+}
+
+/// Send a request and read the body of its response.
+async fn fetch(request: Result<web_sys::Request, JsValue>) -> Output {
+    let request = request.map_err(|error| js_error(&error))?;
+    let response = JsFuture::from(web_sys::window().unwrap().fetch_with_request(&request))
+        .await
+        .map_err(|error| js_error(&error))?
+        .dyn_into::<web_sys::Response>()
+        .map_err(|error| js_error(&error))?;
+    if !response.ok() {
+        return Err(transport_error(&format!("HTTP {}", response.status())));
+    }
+    let text = response.text().map_err(|error| js_error(&error))?;
+    JsFuture::from(text)
+        .await
+        .map_err(|error| js_error(&error))?
+        .as_string()
+        .ok_or_else(|| transport_error("la réponse n'est pas du texte"))
+}
+
+impl SimulatorBackend for Http {
+    fn submit(&self, netlist: &str) -> RequestId {
+        let id = self.requests.create();
+        let controller = web_sys::AbortController::new().ok();
+        let request = self.request(id, netlist, controller.as_ref());
+        if let Some(controller) = controller {
+            self.controllers.borrow_mut().insert(id, controller);
+        }
+        let requests = self.requests.clone();
+        let controllers = self.controllers.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let output = fetch(request).await;
+            controllers.borrow_mut().remove(&id);
+            requests.resolve(id, output);
+        });
+        id
+    }
+
+    fn result(&self, id: RequestId) -> Pending {
+        Requests::wait(&self.requests, id)
+    }
+
+    fn cancel(&self, id: RequestId) {
+        self.requests.cancel(id);
+        if let Some(controller) = self.controllers.borrow_mut().remove(&id) {
+            controller.abort();
+        }
+    }
+
+    fn progress(&self, id: RequestId) -> Option<f64> {
+        self.requests.progress(id)
+    }
+}
+
+/// A message sent to the server through the WebSocket: a netlist to simulate or the
+/// cancellation of a request.
+#[derive(Serialize)]
+struct Message<'a> {
+    id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    netlist: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    cancel: bool,
+}
+
+/// A message of the server: the output of a request, the reason why it failed or a line printed
+/// by ngspice while it simulates.
+#[derive(Deserialize)]
+struct Reply {
+    id: u64,
+    output: Option<String>,
+    error: Option<String>,
+    progress: Option<String>,
+}
+
+/// What a reply tells about a request.
+enum Update {
+    Output(Output),
+    Progress(String),
+}
+
+impl Reply {
+    fn parse(text: &str) -> Option<(RequestId, Update)> {
+        let reply = serde_json::from_str::<Self>(text).ok()?;
+        let update = match (reply.output, reply.error, reply.progress) {
+            (Some(output), _, _) => Update::Output(Ok(output)),
+            (None, None, Some(progress)) => Update::Progress(progress),
+            (None, error, _) => Update::Output(Err(transport_error(&error.unwrap_or_default()))),
+        };
+        Some((RequestId(reply.id), update))
+    }
+}
+
+/// A local ngspice server reached with a WebSocket. The messages are JSON objects:
+/// `{"id":1,"netlist":"..."}` to simulate a netlist, `{"id":1,"cancel":true}` to cancel a
+/// request, and the server answers `{"id":1,"output":"..."}` or `{"id":1,"error":"..."}`. The
+/// server can also report the progress of a request with the lines printed by ngspice
+/// (`{"id":1,"progress":"tran: 34.5%"}`). The messages submitted before the socket is open are
+/// sent once it opens, the socket is opened again if it was closed.
+pub struct WebSocket {
+    url: String,
+    requests: Rc<Requests>,
+    socket: RefCell<Option<Connection>>,
+    queue: Rc<RefCell<Vec<String>>>,
+}
+
+/// A socket and the requests sent through it that have not received their output yet. When the
+/// socket closes, only its requests fail.
+struct Connection {
+    socket: web_sys::WebSocket,
+    sent: Rc<RefCell<HashSet<RequestId>>>,
+}
+
+impl WebSocket {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            requests: Rc::new(Requests::default()),
+            socket: RefCell::new(None),
+            queue: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    fn connect(&self) -> Result<Connection, JsValue> {
+        let socket = web_sys::WebSocket::new(&self.url)?;
+        let sent = Rc::new(RefCell::new(HashSet::new()));
+        let (s, queue) = (socket.clone(), self.queue.clone());
+        let on_open = Closure::wrap(Box::new(move |_: web_sys::Event| {
+            queue.borrow_mut().drain(..).for_each(|message| {
+                let _ = s.send_with_str(&message);
+            });
+        }) as Box<dyn FnMut(web_sys::Event)>);
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        on_open.forget();
+
+        let (requests, received) = (self.requests.clone(), sent.clone());
+        let on_message = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+            match event.data().as_string().as_deref().and_then(Reply::parse) {
+                Some((id, Update::Output(output))) => {
+                    received.borrow_mut().remove(&id);
+                    requests.resolve(id, output);
+                }
+                Some((id, Update::Progress(text))) => requests.report(id, &text),
+                None => {}
+            }
+        }) as Box<dyn FnMut(web_sys::MessageEvent)>);
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        on_message.forget();
+
+        // The requests sent through a closed socket never get their output. The requests sent
+        // through the socket opened again are still pending.
+        let (requests, closed) = (self.requests.clone(), sent.clone());
+        let on_close = Closure::wrap(Box::new(move |_: web_sys::Event| {
+            let pending = requests.pending();
+            closed
+                .borrow_mut()
+                .drain()
+                .filter(|id| pending.contains(id))
+                .for_each(|id| {
+                    requests.resolve(id, Err(transport_error("le WebSocket est fermé")));
+                });
+        }) as Box<dyn FnMut(web_sys::Event)>);
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        on_close.forget();
+        Ok(Connection { socket, sent })
+    }
+
+    fn send(&self, message: &Message) -> Result<(), JsValue> {
+        let text = serde_json::to_string(message)
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        let mut socket = self.socket.borrow_mut();
+        let connection = match socket.take() {
+            Some(connection) if connection.socket.ready_state() <= web_sys::WebSocket::OPEN => {
+                socket.insert(connection)
+            }
+            _ => socket.insert(self.connect()?),
+        };
+        if message.netlist.is_some() {
+            connection.sent.borrow_mut().insert(RequestId(message.id));
+        }
+        match connection.socket.ready_state() {
+            web_sys::WebSocket::OPEN => connection.socket.send_with_str(&text),
+            _ => {
+                self.queue.borrow_mut().push(text);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl SimulatorBackend for WebSocket {
+    fn submit(&self, netlist: &str) -> RequestId {
+        let id = self.requests.create();
+        let message = Message {
+            id: id.0,
+            netlist: Some(netlist),
+            cancel: false,
+        };
+        if let Err(error) = self.send(&message) {
+            self.requests.resolve(id, Err(js_error(&error)));
+        }
+        id
+    }
+
+    fn result(&self, id: RequestId) -> Pending {
+        Requests::wait(&self.requests, id)
+    }
+
+    fn cancel(&self, id: RequestId) {
+        self.requests.cancel(id);
+        // A closed socket does not run anything anymore.
+        let is_open =
+            self.socket.borrow().as_ref().is_some_and(|connection| {
+                connection.socket.ready_state() == web_sys::WebSocket::OPEN
+            });
+        if !is_open {
+            return;
+        }
+        let _ = self.send(&Message {
+            id: id.0,
+            netlist: None,
+            cancel: true,
+        });
+    }
+
+    fn progress(&self, id: RequestId) -> Option<f64> {
+        self.requests.progress(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let message = Message {
+            id: 3,
+            netlist: Some("A Circuit\n.op\n.end"),
+            cancel: false,
+        };
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            r#"{"id":3,"netlist":"A Circuit\n.op\n.end"}"#
+        );
+        let (id, update) = Reply::parse(r#"{"id":3,"output":"a\t1e0\n"}"#).unwrap();
+        assert_eq!(id, RequestId(3));
+        assert!(matches!(update, Update::Output(Ok(output)) if output == "a\t1e0\n"));
+        let (_, update) = Reply::parse(r#"{"id":3,"progress":"tran: 50%"}"#).unwrap();
+        assert!(matches!(update, Update::Progress(text) if text == "tran: 50%"));
+        assert!(Reply::parse("not json").is_none());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::sim::backend::{transport_error, Pending, RequestId, Requests, SimulatorBackend};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A backend that returns recorded outputs instead of simulating, for the tests. The outputs
+/// are recorded by netlist (the blank lines and the spaces around the lines are ignored), a
+/// netlist without record is answered with an error.
+#[derive(Default)]
+pub struct Replay {
+    records: HashMap<String, String>,
+    requests: Rc<Requests>,
+    /// The netlists submitted so far.
+    submitted: RefCell<Vec<String>>,
+}
+
+/// The key of a netlist in the records.
+fn key(netlist: &str) -> String {
+    netlist
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+impl Replay {
+    /// Record the output of a netlist.
+    pub fn record(&mut self, netlist: &str, output: &str) {
+        self.records.insert(key(netlist), output.to_string());
+    }
+
+    pub fn submitted(&self) -> Vec<String> {
+        self.submitted.borrow().clone()
+    }
+}
+
+impl SimulatorBackend for Replay {
+    fn submit(&self, netlist: &str) -> RequestId {
+        let id = self.requests.create();
+        self.submitted.borrow_mut().push(netlist.to_string());
+        let output = match self.records.get(&key(netlist)) {
+            Some(output) => Ok(output.clone()),
+            None => Err(transport_error(
+                "aucune sortie n'est enregistrée pour cette netlist",
+            )),
+        };
+        self.requests.resolve(id, output);
+        id
+    }
+
+    fn result(&self, id: RequestId) -> Pending {
+        Requests::wait(&self.requests, id)
+    }
+
+    fn cancel(&self, id: RequestId) {
+        self.requests.cancel(id);
+    }
+
+    fn progress(&self, id: RequestId) -> Option<f64> {
+        self.requests.progress(id)
+    }
+}
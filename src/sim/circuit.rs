@@ -1,9 +1,14 @@
+use crate::intrinsics::*;
 use crate::schema::{parts, properties, wires};
-use crate::sim::verifier;
-use crate::{dom, dom::form::select, error, sim, PARTS};
+use crate::sim::{nodes, op, temperature, verifier};
+use crate::{error, sim, PARTS};
+use std::collections::{BTreeMap, HashSet};
 use std::convert::TryFrom;
 
-#[derive(PartialEq)]
+/// A copy of the parts for one simulation and the label of its swept values.
+type Combination = (Option<String>, Vec<parts::Part>);
+
+#[derive(Clone, PartialEq)]
 pub struct Connection {
     pub connector: usize,
     part: usize,
@@ -18,103 +23,336 @@ impl Connection {
 pub struct Circuit {
     wires: Vec<wires::Wire>,
     parts: Vec<parts::Part>,
-    errors: Vec<error::Error>,
+    /// The blocks that can be instantiated by the parts (and by the parts of the blocks).
+    subcircuits: Vec<parts::Subcircuit>,
+    /// The warnings found while building the circuit, they do not prevent the simulation.
+    pub diagnostics: Vec<error::Diagnostic>,
+    /// The name of each node and its wires (padding wires included).
+    nodes: Vec<(String, Vec<usize>)>,
+    anchors: Vec<(String, Point)>,
+    /// The connector of each padding wire, the padding wires are after the wires of the schema
+    /// starting at `pads_start`.
+    pads: Vec<Connection>,
+    pads_start: usize,
 }
 
 impl Circuit {
     pub fn new(
         wires: Vec<wires::Wire>,
         parts: Vec<parts::Part>,
-    ) -> Result<Self, Vec<error::Error>> {
+        subcircuits: Vec<parts::Subcircuit>,
+    ) -> Result<Self, Vec<error::Diagnostic>> {
+        let mut circuit = Self::connected(wires, parts, subcircuits);
+        let mut verifier = verifier::Verifier::check(&circuit.parts, &circuit.nodes);
+        circuit.diagnostics.append(&mut verifier.diagnostics);
+        circuit.check_models();
+        let mut diagnostics = std::mem::take(&mut circuit.diagnostics);
+        diagnostics
+            .iter_mut()
+            .for_each(|diagnostic| circuit.locate_nodes(diagnostic));
+        if diagnostics.iter().any(error::Diagnostic::is_error) {
+            Err(diagnostics)
+        } else {
+            circuit.diagnostics = diagnostics;
+            Ok(circuit)
+        }
+    }
+
+    /// The parts connected to their nodes, without any verification.
+    fn connected(
+        wires: Vec<wires::Wire>,
+        parts: Vec<parts::Part>,
+        subcircuits: Vec<parts::Subcircuit>,
+    ) -> Self {
         let mut circuit = Self {
             wires,
             parts,
-            errors: Vec::new(),
+            subcircuits,
+            diagnostics: Vec::new(),
+            nodes: Vec::new(),
+            anchors: Vec::new(),
+            pads: Vec::new(),
+            pads_start: 0,
         };
-        let mut verifier = verifier::Verifier::check(&circuit.parts, &circuit.wires);
-        circuit.errors.append(&mut verifier.errors);
         circuit.pad_parts_connectors();
         circuit.connect_parts_to_node();
-        if circuit.errors.len() == 0 {
-            Ok(circuit)
-        } else {
-            Err(circuit.errors)
+        circuit
+    }
+
+    /// The `.subckt` definition of a block. A block is not a complete circuit (it has no ground
+    /// and no probe), only its connections are checked. Its ports are its named nodes so they
+    /// keep their names in the definition.
+    fn subckt(subcircuit: &parts::Subcircuit) -> Result<String, error::Error> {
+        let invalid = |error: &dyn std::fmt::Display| {
+            Box::new(error::Sim::InvalidSubcircuit(
+                subcircuit.name.clone(),
+                error.to_string(),
+            ))
+        };
+        let block = Self::connected(
+            subcircuit.wires.clone(),
+            subcircuit.parts.clone(),
+            Vec::new(),
+        );
+        if let Some(diagnostic) = block.diagnostics.iter().find(|d| d.is_error()) {
+            return Err(invalid(diagnostic));
+        }
+        let ports = subcircuit
+            .ports()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<String>>();
+        let mut definition = format!(".subckt {} {}\n", subcircuit.name, ports.join(" "));
+        for part in block.parts.iter() {
+            let spice = part.to_spice().map_err(|error| invalid(&error))?;
+            if !spice.is_empty() {
+                definition.push_str(&spice);
+                definition.push('\n');
+            }
         }
+        definition.push_str(".ends\n");
+        Ok(definition)
+    }
+
+    /// The definitions of the blocks instantiated by the circuit. The blocks instantiated by a
+    /// block are defined before it and each block is defined once.
+    fn definitions(&self) -> Result<String, error::Error> {
+        let mut defined = Vec::new();
+        let mut definitions = String::new();
+        self.define(&self.parts, &mut Vec::new(), &mut defined, &mut definitions)?;
+        Ok(definitions)
+    }
+
+    fn define(
+        &self,
+        parts: &[parts::Part],
+        path: &mut Vec<String>,
+        defined: &mut Vec<String>,
+        definitions: &mut String,
+    ) -> Result<(), error::Error> {
+        for name in parts.iter().filter_map(parts::Subcircuit::of) {
+            if path.iter().any(|block| block == name) {
+                return Err(Box::new(error::Sim::RecursiveSubcircuit(name.to_string())));
+            }
+            if defined.iter().any(|block| block == name) {
+                continue;
+            }
+            let subcircuit = self
+                .subcircuits
+                .iter()
+                .find(|subcircuit| subcircuit.name == name)
+                .ok_or_else(|| error::Sim::SubcircuitNotFound(name.to_string()))?;
+            path.push(name.to_string());
+            self.define(&subcircuit.parts, path, defined, definitions)?;
+            path.pop();
+            definitions.push_str(&Self::subckt(subcircuit)?);
+            defined.push(name.to_string());
+        }
+        Ok(())
+    }
+
+    /// The models used by the parts, sorted by name, with the parts that use them. The parts of a
+    /// block use their models through the instances of the block.
+    fn models(&self) -> BTreeMap<String, Vec<usize>> {
+        let mut models: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        self.parts.iter().enumerate().for_each(|(idx, part)| {
+            let mut names = Vec::new();
+            self.part_models(part, &mut Vec::new(), &mut names);
+            names.into_iter().for_each(|name| {
+                let parts = models.entry(name).or_default();
+                if !parts.contains(&idx) {
+                    parts.push(idx);
+                }
+            });
+        });
+        models
+    }
+
+    fn part_models(&self, part: &parts::Part, path: &mut Vec<String>, names: &mut Vec<String>) {
+        names.extend(part.models.iter().cloned());
+        if let Some(name) = parts::Subcircuit::of(part) {
+            // A recursive block is reported when it is defined (see `define`).
+            if path.iter().any(|block| block == name) {
+                return;
+            }
+            if let Some(subcircuit) = self.subcircuits.iter().find(|s| s.name == name) {
+                path.push(name.to_string());
+                subcircuit
+                    .parts
+                    .iter()
+                    .for_each(|part| self.part_models(part, path, names));
+                path.pop();
+            }
+        }
+    }
+
+    /// A model that is not in the libraries is not in the netlist, the simulation will fail if the
+    /// part that uses it is simulated.
+    fn check_models(&mut self) {
+        let missing = self
+            .models()
+            .into_iter()
+            .filter(|(name, _)| PARTS.model(name).is_none())
+            .map(|(name, parts)| {
+                error::Diagnostic::warning(
+                    error::Sim::MissingModel(name),
+                    parts.into_iter().map(error::Entity::Part).collect(),
+                )
+            })
+            .collect::<Vec<error::Diagnostic>>();
+        self.diagnostics.extend(missing);
+    }
+
+    /// The wires of the schema that form the nodes of a diagnostic are added to its entities so
+    /// they can be highlighted. A node without wires is made of the connectors of parts that
+    /// touch each other, those parts are added instead.
+    fn locate_nodes(&self, diagnostic: &mut error::Diagnostic) {
+        let mut located = Vec::new();
+        for entity in diagnostic.entities.iter() {
+            if let error::Entity::Node(name) = entity {
+                let node = self
+                    .nodes
+                    .iter()
+                    .filter(|(node, _)| node == name)
+                    .flat_map(|(_, wires)| wires.iter().cloned());
+                let (wires, pads): (Vec<usize>, Vec<usize>) =
+                    node.partition(|&idx| idx < self.pads_start);
+                if wires.is_empty() {
+                    located.extend(
+                        self.parts_connected_to_node(&pads)
+                            .into_iter()
+                            .map(|conn| error::Entity::Part(conn.part)),
+                    );
+                } else {
+                    located.extend(wires.into_iter().map(error::Entity::Wire));
+                }
+            }
+        }
+        located.into_iter().for_each(|entity| {
+            if !diagnostic.entities.contains(&entity) {
+                diagnostic.entities.push(entity);
+            }
+        });
     }
 
     /// Here we add wires where the connectors are. This is done because when there is no wires
     /// between two parts, those parts are still connected. Adding those wire connect the parts
     /// together. If no wires were added, the parts would not be seen as connected.
     fn pad_parts_connectors(&mut self) {
-        for part in self.parts.iter() {
-            for connector in part.layout.connectors.iter() {
+        self.pads_start = self.wires.len();
+        for (part_idx, part) in self.parts.iter().enumerate() {
+            for (connector_idx, connector) in part.layout.connectors.iter().enumerate() {
                 self.wires.push(wires::Wire::new(
                     part.layout.origin + connector.origin,
                     part.layout.origin + connector.origin,
                 ));
+                self.pads.push(Connection::new(part_idx, connector_idx));
             }
         }
     }
 
-    /// Connect every part to a name node. Also check if a node is missing a connection.
+    /// Connect every part to a name node. Also check if a node is missing a connection. The names
+    /// given by the user (grounds and named nodes) are reserved first, the generated names skip
+    /// them so a node is never merged with a named node by accident.
     fn connect_parts_to_node(&mut self) {
-        self.nodes().iter().enumerate().for_each(|(idx, node)| {
-            let parts = self.parts_connected_to_node(&node);
-            // TODO: Add a check for name collision with named node. If a node is name `a`, do not
-            // give the node the generic name `a`.
-            let name = self.node_name(&parts, idx).unwrap();
-            if parts.len() < 2 {
-                self.errors
-                    .push(Box::new(error::Sim::MissingConnectionNode(name.clone())))
-            }
-            parts.iter().for_each(|conn| {
-                self.parts[conn.part].layout.connect(conn, &name);
+        let nodes = nodes::nodes(&self.wires);
+        let connections = nodes
+            .iter()
+            .map(|node| self.parts_connected_to_node(node))
+            .collect::<Vec<Vec<Connection>>>();
+        let names = connections
+            .iter()
+            .map(|parts| self.node_name(parts))
+            .collect::<Vec<Result<Option<String>, error::Sim>>>();
+        let reserved = names
+            .iter()
+            .filter_map(|name| name.as_ref().ok().cloned().flatten())
+            .collect::<HashSet<String>>();
+        let mut generated = (0..)
+            .map(sim::node_name)
+            .filter(|name| !reserved.contains(name));
+        nodes
+            .iter()
+            .zip(connections)
+            .zip(names)
+            .for_each(|((node, parts), name)| {
+                let name = match name {
+                    Ok(Some(name)) => name,
+                    Ok(None) => generated.next().unwrap(),
+                    Err(error) => {
+                        self.diagnostics.push(error::Diagnostic::error(
+                            error,
+                            parts
+                                .iter()
+                                .map(|conn| error::Entity::Part(conn.part))
+                                .collect(),
+                        ));
+                        generated.next().unwrap()
+                    }
+                };
+                match parts.len() {
+                    // The wires that are not connected to any part are not in the netlist.
+                    0 => self.diagnostics.push(error::Diagnostic::warning(
+                        error::Sim::UnconnectedWire,
+                        node.iter().cloned().map(error::Entity::Wire).collect(),
+                    )),
+                    1 => self.diagnostics.push(error::Diagnostic::error(
+                        error::Sim::MissingConnectionNode(name.clone()),
+                        vec![error::Entity::Node(name.clone())],
+                    )),
+                    _ => {}
+                }
+                parts.iter().for_each(|conn| {
+                    self.parts[conn.part].layout.connect(conn, &name);
+                });
+                self.anchors.push((name.clone(), self.node_anchor(node)));
+                self.nodes.push((name, node.clone()));
             });
-        });
     }
 
-    fn nodes(&mut self) -> Vec<Vec<wires::Wire>> {
-        let mut nodes = Vec::new();
-        let mut visited = Vec::new();
-        for (idx, wire) in self.wires.iter().enumerate() {
-            if !visited.contains(&idx) {
-                let mut node = vec![wire.clone()];
-                let other_wires = self.find_wires_for_node(&wire);
-                visited.append(&mut other_wires.clone());
-                for &other_wire in other_wires.iter() {
-                    node.push(self.wires[other_wire].clone());
-                }
-                nodes.push(node);
-            }
-        }
-        nodes
+    /// The position where the informations about a node can be shown. It is the middle of the
+    /// first wire of the node that is not a padding wire (see `pad_parts_connectors`).
+    fn node_anchor(&self, node: &[usize]) -> Point {
+        node.iter()
+            .map(|&idx| &self.wires[idx].layout.shape.points)
+            .find(|points| points.len() > 1 && points[0] != points[1])
+            .map(|points| {
+                Point::new(
+                    (points[0].x + points[1].x) / 2.0,
+                    (points[0].y + points[1].y) / 2.0,
+                )
+            })
+            .unwrap_or(self.wires[node[0]].layout.shape.points[0])
     }
 
-    fn find_wires_for_node(&self, parent: &wires::Wire) -> Vec<usize> {
-        let mut new_wires_idx = self.wires_connected_to_wire(parent);
-        let mut connected = new_wires_idx.clone();
-
-        while new_wires_idx.len() > 0 {
-            let mut new_wires = Vec::new();
-            for &wire_idx in new_wires_idx.iter() {
-                let wires = self.wires_connected_to_wire(&self.wires[wire_idx]);
-                for &next_wire_idx in wires.iter() {
-                    if !connected.contains(&next_wire_idx) {
-                        new_wires.push(next_wire_idx);
-                        connected.push(next_wire_idx);
-                    }
+    /// Where the results of an operating point analysis should be drawn. Each node is annotated
+    /// with its voltage and each voltage source (ampermeters included) with its current.
+    pub fn annotations(&self) -> op::Annotations {
+        let nodes = self
+            .anchors
+            .iter()
+            .filter(|(name, _)| name != "0")
+            .cloned()
+            .collect::<Vec<(String, Point)>>();
+        let branches = self
+            .parts
+            .iter()
+            .filter_map(|part| {
+                let spice = part.to_spice().ok()?;
+                let device = spice.split_whitespace().next()?;
+                if device.starts_with('V') || device.starts_with('v') {
+                    let position = part.layout.origin + Point::new(0.0, part.layout.size.h + 12.0);
+                    Some((device.to_string(), position))
+                } else {
+                    None
                 }
-            }
-            new_wires_idx = new_wires;
-        }
-        connected
+            })
+            .collect::<Vec<(String, Point)>>();
+        op::Annotations::new(nodes, branches)
     }
 
-    fn node_name(
-        &self,
-        connections: &Vec<Connection>,
-        node_idx: usize,
-    ) -> Result<String, error::Error> {
+    /// The name given by the user to a node (the name of its grounds and named nodes), if any.
+    fn node_name(&self, connections: &[Connection]) -> Result<Option<String>, error::Sim> {
         let mut names = Vec::new();
         for conn in connections.iter() {
             let part = &self.parts[conn.part];
@@ -128,67 +366,405 @@ impl Circuit {
                 }
             }
         }
+        // Many parts can give the same name to a node (ie: 2 grounds).
+        names.sort();
+        names.dedup();
         if names.len() > 1 {
-            Err(Box::new(error::Sim::MultipleNameOnNode(node_idx)))
-        } else if names.len() == 1 {
-            Ok(names[0].clone())
+            Err(error::Sim::MultipleNameOnNode(names.join(", ")))
         } else {
-            Ok(sim::node_name(node_idx))
+            Ok(names.pop())
+        }
+    }
+
+    /// The parts connected to a node. Each connector of a part has its own padding wire (see
+    /// `pad_parts_connectors`), a connector is connected to the node of its padding wire.
+    fn parts_connected_to_node(&self, node: &[usize]) -> Vec<Connection> {
+        node.iter()
+            .filter_map(|&idx| idx.checked_sub(self.pads_start))
+            .map(|pad| self.pads[pad].clone())
+            .collect()
+    }
+
+    pub fn to_string(
+        &self,
+        settings: &sim::settings::Settings,
+    ) -> Result<sim::Simulation, error::Error> {
+        let probes = sim::Probes::try_from(&self.parts)?;
+        let analysis = sim::Analysis::try_from((&settings.analysis, &probes, &self.parts[..]))?;
+        // The runs are counted before they are generated, each one is a copy of the parts.
+        let temperature = &settings.temperature;
+        let runs = settings
+            .montecarlo
+            .as_ref()
+            .map_or(1, |montecarlo| montecarlo.runs);
+        let count = self
+            .sweep_count()
+            .saturating_mul(temperature.temperatures.len())
+            .saturating_mul(runs);
+        if count > sim::MAX_RUNS {
+            return Err(Box::new(error::Sim::TooManyRuns(count, sim::MAX_RUNS)));
+        }
+        let mut combinations = self.sweep_combinations();
+        if let Some(montecarlo) = &settings.montecarlo {
+            combinations = Self::randomize(combinations, montecarlo.runs);
+        }
+        let definitions = self.definitions()?;
+        let models = self
+            .models()
+            .keys()
+            .filter_map(|name| PARTS.model(name))
+            .map(|model| format!("{}\n", model))
+            .collect::<String>();
+        if combinations.len() > 1 || temperature.is_swept() {
+            if let sim::Analysis::OperatingPoint(_) = analysis {
+                return Err(Box::new(error::Sim::SweepWithOperatingPoint));
+            }
+        }
+        let mut runs = Vec::new();
+        for (label, parts) in combinations.into_iter() {
+            let mut netlist = String::from("A Circuit\n");
+            for (idx, part) in parts.iter().enumerate() {
+                let spice = part.to_spice().map_err(|error| error::Diagnostic {
+                    entities: vec![error::Entity::Part(idx)],
+                    ..error::Diagnostic::from(error)
+                })?;
+                netlist.push_str(&spice);
+                netlist.push('\n');
+            }
+            // One run per temperature, the temperatures are the outermost sweep.
+            for &value in temperature.temperatures.iter() {
+                let label = match (&label, temperature.is_swept()) {
+                    (Some(label), true) => {
+                        Some(format!("{}, {}", temperature::label(value), label))
+                    }
+                    (None, true) => Some(temperature::label(value)),
+                    (_, false) => label.clone(),
+                };
+                let netlist = format!(
+                    "{}{}{}{}{}",
+                    netlist,
+                    definitions,
+                    temperature::to_spice(value),
+                    models,
+                    analysis
+                );
+                runs.push(sim::Run { label, netlist });
+            }
         }
+        let output = analysis.output();
+        let fourier = analysis.fourier();
+        let mut simulation = sim::Simulation::new(runs, probes, output);
+        simulation.fourier = fourier;
+        simulation.montecarlo = settings.montecarlo.clone();
+        simulation.engine = settings.engine.clone();
+        Ok(simulation)
     }
 
-    /// Find all the connected wires to a given one. All connected wires represent a single node.
-    ///
-    /// TODO: If 2 wires cross over each other, they are currently not returned as connected. Only
-    /// wires that the extremities are touching
-    fn wires_connected_to_wire(&self, wire: &wires::Wire) -> Vec<usize> {
-        self.wires
+    /// Replace each combination by `runs` copies of it where the values with a tolerance are
+    /// randomized (see `properties::Value::randomize`). Each copy is labelled with its number.
+    fn randomize(combinations: Vec<Combination>, runs: usize) -> Vec<Combination> {
+        combinations
+            .into_iter()
+            .flat_map(|(label, parts)| {
+                (1..=runs)
+                    .map(|run| {
+                        let mut parts = parts.clone();
+                        parts.iter_mut().for_each(|part| {
+                            part.properties
+                                .properties
+                                .values_mut()
+                                .for_each(|property| property.value = property.value.randomize());
+                        });
+                        let label = match &label {
+                            Some(label) => format!("{}, MC {}", label, run),
+                            None => format!("MC {}", run),
+                        };
+                        (Some(label), parts)
+                    })
+                    .collect::<Vec<Combination>>()
+            })
+            .collect()
+    }
+
+    /// The number of combinations of the values taken by the swept properties of the parts (see
+    /// `sweep_combinations`).
+    fn sweep_count(&self) -> usize {
+        self.parts
             .iter()
-            .enumerate()
-            .filter(|(_, other_wire)| wire.collide_with_wire(other_wire))
-            .map(|(idx, _)| idx)
-            .collect::<Vec<usize>>()
+            .flat_map(|part| part.properties.properties.values())
+            .filter_map(|property| match &property.value {
+                properties::Value::Sweep(sweep, _) => Some(sweep.count()),
+                _ => None,
+            })
+            .fold(1, usize::saturating_mul)
     }
 
-    fn parts_connected_to_node(&self, node: &Vec<wires::Wire>) -> Vec<Connection> {
-        node.iter().fold(Vec::new(), |mut acc, wire| {
-            acc.append(
-                &mut self
-                    .parts_connected_to_wire(wire)
+    /// Every combination of the values taken by the swept properties of the parts. Each
+    /// combination is a copy of the parts where the swept properties are replaced by one of their
+    /// values. If no property is swept, there is a single combination without label.
+    fn sweep_combinations(&self) -> Vec<Combination> {
+        let mut combinations = vec![(Vec::new(), self.parts.clone())];
+        for (idx, part) in self.parts.iter().enumerate() {
+            let device = part.device().unwrap_or_else(|| {
+                part.properties
+                    .get("name")
+                    .map_or(String::new(), |p| p.value.to_string())
+            });
+            let mut keys = part.properties.properties.keys().collect::<Vec<&String>>();
+            keys.sort();
+            for key in keys.into_iter() {
+                let values = match part.properties.properties[key].value.sweep_values() {
+                    Some(values) => values,
+                    None => continue,
+                };
+                let name = if key == "value" {
+                    device.clone()
+                } else {
+                    format!("{}.{}", device, key)
+                };
+                combinations = combinations
                     .into_iter()
-                    .filter(|connection| !acc.contains(connection))
-                    .collect::<Vec<Connection>>(),
-            );
-            acc
-        })
+                    .flat_map(|(labels, parts)| {
+                        values
+                            .iter()
+                            .map(|value| {
+                                let mut parts = parts.clone();
+                                if let Some(property) =
+                                    parts[idx].properties.properties.get_mut(key)
+                                {
+                                    property.value = value.clone();
+                                }
+                                let mut labels = labels.clone();
+                                labels.push(format!("{}={}", name, Self::sweep_label(value)));
+                                (labels, parts)
+                            })
+                            .collect::<Vec<(Vec<String>, Vec<parts::Part>)>>()
+                    })
+                    .collect();
+            }
+        }
+        combinations
+            .into_iter()
+            .map(|(labels, parts)| {
+                if labels.is_empty() {
+                    (None, parts)
+                } else {
+                    (Some(labels.join(", ")), parts)
+                }
+            })
+            .collect()
     }
 
-    fn parts_connected_to_wire(&self, wire: &wires::Wire) -> Vec<Connection> {
-        self.parts
+    fn sweep_label(value: &properties::Value) -> String {
+        match value {
+            properties::Value::Unit(value, unit, prefix, _) => {
+                format!("{} {}{}", value, prefix, unit)
+            }
+            _ => value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project;
+    use crate::sim::native::transient::Method;
+    use crate::sim::{montecarlo, settings};
+
+    const NETLIST: &str = "A Circuit\nV1 a 0 dc 10\nR1 a b 1k\nR2 b 0 3k\n.op\n.end";
+
+    /// The circuit of an imported netlist.
+    fn circuit(netlist: &str) -> Circuit {
+        let ((wires, parts, subcircuits, _), _) = project::spice::from_spice(netlist).unwrap();
+        Circuit::new(wires, parts, subcircuits).unwrap()
+    }
+
+    fn settings(analysis: settings::Analysis) -> settings::Settings {
+        settings::Settings {
+            analysis,
+            temperature: temperature::Settings {
+                temperatures: vec![temperature::NOMINAL],
+            },
+            montecarlo: None,
+            engine: sim::Engine::Native,
+            timeout: None,
+        }
+    }
+
+    /// A block with a dipole of type `typ` between the ports `a` and `b`, and instances of other
+    /// blocks.
+    fn block(name: &str, typ: &str, instances: &[&parts::Subcircuit]) -> parts::Subcircuit {
+        let dipole = PARTS.get(typ).unwrap();
+        let mut parts = dipole
+            .layout
+            .connectors
             .iter()
-            .enumerate()
-            .fold(Vec::new(), |mut acc, (idx, part)| {
-                acc.append(
-                    &mut wire
-                        .collide_with_part(part)
-                        .iter()
-                        .map(|conn_idx| Connection::new(idx, *conn_idx))
-                        .collect::<Vec<Connection>>(),
-                );
-                acc
+            .zip(["a", "b"])
+            .map(|(connector, port)| {
+                let mut node = PARTS.get("lumped.node").unwrap();
+                node.properties.properties.get_mut("name").unwrap().value =
+                    properties::Value::from(port);
+                node.layout.origin = connector.origin - node.layout.connectors[0].origin;
+                node
             })
+            .collect::<Vec<parts::Part>>();
+        parts.push(dipole);
+        parts.extend(
+            instances
+                .iter()
+                .enumerate()
+                .map(|(idx, block)| block.instance(&format!("x{}", idx))),
+        );
+        parts::Subcircuit::new(name, Vec::new(), parts)
+    }
+
+    #[test]
+    fn blocks_are_defined_once() {
+        let inner = block("inner", "lumped.resistor", &[]);
+        let outer = block("outer", "lumped.resistor", &[&inner, &inner]);
+        let parts = vec![outer.instance("o1"), inner.instance("i1")];
+        let circuit = Circuit::connected(Vec::new(), parts, vec![inner, outer]);
+        let definitions = circuit.definitions().unwrap();
+        assert_eq!(definitions.matches(".subckt inner a b\n").count(), 1);
+        let inner = definitions.find(".subckt inner").unwrap();
+        let outer = definitions.find(".subckt outer").unwrap();
+        assert!(inner < outer);
+        // The instances of `outer` call `inner`.
+        assert_eq!(definitions.matches(" inner\n").count(), 2);
+    }
+
+    #[test]
+    fn recursive_blocks() {
+        let inner = block("loop", "lumped.resistor", &[]);
+        let looped = block("loop", "lumped.resistor", &[&inner]);
+        let circuit = Circuit::connected(Vec::new(), vec![looped.instance("l1")], vec![looped]);
+        let error = circuit.definitions().unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<error::Sim>(),
+            Some(error::Sim::RecursiveSubcircuit(name)) if name == "loop"
+        ));
+        let circuit = Circuit::connected(Vec::new(), vec![inner.instance("l1")], Vec::new());
+        assert!(circuit.definitions().is_err());
+    }
+
+    #[test]
+    fn used_models() {
+        let ((wires, mut parts, _, _), _) = project::spice::from_spice(NETLIST).unwrap();
+        // The models of the blocks are used through their instances.
+        let clamp = block("clamp", "non_linear.diode-1N4148", &[]);
+        parts.push(PARTS.get("non_linear.transistor-pnp").unwrap());
+        parts.push(clamp.instance("c1"));
+        parts.push(clamp.instance("c2"));
+        let circuit = Circuit::connected(wires, parts, vec![clamp]);
+        let simulation = circuit
+            .to_string(&settings(settings::Analysis::OperatingPoint))
+            .unwrap();
+        let models = simulation.runs[0]
+            .netlist
+            .lines()
+            .filter(|line| line.starts_with(".model"))
+            .map(|line| line.split_whitespace().nth(1).unwrap().to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(models, vec!["diode-1N4148", "transistor-pnp"]);
+    }
+
+    #[test]
+    fn netlist_from_settings() {
+        let circuit = circuit(NETLIST);
+        let simulation = circuit
+            .to_string(&settings(settings::Analysis::OperatingPoint))
+            .unwrap();
+        assert_eq!(simulation.runs.len(), 1);
+        let netlist = &simulation.runs[0].netlist;
+        assert!(netlist.ends_with(".temp 27\n.options NOACCT\n.op\n.end"));
+        assert!(simulation.engine == sim::Engine::Native);
+        assert!(sim::native::simulate(netlist).is_ok());
     }
 
-    pub fn to_string(&self) -> Result<(String, sim::Probes), error::Error> {
-        let mut parts = String::from("A Circuit\n");
-        for part in self.parts.iter() {
-            parts.push_str(&part.to_spice()?);
-            parts.push_str("\n");
+    fn transient() -> settings::Analysis {
+        settings::Analysis::Transient(settings::Transient {
+            step: properties::Value::from(1e-6),
+            stop: properties::Value::from(1e-3),
+            method: Method::Euler,
+            fourier: None,
+        })
+    }
+
+    #[test]
+    fn transient_settings() {
+        let circuit = circuit(NETLIST);
+        let mut settings = settings(transient());
+        settings.temperature.temperatures = vec![-40.0, 85.0];
+        let simulation = circuit.to_string(&settings).unwrap();
+        let labels = simulation
+            .runs
+            .iter()
+            .map(|run| run.label.clone().unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(labels, vec!["T=-40 °C", "T=85 °C"]);
+        let netlist = &simulation.runs[1].netlist;
+        assert!(netlist.contains(".temp 85\n"));
+        assert!(netlist.contains(".options NOACCT method=gear maxord=1\n.tran 0.000001 0.001"));
+        // The default method of ngspice is not written.
+        if let settings::Analysis::Transient(transient) = &mut settings.analysis {
+            transient.method = Method::Trapezoidal;
         }
+        let netlist = &circuit.to_string(&settings).unwrap().runs[0].netlist;
+        assert!(netlist.contains(".options NOACCT\n.tran 0.000001 0.001 uic"));
+    }
 
-        let probes = sim::Probes::try_from(&self.parts)?;
-        let simulation = select::value::<String>(dom::select("[name=\"sim__type\"]"))?;
-        let analysis = sim::Analysis::try_from((simulation, &probes))?;
-        Ok((format!("{}{}{}", parts, PARTS.models(), analysis), probes))
+    /// Replace the value of the part named `name` by a sweep.
+    fn sweep(circuit: &mut Circuit, name: &str, sweep: properties::Sweep) {
+        let part = circuit
+            .parts
+            .iter_mut()
+            .find(|part| part.properties.get("name").unwrap().value.to_string() == name)
+            .unwrap();
+        part.properties.properties.get_mut("value").unwrap().value =
+            properties::Value::Sweep(sweep, None);
+    }
+
+    #[test]
+    fn sweep_combinations() {
+        let mut circuit = circuit(NETLIST);
+        sweep(
+            &mut circuit,
+            "R1",
+            properties::Sweep::List(vec![1.0, 2.0, 3.0]),
+        );
+        sweep(
+            &mut circuit,
+            "R2",
+            properties::Sweep::Range(1.0, 100.0, 1.0),
+        );
+        assert_eq!(circuit.sweep_count(), 300);
+        assert_eq!(circuit.sweep_combinations().len(), 300);
+        let mut settings = settings(transient());
+        settings.temperature.temperatures = vec![0.0, 27.0, 50.0];
+        assert_eq!(circuit.to_string(&settings).unwrap().runs.len(), 900);
+        // The runs are not generated above the maximum, the temperatures multiply them.
+        settings.temperature.temperatures = vec![0.0, 27.0, 50.0, 85.0];
+        let error = circuit.to_string(&settings).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<error::Sim>(),
+            Some(error::Sim::TooManyRuns(1200, sim::MAX_RUNS))
+        ));
+        sweep(
+            &mut circuit,
+            "R2",
+            properties::Sweep::Range(0.0, 1e12, 1e-3),
+        );
+        assert!(circuit.to_string(&settings).is_err());
+        // And so do the Monte Carlo runs.
+        sweep(&mut circuit, "R2", properties::Sweep::List(vec![1.0, 2.0]));
+        settings.temperature.temperatures = vec![27.0];
+        settings.montecarlo =
+            montecarlo::Settings::parse("200", String::from("v(b)"), "avg").unwrap();
+        let error = circuit.to_string(&settings).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<error::Sim>(),
+            Some(error::Sim::TooManyRuns(1200, sim::MAX_RUNS))
+        ));
     }
 }
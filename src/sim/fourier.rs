@@ -0,0 +1,190 @@
+use crate::intrinsics::*;
+use crate::{error, plot, unit};
+
+/// The number of harmonics computed (the fundamental included), the same as ngspice.
+const HARMONIC_COUNT: usize = 9;
+/// The number of points used to sample the last period of the waveform.
+const SAMPLE_COUNT: usize = 200;
+
+/// Which probe should be analysed after a transient analysis and the frequency of its
+/// fundamental.
+#[derive(Clone)]
+pub struct Settings {
+    pub probe: String,
+    pub fundamental: f64,
+}
+
+impl Settings {
+    /// Parse the probe and the fundamental of the transient settings. If no probe is selected,
+    /// there is no fourier analysis.
+    pub fn parse(probe: String, fundamental: Option<f64>) -> Result<Option<Self>, error::Error> {
+        if probe.is_empty() {
+            return Ok(None);
+        }
+        match fundamental {
+            Some(fundamental) if fundamental > 0.0 => Ok(Some(Self { probe, fundamental })),
+            _ => Err(Box::new(error::Sim::InvalidFundamental)),
+        }
+    }
+}
+
+/// A component of the fourier series of a waveform. The order 0 is the DC component.
+pub struct Harmonic {
+    pub order: usize,
+    pub frequency: f64,
+    pub magnitude: f64,
+    /// The phase in degrees.
+    pub phase: f64,
+}
+
+/// The harmonics of a waveform and its total harmonic distortion (in %). Like ngspice, only the
+/// last period of the waveform is analysed.
+pub struct Fourier {
+    pub label: String,
+    pub harmonics: Vec<Harmonic>,
+    pub thd: f64,
+}
+
+impl Fourier {
+    /// Compute the harmonics of a series. Returns `None` if the series is shorter than a period
+    /// of the fundamental.
+    pub fn compute(label: String, points: &[Point], fundamental: f64) -> Option<Self> {
+        let period = 1.0 / fundamental;
+        let end = points.last()?.x;
+        let start = end - period;
+        if points[0].x > start + period * 1e-6 {
+            return None;
+        }
+        let samples = (0..SAMPLE_COUNT)
+            .map(|idx| interpolate(points, start + period * idx as f64 / SAMPLE_COUNT as f64))
+            .collect::<Vec<f64>>();
+        let harmonics = (0..=HARMONIC_COUNT)
+            .map(|order| {
+                let (re, im) =
+                    samples
+                        .iter()
+                        .enumerate()
+                        .fold((0.0, 0.0), |(re, im), (idx, sample)| {
+                            let angle = 2.0 * std::f64::consts::PI * (order * idx) as f64
+                                / SAMPLE_COUNT as f64;
+                            (re + sample * angle.cos(), im + sample * angle.sin())
+                        });
+                let factor = if order == 0 { 1.0 } else { 2.0 } / SAMPLE_COUNT as f64;
+                let component = Complex::new(re * factor, im * factor);
+                Harmonic {
+                    order,
+                    frequency: fundamental * order as f64,
+                    magnitude: component.norm(),
+                    phase: component.phase(),
+                }
+            })
+            .collect::<Vec<Harmonic>>();
+        let distortion = harmonics[2..]
+            .iter()
+            .map(|harmonic| harmonic.magnitude.powi(2))
+            .sum::<f64>()
+            .sqrt();
+        let thd = if harmonics[1].magnitude > 0.0 {
+            distortion / harmonics[1].magnitude * 100.0
+        } else {
+            0.0
+        };
+        Some(Self {
+            label,
+            harmonics,
+            thd,
+        })
+    }
+}
+
+impl Fourier {
+    /// The harmonics as a table with the total harmonic distortion in the title.
+    pub fn table(&self) -> plot::Table {
+        plot::Table {
+            title: format!("{} (THD : {:.3} %)", self.label, self.thd),
+            headers: ["#", "Fréquence", "Amplitude", "Phase"]
+                .iter()
+                .map(|header| header.to_string())
+                .collect(),
+            rows: self
+                .harmonics
+                .iter()
+                .map(|harmonic| {
+                    vec![
+                        harmonic.order.to_string(),
+                        unit::format(harmonic.frequency, &unit::Unit::Hertz),
+                        format!("{:.4e}", harmonic.magnitude),
+                        format!("{:.2}°", harmonic.phase),
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The value of a series at `x`, linearly interpolated between the closest points.
+fn interpolate(points: &[Point], x: f64) -> f64 {
+    let idx = points.partition_point(|point| point.x < x);
+    if idx == 0 {
+        points[0].y
+    } else if idx >= points.len() {
+        points[points.len() - 1].y
+    } else {
+        let (a, b) = (points[idx - 1], points[idx]);
+        if b.x == a.x {
+            b.y
+        } else {
+            a.y + (b.y - a.y) * (x - a.x) / (b.x - a.x)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `sin(ωt) + 0.1·sin(3ωt)` at 1 kHz sampled over `periods` periods.
+    fn signal(periods: f64) -> Vec<Point> {
+        let omega = 2.0 * std::f64::consts::PI * 1e3;
+        let count = (periods * 10_000.0) as usize;
+        (0..=count)
+            .map(|idx| {
+                let t = idx as f64 * 1e-7;
+                Point::new(t, (omega * t).sin() + 0.1 * (3.0 * omega * t).sin())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn harmonics_of_a_distorted_sine() {
+        let fourier = Fourier::compute(String::from("out"), &signal(2.0), 1e3).unwrap();
+        assert_eq!(fourier.harmonics.len(), HARMONIC_COUNT + 1);
+        assert!(fourier.harmonics[0].magnitude < 1e-6);
+        assert!((fourier.harmonics[1].magnitude - 1.0).abs() < 1e-4);
+        assert!(fourier.harmonics[2].magnitude < 1e-6);
+        assert_eq!(fourier.harmonics[3].frequency, 3e3);
+        assert!((fourier.harmonics[3].magnitude - 0.1).abs() < 1e-4);
+        assert!((fourier.thd - 10.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn shorter_than_a_period_is_none() {
+        assert!(Fourier::compute(String::from("out"), &signal(0.5), 1e3).is_none());
+        assert!(Fourier::compute(String::from("out"), &[], 1e3).is_none());
+    }
+
+    #[test]
+    fn interpolate_between_the_points() {
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 2.0),
+            Point::new(3.0, -2.0),
+        ];
+        assert_eq!(interpolate(&points, 0.5), 1.0);
+        assert_eq!(interpolate(&points, 1.0), 2.0);
+        assert_eq!(interpolate(&points, 2.5), -1.0);
+        // Outside of the series, the closest point is used.
+        assert_eq!(interpolate(&points, -1.0), 0.0);
+        assert_eq!(interpolate(&points, 4.0), -2.0);
+    }
+}
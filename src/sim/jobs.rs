@@ -0,0 +1,276 @@
+use crate::sim::backend::RequestId;
+
+/// The number of jobs that are done that are still shown.
+const HISTORY: usize = 10;
+
+/// The state of a job.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    Queued,
+    Running,
+    Finished,
+    Failed(String),
+    Cancelled,
+    TimedOut,
+}
+
+impl Status {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Queued => "En attente",
+            Self::Running => "En cours",
+            Self::Finished => "Terminé",
+            Self::Failed(_) => "Échec",
+            Self::Cancelled => "Annulé",
+            Self::TimedOut => "Délai dépassé",
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        !matches!(self, Self::Queued | Self::Running)
+    }
+}
+
+/// A run of a simulation (see `sim::Run`) sent to the simulator.
+pub struct Job {
+    pub label: String,
+    pub status: Status,
+    /// The request of the run once it is sent to the simulator.
+    request: Option<RequestId>,
+    /// The progress of the run between 0 and 1, when the simulator reports it.
+    pub progress: Option<f64>,
+    /// When the run was sent and when it was done, in milliseconds (see `js_sys::Date::now`).
+    started: Option<f64>,
+    ended: Option<f64>,
+}
+
+impl Job {
+    fn new(label: String) -> Self {
+        Self {
+            label,
+            status: Status::Queued,
+            request: None,
+            progress: None,
+            started: None,
+            ended: None,
+        }
+    }
+
+    /// The time spent by the simulator on the run in seconds.
+    pub fn elapsed(&self, now: f64) -> Option<f64> {
+        let started = self.started?;
+        Some((self.ended.unwrap_or(now) - started).max(0.0) / 1000.0)
+    }
+
+    fn end(&mut self, status: Status, now: f64) {
+        if self.started.is_some() {
+            self.ended = Some(now);
+        }
+        self.status = status;
+    }
+}
+
+/// The runs of the simulations. The runs of a simulation are queued and sent to the simulator one
+/// after the other, the last jobs that are done are kept to show how they ended. The time is
+/// given to every method (in milliseconds) so the jobs do not depend on the browser.
+#[derive(Default)]
+pub struct Jobs {
+    jobs: Vec<Job>,
+    /// The longest time a run can take in seconds, it is cancelled once it is exceeded.
+    timeout: Option<f64>,
+}
+
+impl Jobs {
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter()
+    }
+
+    /// Queue the runs of a new simulation, the jobs of the previous simulation that are not done
+    /// are cancelled.
+    pub fn queue(&mut self, labels: Vec<String>, timeout: Option<f64>, now: f64) {
+        self.cancel(now);
+        let done = self.jobs.len().saturating_sub(HISTORY);
+        self.jobs.drain(..done);
+        self.jobs.extend(labels.into_iter().map(Job::new));
+        self.timeout = timeout;
+    }
+
+    /// Start the next queued job with the request of its run.
+    pub fn start(&mut self, request: RequestId, now: f64) {
+        if let Some(job) = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.status == Status::Queued)
+        {
+            job.status = Status::Running;
+            job.request = Some(request);
+            job.started = Some(now);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.jobs.iter().any(|job| !job.status.is_done())
+    }
+
+    fn running_mut(&mut self, request: RequestId) -> Option<&mut Job> {
+        self.jobs
+            .iter_mut()
+            .find(|job| job.status == Status::Running && job.request == Some(request))
+    }
+
+    /// End the job of a request with the error of its output, if any. A request without a
+    /// running job is stale (its job was cancelled), `false` is then returned. The runs that
+    /// follow a failed run are cancelled.
+    pub fn finish(&mut self, request: RequestId, error: Option<String>, now: f64) -> bool {
+        let job = match self.running_mut(request) {
+            Some(job) => job,
+            None => return false,
+        };
+        job.progress = None;
+        match error {
+            Some(error) => {
+                job.end(Status::Failed(error), now);
+                self.cancel(now);
+            }
+            None => job.end(Status::Finished, now),
+        }
+        true
+    }
+
+    /// Update the progress of the job of a request.
+    pub fn report(&mut self, request: RequestId, progress: f64) {
+        if let Some(job) = self.running_mut(request) {
+            job.progress = Some(progress);
+        }
+    }
+
+    /// Cancel the running job and the queued jobs.
+    pub fn cancel(&mut self, now: f64) {
+        self.jobs
+            .iter_mut()
+            .filter(|job| !job.status.is_done())
+            .for_each(|job| job.end(Status::Cancelled, now));
+    }
+
+    /// End the running job if it exceeded the timeout, the queued jobs are then cancelled. The
+    /// request of the job is returned to be cancelled.
+    pub fn check_timeout(&mut self, now: f64) -> Option<RequestId> {
+        let timeout = self.timeout?;
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.status == Status::Running)
+            .filter(|job| job.elapsed(now).is_some_and(|elapsed| elapsed > timeout))?;
+        job.end(Status::TimedOut, now);
+        let request = job.request;
+        self.cancel(now);
+        request
+    }
+
+    pub fn timeout(&self) -> Option<f64> {
+        self.timeout
+    }
+}
+
+/// The progress (between 0 and 1) printed by ngspice, its last percentage (ie: `tran: 34.5%` or
+/// `Reference value : 1.00e-03 ... 12%`).
+pub fn parse_progress(text: &str) -> Option<f64> {
+    text.rmatch_indices('%').find_map(|(idx, _)| {
+        let before = text[..idx].trim_end();
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|&(_, c)| !(c.is_ascii_digit() || c == '.'))
+            .map_or(0, |(idx, c)| idx + c.len_utf8());
+        before[start..]
+            .parse::<f64>()
+            .ok()
+            .map(|percent| (percent / 100.0).clamp(0.0, 1.0))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(count: usize, timeout: Option<f64>) -> Jobs {
+        let mut jobs = Jobs::default();
+        let labels = (0..count).map(|idx| format!("R1={}", idx)).collect();
+        jobs.queue(labels, timeout, 0.0);
+        jobs
+    }
+
+    fn statuses(jobs: &Jobs) -> Vec<Status> {
+        jobs.iter().map(|job| job.status.clone()).collect()
+    }
+
+    #[test]
+    fn runs_are_started_in_order() {
+        let mut jobs = queued(2, None);
+        jobs.start(RequestId(0), 1000.0);
+        assert_eq!(statuses(&jobs), vec![Status::Running, Status::Queued]);
+        jobs.report(RequestId(0), 0.5);
+        assert_eq!(jobs.iter().next().unwrap().progress, Some(0.5));
+        assert!(jobs.finish(RequestId(0), None, 3500.0));
+        jobs.start(RequestId(1), 3500.0);
+        assert!(jobs.finish(RequestId(1), Some(String::from("no data")), 4000.0));
+        assert_eq!(
+            statuses(&jobs),
+            vec![Status::Finished, Status::Failed(String::from("no data"))]
+        );
+        assert_eq!(jobs.iter().next().unwrap().elapsed(9000.0), Some(2.5));
+        assert!(!jobs.is_active());
+    }
+
+    #[test]
+    fn cancelled_runs_are_stale() {
+        let mut jobs = queued(2, None);
+        jobs.start(RequestId(4), 0.0);
+        jobs.cancel(200.0);
+        assert_eq!(statuses(&jobs), vec![Status::Cancelled, Status::Cancelled]);
+        assert!(!jobs.finish(RequestId(4), None, 300.0));
+        // A new simulation does not take the output of the previous one.
+        jobs.queue(vec![String::from("run")], None, 400.0);
+        jobs.start(RequestId(5), 400.0);
+        assert!(!jobs.finish(RequestId(4), None, 500.0));
+        assert!(jobs.finish(RequestId(5), None, 500.0));
+    }
+
+    #[test]
+    fn timeout() {
+        let mut jobs = queued(2, Some(2.0));
+        jobs.start(RequestId(0), 0.0);
+        assert_eq!(jobs.check_timeout(1500.0), None);
+        assert_eq!(jobs.check_timeout(2500.0), Some(RequestId(0)));
+        assert_eq!(statuses(&jobs), vec![Status::TimedOut, Status::Cancelled]);
+        assert_eq!(jobs.check_timeout(5000.0), None);
+        let mut jobs = queued(1, None);
+        jobs.start(RequestId(0), 0.0);
+        assert_eq!(jobs.check_timeout(1e9), None);
+    }
+
+    #[test]
+    fn history() {
+        let mut jobs = Jobs::default();
+        (0..3 * HISTORY).for_each(|idx| {
+            jobs.queue(vec![String::from("run")], None, 0.0);
+            jobs.start(RequestId(idx as u64), 0.0);
+        });
+        assert!(jobs.iter().count() <= HISTORY + 1);
+        let running = jobs.iter().find(|job| job.status == Status::Running);
+        assert_eq!(running.unwrap().request, Some(RequestId(29)));
+    }
+
+    #[test]
+    fn progress() {
+        assert_eq!(parse_progress("tran: 34.5%"), Some(0.345));
+        assert_eq!(parse_progress("tran: 10% tran: 20 %"), Some(0.2));
+        assert_eq!(parse_progress("100%"), Some(1.0));
+        assert_eq!(parse_progress("Reference value : 1.0e-03"), None);
+        assert_eq!(parse_progress("% done"), None);
+        // The character before the percentage is not a single byte.
+        assert_eq!(parse_progress("é5%"), Some(0.05));
+        assert_eq!(parse_progress("transitoire : 50 % réalisé"), Some(0.5));
+        assert_eq!(parse_progress("é%"), None);
+    }
+}
@@ -1,17 +1,32 @@
+pub mod backend;
 pub mod circuit;
 pub mod dialog;
+pub mod fourier;
+pub mod jobs;
+pub mod montecarlo;
+pub mod native;
+pub mod netlist;
+pub mod nodes;
+pub mod op;
+pub mod pz;
+pub mod settings;
+pub mod temperature;
+pub mod tf;
 pub mod verifier;
+use crate::intrinsics::*;
 use crate::schema::{parts, properties};
-use crate::{
-    dom,
-    dom::form::{select, text_input},
-    error,
-};
+use crate::{error, plot};
 
+/// The generated name of a node: `a` to `z`, then `aa`, `ab`, ..., `zz`, `aaa`, ...
 pub fn node_name(idx: usize) -> String {
-    (0..(idx / 26) + 1)
-        .map(|i| ((idx - 26 * i) as u8 + 97u8) as char)
-        .collect::<String>()
+    let mut idx = idx + 1;
+    let mut name = Vec::new();
+    while idx > 0 {
+        idx -= 1;
+        name.push(b'a' + (idx % 26) as u8);
+        idx /= 26;
+    }
+    name.iter().rev().map(|&letter| letter as char).collect()
 }
 
 pub struct Probe {
@@ -40,6 +55,22 @@ impl std::convert::TryFrom<&parts::Part> for Probe {
     }
 }
 
+impl Probe {
+    /// The nodes of a voltmeter (`v(a,b)` gives `a` and `b`).
+    fn nodes(&self) -> Option<(String, String)> {
+        let nodes = self.spice.strip_prefix("v(")?.strip_suffix(')')?;
+        let mut nodes = nodes.split(',');
+        Some((nodes.next()?.to_string(), nodes.next()?.to_string()))
+    }
+
+    /// The real and the imaginary parts of the probe (`vr(a,b) vi(a,b)` or `ir(V1) ii(V1)`) used
+    /// by an AC analysis.
+    fn complex(&self) -> String {
+        let (typ, nodes) = self.spice.split_at(1);
+        format!("{}r{} {}i{}", typ, nodes, typ, nodes)
+    }
+}
+
 pub struct Probes {
     voltmeters: Vec<Probe>,
     ampermeters: Vec<Probe>,
@@ -52,6 +83,29 @@ impl Probes {
             ampermeters,
         }
     }
+
+    /// Find a probe (voltmeter or ampermeter) with its name.
+    pub fn get(&self, name: &str) -> Option<&Probe> {
+        self.ampermeters
+            .iter()
+            .chain(self.voltmeters.iter())
+            .find(|probe| probe.name == name)
+    }
+
+    /// Find a voltmeter with its name.
+    pub fn voltmeter(&self, name: &str) -> Option<&Probe> {
+        self.voltmeters.iter().find(|probe| probe.name == name)
+    }
+
+    /// The probes for an AC analysis, every probe is printed as 2 columns (real and imaginary).
+    pub fn to_complex_string(&self) -> String {
+        self.ampermeters
+            .iter()
+            .chain(self.voltmeters.iter())
+            .map(|probe| probe.complex())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
 }
 
 impl std::ops::Index<usize> for Probes {
@@ -59,7 +113,7 @@ impl std::ops::Index<usize> for Probes {
 
     fn index(&self, idx: usize) -> &Self::Output {
         if idx >= self.ampermeters.len() {
-            &self.voltmeters[idx]
+            &self.voltmeters[idx - self.ampermeters.len()]
         } else {
             &self.ampermeters[idx]
         }
@@ -107,19 +161,266 @@ impl std::fmt::Display for Probes {
     }
 }
 
+/// Generate the values of a sweep the same way ngspice does, from `start` to `stop` (included)
+/// by increment of `step`.
+pub fn sweep_values(start: f64, stop: f64, step: f64) -> Vec<f64> {
+    (0..sweep_count(start, stop, step))
+        .map(|idx| start + step * idx as f64)
+        .collect()
+}
+
+/// The number of values of a sweep, without generating them.
+pub fn sweep_count(start: f64, stop: f64, step: f64) -> usize {
+    if step == 0.0 || (stop - start) / step < 0.0 {
+        return 1;
+    }
+    (((stop - start) / step + 1e-9).floor() as usize).saturating_add(1)
+}
+
+/// How the output of ngspice must be interpreted. It depends on the analysis that was run.
+pub enum Output {
+    /// A table of values where the first column is the x axis and every other column is a probe.
+    Series,
+    /// The voltage of every node and the current of every voltage source.
+    OperatingPoint,
+    /// A table of values that contains a curve for each value of an outer sweep. The device that
+    /// is swept by the outer sweep and its values are kept to label the curves.
+    Family(String, Vec<f64>),
+    /// A table of complex values (real and imaginary columns) for every probe that is shown as a
+    /// bode plot (magnitude and phase).
+    Bode,
+    /// The output and the input referred noise spectral densities. The input source is kept to
+    /// know the unit of the input noise.
+    Noise(String),
+    /// The gain, the input and the output resistances of a transfer function analysis.
+    TransferFunction,
+    /// The poles and the zeros of a pole-zero analysis.
+    PoleZero,
+}
+
+/// The maximum number of runs of a simulation: a run per combination of the swept properties, per
+/// temperature and per Monte Carlo run.
+pub const MAX_RUNS: usize = 1000;
+
+/// The address of the local ngspice server when none is given in the settings.
+pub const DEFAULT_SERVER: &str = "localhost:8080/ngspice";
+
+/// The simulator that runs the netlists (see `backend::create`).
+#[derive(Clone, PartialEq)]
+pub enum Engine {
+    /// ngspice, called through the JavaScript of the page (see `backend::Bridge`).
+    Ngspice,
+    /// The simulator of the crate (see `native::simulate_in_chunks`), it only supports some parts.
+    Native,
+    /// A local ngspice server reached with HTTP at an URL.
+    Http(String),
+    /// A local ngspice server reached with a WebSocket at an URL.
+    WebSocket(String),
+}
+
+/// A single netlist sent to ngspice. When a property of a part is swept, there is a run for each
+/// value of the property and the label contains the value used (ie: `R1=10 kΩ`).
+pub struct Run {
+    pub label: Option<String>,
+    pub netlist: String,
+}
+
+/// Everything that is needed to run a simulation and to read its results. The runs are sent to
+/// ngspice one after the other and the results of every run are merged together.
+pub struct Simulation {
+    pub runs: Vec<Run>,
+    pub probes: Probes,
+    pub output: Output,
+    /// The fourier analysis to do on the results of a transient analysis.
+    pub fourier: Option<fourier::Settings>,
+    /// The results that are shown as tables (ie: the harmonics of each run).
+    pub tables: Vec<plot::Table>,
+    /// The Monte Carlo analysis and the value measured after each run.
+    pub montecarlo: Option<montecarlo::Settings>,
+    pub measurements: Vec<f64>,
+    pub engine: Engine,
+    current: usize,
+    y_labels: Vec<String>,
+    series: Vec<Vec<Point>>,
+}
+
+impl Simulation {
+    pub fn new(runs: Vec<Run>, probes: Probes, output: Output) -> Self {
+        Self {
+            runs,
+            probes,
+            output,
+            fourier: None,
+            tables: Vec::new(),
+            montecarlo: None,
+            measurements: Vec::new(),
+            engine: Engine::Ngspice,
+            current: 0,
+            y_labels: Vec::new(),
+            series: Vec::new(),
+        }
+    }
+
+    /// The netlist of the run that is waiting for a response of ngspice.
+    pub fn netlist(&self) -> Option<&str> {
+        self.runs.get(self.current).map(|run| &run.netlist[..])
+    }
+
+    /// Add the label of the current run (if there is one) to the label of a series.
+    fn run_label(&self, y_label: String) -> String {
+        match self
+            .runs
+            .get(self.current)
+            .and_then(|run| run.label.as_ref())
+        {
+            Some(label) => format!("{} [{}]", y_label, label),
+            None => y_label,
+        }
+    }
+
+    /// Add a table of results, the title of the table contains the label of the current run.
+    pub fn push_table(&mut self, mut table: plot::Table) {
+        table.title = self.run_label(table.title);
+        self.tables.push(table);
+    }
+
+    /// Compute the harmonics of the probe selected for the fourier analysis with the series of
+    /// the current run.
+    pub fn analyse_fourier(
+        &mut self,
+        y_labels: &[String],
+        series: &[Vec<Point>],
+    ) -> Result<(), error::Error> {
+        if let Some(settings) = &self.fourier {
+            if let Some(idx) = y_labels.iter().position(|label| *label == settings.probe) {
+                let label = self.run_label(settings.probe.clone());
+                match fourier::Fourier::compute(label, &series[idx], settings.fundamental) {
+                    Some(result) => self.tables.push(result.table()),
+                    None => return Err(Box::new(error::Sim::FourierTooShort)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Measure the series of the probe selected for the Monte Carlo analysis with the series of
+    /// the current run. The series of an AC analysis are labelled with their unit (ie: `v1 (dB)`),
+    /// the first one is used.
+    pub fn measure(&mut self, y_labels: &[String], series: &[Vec<Point>]) {
+        if let Some(settings) = &self.montecarlo {
+            let prefix = format!("{} (", settings.probe);
+            let idx = y_labels
+                .iter()
+                .position(|label| *label == settings.probe || label.starts_with(&prefix));
+            if let Some(value) = idx.and_then(|idx| settings.measure.measure(&series[idx])) {
+                self.measurements.push(value);
+            }
+        }
+    }
+
+    /// The histogram of the values measured by the Monte Carlo analysis.
+    pub fn histogram(&self) -> Result<Option<plot::histogram::Histogram>, error::Error> {
+        match &self.montecarlo {
+            Some(settings) => {
+                let title = format!("{} : {}", settings.probe, settings.measure.name());
+                match plot::histogram::Histogram::compute(title, &self.measurements) {
+                    Some(histogram) => Ok(Some(histogram)),
+                    None => Err(Box::new(error::Sim::NoMonteCarloMeasurement(
+                        settings.probe.clone(),
+                    ))),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Add the series of the current run to the results and move to the next run. Each series is
+    /// labelled with the label of its run. Once every run is done, the merged results are
+    /// returned (x_label, y_labels, series).
+    pub fn push_results(
+        &mut self,
+        x_label: String,
+        y_labels: Vec<String>,
+        series: Vec<Vec<Point>>,
+    ) -> Option<(String, Vec<String>, Vec<Vec<Point>>)> {
+        y_labels.into_iter().for_each(|y_label| {
+            let y_label = self.run_label(y_label);
+            self.y_labels.push(y_label);
+        });
+        self.series.extend(series);
+        self.current += 1;
+        if self.current < self.runs.len() {
+            None
+        } else {
+            Some((
+                x_label,
+                std::mem::take(&mut self.y_labels),
+                std::mem::take(&mut self.series),
+            ))
+        }
+    }
+}
+
 pub enum Analysis<'probes> {
     Transiant(TransiantAnalysis<'probes>),
     Frequency(FrequencyAnalysis<'probes>),
+    OperatingPoint(OperatingPointAnalysis),
+    Dc(DcAnalysis<'probes>),
+    Noise(NoiseAnalysis),
+    TransferFunction(TransferFunctionAnalysis),
+    PoleZero(PoleZeroAnalysis),
 }
 
-impl<'probes> std::convert::TryFrom<(String, &'probes Probes)> for Analysis<'probes> {
+impl<'probes> Analysis<'probes> {
+    pub fn fourier(&self) -> Option<fourier::Settings> {
+        match self {
+            Self::Transiant(transiant) => transiant.fourier.clone(),
+            _ => None,
+        }
+    }
+
+    pub fn output(&self) -> Output {
+        match self {
+            Self::OperatingPoint(_) => Output::OperatingPoint,
+            Self::Frequency(_) => Output::Bode,
+            Self::Noise(noise) => Output::Noise(noise.source.clone()),
+            Self::TransferFunction(_) => Output::TransferFunction,
+            Self::PoleZero(_) => Output::PoleZero,
+            Self::Dc(dc) if dc.sweeps.len() > 1 => {
+                let outer = &dc.sweeps[1];
+                Output::Family(outer.source.clone(), outer.values())
+            }
+            _ => Output::Series,
+        }
+    }
+}
+
+impl<'probes> std::convert::TryFrom<(&settings::Analysis, &'probes Probes, &[parts::Part])>
+    for Analysis<'probes>
+{
     type Error = error::Error;
-    fn try_from(value: (String, &'probes Probes)) -> Result<Self, error::Error> {
-        let (typ, probes) = value;
-        match &typ[..] {
-            "tran" => Ok(Self::Transiant(TransiantAnalysis::try_from(probes)?)),
-            "freq" => Ok(Self::Frequency(FrequencyAnalysis::try_from(probes)?)),
-            _ => Err(Box::new(error::Sim::UnavailableAnalysis)),
+    fn try_from(
+        value: (&settings::Analysis, &'probes Probes, &[parts::Part]),
+    ) -> Result<Self, error::Error> {
+        let (analysis, probes, parts) = value;
+        match analysis {
+            settings::Analysis::Transient(settings) => Ok(Self::Transiant(
+                TransiantAnalysis::try_from((settings, probes))?,
+            )),
+            settings::Analysis::Frequency(settings) => {
+                Ok(Self::Frequency(FrequencyAnalysis::from((settings, probes))))
+            }
+            settings::Analysis::OperatingPoint => Ok(Self::OperatingPoint(OperatingPointAnalysis)),
+            settings::Analysis::Dc(sweeps) => Ok(Self::Dc(DcAnalysis::try_from((sweeps, probes))?)),
+            settings::Analysis::Noise(settings) => {
+                Ok(Self::Noise(NoiseAnalysis::try_from((settings, probes))?))
+            }
+            settings::Analysis::TransferFunction(settings) => Ok(Self::TransferFunction(
+                TransferFunctionAnalysis::try_from((settings, probes))?,
+            )),
+            settings::Analysis::PoleZero(settings) => Ok(Self::PoleZero(
+                PoleZeroAnalysis::try_from((settings, probes, parts))?,
+            )),
         }
     }
 }
@@ -129,6 +430,11 @@ impl<'probes> std::fmt::Display for Analysis<'probes> {
         match self {
             Self::Transiant(transiant) => write!(f, "{}", transiant),
             Self::Frequency(frequency) => write!(f, "{}", frequency),
+            Self::OperatingPoint(op) => write!(f, "{}", op),
+            Self::Dc(dc) => write!(f, "{}", dc),
+            Self::Noise(noise) => write!(f, "{}", noise),
+            Self::TransferFunction(tf) => write!(f, "{}", tf),
+            Self::PoleZero(pz) => write!(f, "{}", pz),
         }
     }
 }
@@ -137,27 +443,43 @@ pub struct TransiantAnalysis<'probes> {
     probes: &'probes Probes,
     step: properties::Value,
     stop: properties::Value,
+    fourier: Option<fourier::Settings>,
+    method: native::transient::Method,
 }
 
-impl<'probes> std::convert::TryFrom<&'probes Probes> for TransiantAnalysis<'probes> {
+impl<'probes> std::convert::TryFrom<(&settings::Transient, &'probes Probes)>
+    for TransiantAnalysis<'probes>
+{
     type Error = error::Error;
-    fn try_from(probes: &'probes Probes) -> Result<Self, error::Error> {
-        let step_input = dom::select("[name=\"tran__step\"]");
-        let stop_input = dom::select("[name=\"tran__stop\"]");
+    fn try_from(value: (&settings::Transient, &'probes Probes)) -> Result<Self, error::Error> {
+        let (settings, probes) = value;
+        if let Some(fourier) = &settings.fourier {
+            if probes.get(&fourier.probe).is_none() {
+                return Err(Box::new(error::Sim::UnknownProbe(fourier.probe.clone())));
+            }
+        }
         Ok(Self {
             probes,
-            step: properties::Value::from(step_input),
-            stop: properties::Value::from(stop_input),
+            step: settings.step.clone(),
+            stop: settings.stop.clone(),
+            fourier: settings.fourier.clone(),
+            method: settings.method,
         })
     }
 }
 
 impl<'probes> std::fmt::Display for TransiantAnalysis<'probes> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The trapezoidal method is the default of ngspice, the options are only changed when
+        // the backward Euler method is selected.
+        let method = match self.method {
+            native::transient::Method::Euler => " method=gear maxord=1",
+            native::transient::Method::Trapezoidal => "",
+        };
         write!(
             f,
-            ".options NOACCT\n.tran {} {} uic\n.print tran {}\n.end",
-            self.step, self.stop, self.probes
+            ".options NOACCT{}\n.tran {} {} uic\n.print tran {}\n.end",
+            method, self.step, self.stop, self.probes
         )
     }
 }
@@ -170,27 +492,242 @@ pub struct FrequencyAnalysis<'probes> {
     stop: properties::Value,
 }
 
-impl<'probes> std::convert::TryFrom<&'probes Probes> for FrequencyAnalysis<'probes> {
+impl<'probes> From<(&settings::Frequency, &'probes Probes)> for FrequencyAnalysis<'probes> {
+    fn from(value: (&settings::Frequency, &'probes Probes)) -> Self {
+        let (settings, probes) = value;
+        Self {
+            probes,
+            variation: settings.variation.clone(),
+            np: settings.points.clone(),
+            start: settings.start.clone(),
+            stop: settings.stop.clone(),
+        }
+    }
+}
+
+impl<'probes> std::fmt::Display for FrequencyAnalysis<'probes> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            ".options NOACCT NOPAGE\n.ac {} {} {} {}\n.print ac {}\n.end",
+            self.variation,
+            self.np,
+            self.start,
+            self.stop,
+            self.probes.to_complex_string()
+        )
+    }
+}
+
+/// The operating point does not need any probe, ngspice will return the voltage of every node and
+/// the current of every voltage source.
+pub struct OperatingPointAnalysis;
+
+impl std::fmt::Display for OperatingPointAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, ".options NOACCT\n.op\n.end")
+    }
+}
+
+/// A source swept by a DC analysis.
+#[derive(Clone)]
+pub struct DcSweep {
+    pub source: String,
+    pub start: properties::Value,
+    pub stop: properties::Value,
+    pub step: properties::Value,
+}
+
+impl DcSweep {
+    pub fn values(&self) -> Vec<f64> {
+        match (self.start.as_f64(), self.stop.as_f64(), self.step.as_f64()) {
+            (Some(start), Some(stop), Some(step)) => sweep_values(start, stop, step),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for DcSweep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.source, self.start, self.stop, self.step
+        )
+    }
+}
+
+/// Sweep the value of an independent source. A second source can be swept, in that case the
+/// first source is swept for each value of the second one which gives a family of curves.
+pub struct DcAnalysis<'probes> {
+    probes: &'probes Probes,
+    sweeps: Vec<DcSweep>,
+}
+
+impl<'probes> std::convert::TryFrom<(&Vec<DcSweep>, &'probes Probes)> for DcAnalysis<'probes> {
     type Error = error::Error;
-    fn try_from(probes: &'probes Probes) -> Result<Self, error::Error> {
-        let start_input = dom::select("[name=\"sim__freq-fstart\"]");
-        let stop_input = dom::select("[name=\"sim__freq-fstop\"]");
+    fn try_from(value: (&Vec<DcSweep>, &'probes Probes)) -> Result<Self, error::Error> {
+        let (sweeps, probes) = value;
+        if sweeps.is_empty() {
+            return Err(Box::new(error::Sim::NoSweptSource));
+        }
         Ok(Self {
             probes,
-            variation: select::value::<String>(dom::select("[name=\"sim__freq-variation-type\"]"))?,
-            np: text_input::value::<String>(dom::select("[name=\"sim__freq-np\"]"))?,
-            start: properties::Value::from(start_input),
-            stop: properties::Value::from(stop_input),
+            sweeps: sweeps.clone(),
         })
     }
 }
 
-impl<'probes> std::fmt::Display for FrequencyAnalysis<'probes> {
+impl<'probes> std::fmt::Display for DcAnalysis<'probes> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sweeps = self
+            .sweeps
+            .iter()
+            .map(|sweep| sweep.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        write!(
+            f,
+            ".options NOACCT\n.dc {}\n.print dc {}\n.end",
+            sweeps, self.probes
+        )
+    }
+}
+
+/// Compute the noise at an output (the nodes of a voltmeter) for a range of frequencies. The noise
+/// is also referred to an input source.
+pub struct NoiseAnalysis {
+    output: String,
+    source: String,
+    variation: String,
+    np: String,
+    start: properties::Value,
+    stop: properties::Value,
+}
+
+impl std::convert::TryFrom<(&settings::Noise, &Probes)> for NoiseAnalysis {
+    type Error = error::Error;
+    fn try_from(value: (&settings::Noise, &Probes)) -> Result<Self, error::Error> {
+        let (settings, probes) = value;
+        let output = match probes.voltmeter(&settings.output) {
+            Some(probe) => probe.spice.clone(),
+            None => return Err(Box::new(error::Sim::NoNoiseOutput)),
+        };
+        if settings.source.is_empty() {
+            return Err(Box::new(error::Sim::NoNoiseSource));
+        }
+        Ok(Self {
+            output,
+            source: settings.source.clone(),
+            variation: settings.frequency.variation.clone(),
+            np: settings.frequency.points.clone(),
+            start: settings.frequency.start.clone(),
+            stop: settings.frequency.stop.clone(),
+        })
+    }
+}
+
+impl std::fmt::Display for NoiseAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            ".options NOACCT NOPAGE\n.noise {} {} {} {} {} {}\n.print noise onoise_spectrum inoise_spectrum\n.end",
+            self.output, self.source, self.variation, self.np, self.start, self.stop
+        )
+    }
+}
+
+/// The source selected in the settings of an analysis.
+fn selected_source(source: &str) -> Result<String, error::Error> {
+    if source.is_empty() {
+        Err(Box::new(error::Sim::NoAnalysisSource))
+    } else {
+        Ok(source.to_string())
+    }
+}
+
+/// The small-signal gain between an input source and an output (a probe) at DC with the input and
+/// the output resistances.
+pub struct TransferFunctionAnalysis {
+    output: String,
+    source: String,
+}
+
+impl std::convert::TryFrom<(&settings::Transfer, &Probes)> for TransferFunctionAnalysis {
+    type Error = error::Error;
+    fn try_from(value: (&settings::Transfer, &Probes)) -> Result<Self, error::Error> {
+        let (settings, probes) = value;
+        let output = match probes.get(&settings.output) {
+            Some(probe) => probe.spice.clone(),
+            None => return Err(Box::new(error::Sim::NoAnalysisOutput)),
+        };
+        Ok(Self {
+            output,
+            source: selected_source(&settings.source)?,
+        })
+    }
+}
+
+impl std::fmt::Display for TransferFunctionAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            ".options NOACCT\n.tf {} {}\n.control\nrun\nprint all\n.endc\n.end",
+            self.output, self.source
+        )
+    }
+}
+
+/// The poles and the zeros of the transfer function between the nodes of an input source and the
+/// nodes of a voltmeter.
+pub struct PoleZeroAnalysis {
+    input: (String, String),
+    output: (String, String),
+    is_current: bool,
+}
+
+impl std::convert::TryFrom<(&settings::Transfer, &Probes, &[parts::Part])> for PoleZeroAnalysis {
+    type Error = error::Error;
+    fn try_from(
+        value: (&settings::Transfer, &Probes, &[parts::Part]),
+    ) -> Result<Self, error::Error> {
+        let (settings, probes, parts) = value;
+        let output = match probes
+            .voltmeter(&settings.output)
+            .and_then(|probe| probe.nodes())
+        {
+            Some(nodes) => nodes,
+            None => return Err(Box::new(error::Sim::NoAnalysisOutput)),
+        };
+        let source = selected_source(&settings.source)?;
+        let connectors = match parts
+            .iter()
+            .find(|part| part.device().as_ref() == Some(&source))
+        {
+            Some(part) => part.connectors()?,
+            None => return Err(Box::new(error::Sim::NoAnalysisSource)),
+        };
+        if connectors.len() < 2 {
+            return Err(Box::new(error::Sim::NoAnalysisSource));
+        }
+        Ok(Self {
+            input: (connectors[0].clone(), connectors[1].clone()),
+            output,
+            is_current: source.to_lowercase().starts_with('i'),
+        })
+    }
+}
+
+impl std::fmt::Display for PoleZeroAnalysis {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            ".options NOACCT\n.ac {} {} {} {} uic\n.print tran {}\n.end",
-            self.variation, self.np, self.start, self.stop, self.probes
+            ".options NOACCT\n.pz {} {} {} {} {} pz\n.control\nrun\nprint all\n.endc\n.end",
+            self.input.0,
+            self.input.1,
+            self.output.0,
+            self.output.1,
+            if self.is_current { "cur" } else { "vol" }
         )
     }
 }
@@ -0,0 +1,149 @@
+use crate::intrinsics::*;
+use crate::{error, sim};
+
+/// How many randomized netlists are simulated and which measurement of a probe is collected for
+/// each of them.
+#[derive(Clone)]
+pub struct Settings {
+    pub runs: usize,
+    pub probe: String,
+    pub measure: Measure,
+}
+
+impl Settings {
+    /// Parse the settings of the Monte Carlo analysis. If the number of runs is 0, there is no
+    /// Monte Carlo analysis.
+    pub fn parse(runs: &str, probe: String, measure: &str) -> Result<Option<Self>, error::Error> {
+        let runs = match runs.trim() {
+            "" => 0,
+            runs => runs
+                .parse::<usize>()
+                .map_err(|_| error::Sim::InvalidMonteCarloRuns)?,
+        };
+        if runs == 0 {
+            return Ok(None);
+        } else if runs > sim::MAX_RUNS {
+            return Err(Box::new(error::Sim::TooManyRuns(runs, sim::MAX_RUNS)));
+        }
+        let measure = measure.parse::<Measure>()?;
+        Ok(Some(Self {
+            runs,
+            probe,
+            measure,
+        }))
+    }
+}
+
+/// The value extracted from a series after each run.
+#[derive(Clone, Copy)]
+pub enum Measure {
+    Final,
+    Maximum,
+    Minimum,
+    PeakToPeak,
+    Average,
+}
+
+impl Measure {
+    pub const ALL: [Measure; 5] = [
+        Measure::Final,
+        Measure::Maximum,
+        Measure::Minimum,
+        Measure::PeakToPeak,
+        Measure::Average,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Final => "final",
+            Self::Maximum => "max",
+            Self::Minimum => "min",
+            Self::PeakToPeak => "pp",
+            Self::Average => "avg",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Final => "Valeur finale",
+            Self::Maximum => "Maximum",
+            Self::Minimum => "Minimum",
+            Self::PeakToPeak => "Crête à crête",
+            Self::Average => "Moyenne",
+        }
+    }
+
+    /// Measure a series. Returns `None` if the series is empty.
+    pub fn measure(&self, points: &[Point]) -> Option<f64> {
+        let last = points.last()?;
+        let max = points.iter().map(|point| point.y).fold(f64::MIN, f64::max);
+        let min = points.iter().map(|point| point.y).fold(f64::MAX, f64::min);
+        Some(match self {
+            Self::Final => last.y,
+            Self::Maximum => max,
+            Self::Minimum => min,
+            Self::PeakToPeak => max - min,
+            Self::Average => {
+                // The average is weighted by the x axis because the steps are not constant.
+                let span = last.x - points[0].x;
+                if span > 0.0 {
+                    points
+                        .windows(2)
+                        .map(|pair| (pair[1].x - pair[0].x) * (pair[0].y + pair[1].y) / 2.0)
+                        .sum::<f64>()
+                        / span
+                } else {
+                    last.y
+                }
+            }
+        })
+    }
+}
+
+impl std::str::FromStr for Measure {
+    type Err = error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|measure| measure.key() == s)
+            .copied()
+            .ok_or_else(|| Box::new(error::Import::UnexpectedValue) as error::Error)
+    }
+}
+
+/// A random number between `min` and `max` with a uniform distribution.
+pub fn uniform(min: f64, max: f64) -> f64 {
+    min + (max - min) * js_sys::Math::random()
+}
+
+/// A random number with a normal distribution (Box-Muller transform).
+pub fn gaussian(mean: f64, std_dev: f64) -> f64 {
+    let u = 1.0 - js_sys::Math::random();
+    let v = js_sys::Math::random();
+    mean + std_dev * (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(runs: &str) -> Result<Option<usize>, error::Error> {
+        Settings::parse(runs, String::from("v(out)"), "final")
+            .map(|settings| settings.map(|settings| settings.runs))
+    }
+
+    #[test]
+    fn runs_are_validated() {
+        assert_eq!(runs("").unwrap(), None);
+        assert_eq!(runs("0").unwrap(), None);
+        assert_eq!(runs(" 20 ").unwrap(), Some(20));
+        assert!(runs("-1").is_err());
+        assert!(runs("2.5").is_err());
+        let error = runs("1000000").err().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<error::Sim>(),
+            Some(error::Sim::TooManyRuns(1000000, sim::MAX_RUNS))
+        ));
+    }
+}
@@ -0,0 +1,310 @@
+use crate::error;
+use crate::sim::native::element::value;
+use crate::sim::native::mna::GMIN;
+use crate::sim::netlist;
+use crate::sim::temperature;
+
+/// The Boltzmann constant (J/K).
+const BOLTZMANN: f64 = 1.380649e-23;
+/// The elementary charge (C).
+const CHARGE: f64 = 1.602176634e-19;
+/// The exponentials are continued by their tangent above this argument, a wild guess of
+/// Newton-Raphson would overflow them.
+const MAX_EXPONENT: f64 = 80.0;
+
+/// The temperature in kelvin of a temperature in °C.
+fn kelvin(temperature: f64) -> f64 {
+    temperature + 273.15
+}
+
+/// The thermal voltage `kT/q` at a temperature in °C.
+pub fn thermal_voltage(temperature: f64) -> f64 {
+    BOLTZMANN * kelvin(temperature) / CHARGE
+}
+
+/// `e^x` and its derivative.
+fn exp(x: f64) -> (f64, f64) {
+    if x > MAX_EXPONENT {
+        let max = MAX_EXPONENT.exp();
+        (max * (1.0 + x - MAX_EXPONENT), max)
+    } else {
+        (x.exp(), x.exp())
+    }
+}
+
+/// The voltage above which the current of a junction grows so fast that its voltage is limited
+/// between two iterations.
+fn critical_voltage(saturation: f64, nvt: f64) -> f64 {
+    nvt * (nvt / (std::f64::consts::SQRT_2 * saturation)).ln()
+}
+
+/// Limit the change of the voltage of a junction between two iterations of Newton-Raphson (the
+/// `pnjlim` of SPICE). Above the critical voltage, the change is logarithmic instead of linear,
+/// the current of the junction would otherwise explode.
+fn limit(new: f64, old: f64, nvt: f64, critical: f64) -> f64 {
+    if new > critical && (new - old).abs() > 2.0 * nvt {
+        if old > 0.0 {
+            let arg = 1.0 + (new - old) / nvt;
+            if arg > 0.0 {
+                old + nvt * arg.ln()
+            } else {
+                critical
+            }
+        } else {
+            nvt * (new / nvt).ln()
+        }
+    } else {
+        new
+    }
+}
+
+/// The depletion capacitance of a junction. Above `fc * vj`, where the formula diverges, it is
+/// continued by its tangent like SPICE does.
+fn depletion(cj0: f64, vj: f64, m: f64, fc: f64, voltage: f64) -> f64 {
+    if cj0 == 0.0 {
+        0.0
+    } else if voltage < fc * vj {
+        cj0 * (1.0 - voltage / vj).powf(-m)
+    } else {
+        cj0 * (1.0 - fc).powf(-(1.0 + m)) * (1.0 - fc * (1.0 + m) + m * voltage / vj)
+    }
+}
+
+/// The parameters of a `.model` card, a missing parameter takes its default value.
+struct Parameters<'card> {
+    card: &'card netlist::Card,
+}
+
+impl<'card> Parameters<'card> {
+    fn get(&self, key: &str, default: f64) -> f64 {
+        self.card.param(key).and_then(value).unwrap_or(default)
+    }
+
+    /// A parameter that is infinite by default (ie: the breakdown voltage).
+    fn optional(&self, key: &str) -> Option<f64> {
+        self.card
+            .param(key)
+            .and_then(value)
+            .filter(|value| value.is_finite())
+    }
+}
+
+/// The type of a `.model` card (ie: `d` or `npn`).
+pub fn model_type(card: &netlist::Card) -> String {
+    card.words
+        .get(2)
+        .map_or(String::new(), |typ| typ.to_lowercase())
+}
+
+/// A diode of the Shockley equation with a series resistance, a breakdown and a junction
+/// capacitance. The parameters are the ones of the `d` model of SPICE.
+pub struct Diode {
+    /// The saturation current (`IS`) at the temperature of the diode.
+    saturation: f64,
+    /// The series resistance (`RS`).
+    pub resistance: f64,
+    /// The emission coefficient (`N`) times the thermal voltage.
+    nvt: f64,
+    /// The transit time (`TT`), it gives the diffusion capacitance.
+    transit_time: f64,
+    /// The zero-bias junction capacitance (`CJO`), the junction potential (`VJ`), the grading
+    /// coefficient (`M`) and the forward-bias depletion capacitance coefficient (`FC`).
+    junction: [f64; 4],
+    /// The reverse breakdown voltage (`BV`) and the current at this voltage (`IBV`).
+    breakdown: Option<(f64, f64)>,
+}
+
+impl Diode {
+    pub fn new(model: &netlist::Card, temperature: f64) -> Result<Self, error::Sim> {
+        if model_type(model) != "d" {
+            return Err(error::Sim::NativeInvalidModel(model.line.clone()));
+        }
+        let params = Parameters { card: model };
+        let n = params.get("n", 1.0);
+        let vt = thermal_voltage(temperature);
+        // The saturation current grows with the temperature (`EG` and `XTI`).
+        let ratio = kelvin(temperature) / kelvin(temperature::NOMINAL);
+        let saturation = params.get("is", 1e-14)
+            * ratio.powf(params.get("xti", 3.0) / n)
+            * ((ratio - 1.0) * params.get("eg", 1.11) / (n * vt)).exp();
+        Ok(Self {
+            saturation,
+            resistance: params.get("rs", 0.0),
+            nvt: n * vt,
+            transit_time: params.get("tt", 0.0),
+            junction: [
+                params.get("cjo", params.get("cj0", 0.0)),
+                params.get("vj", 1.0),
+                params.get("m", 0.5),
+                params.get("fc", 0.5),
+            ],
+            breakdown: params
+                .optional("bv")
+                .map(|bv| (bv, params.get("ibv", 1e-3))),
+        })
+    }
+
+    /// The current of the junction (from the anode to the cathode) at a voltage and its
+    /// conductance.
+    pub fn current(&self, voltage: f64) -> (f64, f64) {
+        let (forward, derivative) = exp(voltage / self.nvt);
+        let mut current = self.saturation * (forward - 1.0) + GMIN * voltage;
+        let mut conductance = self.saturation * derivative / self.nvt + GMIN;
+        if let Some((bv, ibv)) = self.breakdown {
+            let (reverse, derivative) = exp(-(bv + voltage) / self.nvt);
+            current -= ibv * reverse;
+            conductance += ibv * derivative / self.nvt;
+        }
+        (current, conductance)
+    }
+
+    /// The capacitance of the junction at a voltage: its depletion capacitance and its diffusion
+    /// capacitance.
+    pub fn capacitance(&self, voltage: f64) -> f64 {
+        let [cj0, vj, m, fc] = self.junction;
+        let diffusion = self.transit_time * self.current(voltage).1;
+        depletion(cj0, vj, m, fc, voltage) + diffusion
+    }
+
+    /// Limit the voltage of the junction between two iterations, in the forward direction and
+    /// in the breakdown.
+    pub fn limit(&self, new: f64, old: f64) -> f64 {
+        let critical = critical_voltage(self.saturation, self.nvt);
+        match self.breakdown {
+            Some((bv, _)) if new < (-bv + 10.0 * self.nvt).min(0.0) => {
+                -(limit(-(new + bv), -(old + bv), self.nvt, critical) + bv)
+            }
+            _ => limit(new, old, self.nvt, critical),
+        }
+    }
+}
+
+/// A current of a transistor and its derivatives by the base-emitter and the base-collector
+/// voltages.
+#[derive(Clone, Copy)]
+pub struct Current {
+    pub value: f64,
+    pub dbe: f64,
+    pub dbc: f64,
+}
+
+/// A bipolar transistor of the Ebers-Moll model with the Early effect of the Gummel-Poon model
+/// (without high injection nor the series resistances). The parameters are the ones of the `npn`
+/// and `pnp` models of SPICE. The voltages and the currents are the ones of an NPN transistor,
+/// they are reversed for a PNP transistor.
+pub struct Bjt {
+    /// 1 for an NPN transistor, -1 for a PNP transistor.
+    pub polarity: f64,
+    /// The saturation current (`IS`) at the temperature of the transistor.
+    saturation: f64,
+    /// The forward (`BF`) and the reverse (`BR`) current gains.
+    gains: (f64, f64),
+    /// The forward (`NF`) and the reverse (`NR`) emission coefficients times the thermal
+    /// voltage.
+    nvt: (f64, f64),
+    /// The forward Early voltage (`VAF`).
+    early: Option<f64>,
+    /// The depletion capacitances of the base-emitter (`CJE`, `VJE`, `MJE`) and of the
+    /// base-collector (`CJC`, `VJC`, `MJC`) junctions.
+    junctions: [[f64; 3]; 2],
+    /// The forward-bias depletion capacitance coefficient (`FC`).
+    fc: f64,
+    /// The forward (`TF`) and the reverse (`TR`) transit times.
+    transit_times: (f64, f64),
+}
+
+impl Bjt {
+    pub fn new(model: &netlist::Card, temperature: f64) -> Result<Self, error::Sim> {
+        let polarity = match &model_type(model)[..] {
+            "npn" => 1.0,
+            "pnp" => -1.0,
+            _ => return Err(error::Sim::NativeInvalidModel(model.line.clone())),
+        };
+        let params = Parameters { card: model };
+        let vt = thermal_voltage(temperature);
+        let ratio = kelvin(temperature) / kelvin(temperature::NOMINAL);
+        let saturation = params.get("is", 1e-16)
+            * ratio.powf(params.get("xti", 3.0))
+            * ((ratio - 1.0) * params.get("eg", 1.11) / vt).exp();
+        Ok(Self {
+            polarity,
+            saturation,
+            gains: (params.get("bf", 100.0), params.get("br", 1.0)),
+            nvt: (params.get("nf", 1.0) * vt, params.get("nr", 1.0) * vt),
+            early: params.optional("vaf").or_else(|| params.optional("va")),
+            junctions: [
+                [
+                    params.get("cje", 0.0),
+                    params.get("vje", 0.75),
+                    params.get("mje", 0.33),
+                ],
+                [
+                    params.get("cjc", 0.0),
+                    params.get("vjc", 0.75),
+                    params.get("mjc", 0.33),
+                ],
+            ],
+            fc: params.get("fc", 0.5),
+            transit_times: (params.get("tf", 0.0), params.get("tr", 0.0)),
+        })
+    }
+
+    /// The forward and the reverse diffusion currents and their conductances.
+    fn diffusion(&self, vbe: f64, vbc: f64) -> [(f64, f64); 2] {
+        let (forward, forward_derivative) = exp(vbe / self.nvt.0);
+        let (reverse, reverse_derivative) = exp(vbc / self.nvt.1);
+        [
+            (
+                self.saturation * (forward - 1.0),
+                self.saturation * forward_derivative / self.nvt.0,
+            ),
+            (
+                self.saturation * (reverse - 1.0),
+                self.saturation * reverse_derivative / self.nvt.1,
+            ),
+        ]
+    }
+
+    /// The currents that enter the collector and the base. The current that enters the emitter
+    /// is the opposite of their sum.
+    pub fn currents(&self, vbe: f64, vbc: f64) -> [Current; 2] {
+        let [(forward, gf), (reverse, gr)] = self.diffusion(vbe, vbc);
+        let (bf, br) = self.gains;
+        // The Early effect modulates the current transported from the emitter to the collector.
+        let (early, early_derivative) = self
+            .early
+            .map_or((1.0, 0.0), |vaf| (1.0 - vbc / vaf, -1.0 / vaf));
+        let transport = (forward - reverse) * early;
+        let collector = Current {
+            value: transport - reverse / br - GMIN * vbc,
+            dbe: gf * early,
+            dbc: -gr * early + (forward - reverse) * early_derivative - gr / br - GMIN,
+        };
+        let base = Current {
+            value: forward / bf + reverse / br + GMIN * (vbe + vbc),
+            dbe: gf / bf + GMIN,
+            dbc: gr / br + GMIN,
+        };
+        [collector, base]
+    }
+
+    /// The capacitances of the base-emitter and of the base-collector junctions: their depletion
+    /// capacitances and their diffusion capacitances.
+    pub fn capacitances(&self, vbe: f64, vbc: f64) -> [f64; 2] {
+        let [(_, gf), (_, gr)] = self.diffusion(vbe, vbc);
+        let [[cje, vje, mje], [cjc, vjc, mjc]] = self.junctions;
+        [
+            depletion(cje, vje, mje, self.fc, vbe) + self.transit_times.0 * gf,
+            depletion(cjc, vjc, mjc, self.fc, vbc) + self.transit_times.1 * gr,
+        ]
+    }
+
+    /// Limit the voltages of the junctions between two iterations.
+    pub fn limit(&self, new: [f64; 2], old: [f64; 2]) -> [f64; 2] {
+        let (nf, nr) = self.nvt;
+        [
+            limit(new[0], old[0], nf, critical_voltage(self.saturation, nf)),
+            limit(new[1], old[1], nr, critical_voltage(self.saturation, nr)),
+        ]
+    }
+}
@@ -0,0 +1,244 @@
+use crate::error;
+use crate::intrinsics::*;
+use crate::sim::native::device::{Bjt, Diode};
+use crate::sim::native::mna;
+use crate::sim::netlist;
+use crate::sim::temperature;
+use std::collections::HashMap;
+
+/// A number of a card (ie: `4.7k` or `1Meg`).
+pub fn value(text: &str) -> Option<f64> {
+    netlist::number(text).map(|(value, prefix)| value * prefix.factor())
+}
+
+/// The waveform of a source for the transient analysis. The parameters are the ones of SPICE.
+pub enum Waveform {
+    /// `SIN(offset amplitude frequency delay damping_factor phase)`, the phase is in degrees.
+    Sin([f64; 6]),
+}
+
+impl Waveform {
+    pub fn value(&self, time: f64) -> f64 {
+        match self {
+            Self::Sin([offset, amplitude, frequency, delay, damping, phase]) => {
+                let phase = phase.to_radians();
+                if time < *delay {
+                    offset + amplitude * phase.sin()
+                } else {
+                    let time = time - delay;
+                    offset
+                        + amplitude
+                            * (-damping * time).exp()
+                            * (2.0 * std::f64::consts::PI * frequency * time + phase).sin()
+                }
+            }
+        }
+    }
+}
+
+/// The value of an independent source for each analysis.
+pub struct Source {
+    /// The value of the DC and of the operating point analyses.
+    pub dc: f64,
+    /// The magnitude and the phase of the AC analysis.
+    pub ac: Complex,
+    pub waveform: Option<Waveform>,
+}
+
+impl Source {
+    /// Read the value of a source: `[dc] value`, `ac magnitude [phase]` and the waveform. Like
+    /// ngspice, a source without a DC value uses the value of its waveform at the time 0.
+    fn parse(words: &[String], card: &netlist::Card) -> Result<Self, error::Sim> {
+        let invalid = || error::Sim::NativeInvalidCard(card.line.clone());
+        let mut dc = None;
+        let mut ac = Complex::new(0.0, 0.0);
+        let mut waveform = None;
+        let mut idx = 0;
+        while idx < words.len() {
+            let numbers = words[idx + 1..]
+                .iter()
+                .map_while(|word| value(word))
+                .collect::<Vec<f64>>();
+            match &words[idx].to_lowercase()[..] {
+                "dc" => {
+                    dc = Some(*numbers.first().ok_or_else(invalid)?);
+                    idx += 2;
+                }
+                "ac" => {
+                    let magnitude = numbers.first().copied().unwrap_or(1.0);
+                    let phase = numbers.get(1).copied().unwrap_or(0.0).to_radians();
+                    ac = Complex::new(magnitude * phase.cos(), magnitude * phase.sin());
+                    idx += 1 + numbers.len().min(2);
+                }
+                "sin" => {
+                    // The missing parameters are 0 (the frequency is not used without amplitude).
+                    let mut parameters = [0.0; 6];
+                    parameters
+                        .iter_mut()
+                        .zip(numbers.iter())
+                        .for_each(|(parameter, number)| *parameter = *number);
+                    waveform = Some(Waveform::Sin(parameters));
+                    idx += 1 + numbers.len();
+                }
+                word if dc.is_none() => {
+                    dc = Some(value(word).ok_or_else(invalid)?);
+                    idx += 1;
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        let dc = match (dc, &waveform) {
+            (Some(dc), _) => dc,
+            (None, Some(waveform)) => waveform.value(0.0),
+            (None, None) => 0.0,
+        };
+        Ok(Self { dc, ac, waveform })
+    }
+
+    /// The value of the source during the transient analysis.
+    pub fn at(&self, time: f64) -> f64 {
+        self.waveform
+            .as_ref()
+            .map_or(self.dc, |waveform| waveform.value(time))
+    }
+}
+
+/// The kind of an element and its values.
+pub enum Kind {
+    Resistor(f64),
+    /// A capacitor and its initial voltage.
+    Capacitor {
+        capacitance: f64,
+        init_cond: Option<f64>,
+    },
+    /// An inductor and its initial current.
+    Inductor {
+        inductance: f64,
+        init_cond: Option<f64>,
+    },
+    VoltageSource(Source),
+    CurrentSource(Source),
+    /// A voltage controlled voltage source (`E`) and its gain.
+    Vcvs(f64),
+    /// A voltage controlled current source (`G`) and its transconductance.
+    Vccs(f64),
+    /// A current controlled current source (`F`), its gain and the voltage source that measures
+    /// the controlling current.
+    Cccs(f64, String),
+    /// A current controlled voltage source (`H`), its transresistance and the voltage source
+    /// that measures the controlling current.
+    Ccvs(f64, String),
+    /// A diode, its nodes are the anode, the cathode and the anode of its junction (after its
+    /// series resistance).
+    Diode(Diode),
+    /// A bipolar transistor, its nodes are the collector, the base and the emitter.
+    Bjt(Bjt),
+}
+
+/// An element card of the netlist. The nodes are the indices of the nodes of the netlist (see
+/// `mna::Nodes`), the controlling nodes of the voltage controlled sources are after their nodes.
+pub struct Element {
+    pub name: String,
+    pub nodes: Vec<usize>,
+    pub kind: Kind,
+}
+
+impl Element {
+    /// Read an element card. `models` are the `.model` cards of the netlist by their name in
+    /// lowercase.
+    pub fn parse(
+        card: &netlist::Card,
+        nodes: &mut mna::Nodes,
+        temperature: f64,
+        models: &HashMap<String, &netlist::Card>,
+    ) -> Result<Self, error::Sim> {
+        let invalid = || error::Sim::NativeInvalidCard(card.line.clone());
+        let name = card.words[0].clone();
+        let count = match &card.keyword()[..] {
+            "R" | "C" | "L" | "V" | "I" | "F" | "H" | "D" => 2,
+            "Q" => 3,
+            "E" | "G" => 4,
+            _ => return Err(error::Sim::NativeUnsupportedDevice(name)),
+        };
+        if card.words.len() < count + 1 {
+            return Err(invalid());
+        }
+        let mut element_nodes = card.words[1..=count]
+            .iter()
+            .map(|node| nodes.index(node))
+            .collect::<Vec<usize>>();
+        let rest = &card.words[count + 1..];
+        let number = |idx: usize| {
+            rest.get(idx)
+                .and_then(|word| value(word))
+                .ok_or_else(invalid)
+        };
+        let param = |key: &str| card.param(key).and_then(value);
+        let control = || rest.first().cloned().ok_or_else(invalid);
+        let model = || {
+            let name = rest.first().ok_or_else(invalid)?;
+            models
+                .get(&name.to_lowercase())
+                .copied()
+                .ok_or_else(|| error::Sim::NativeUnknownModel(name.clone()))
+        };
+        // The temperature of the element replaces the temperature of the circuit.
+        let temperature = param("temp").unwrap_or(temperature);
+        let kind = match &card.keyword()[..] {
+            "R" => {
+                let delta = temperature - temperature::NOMINAL;
+                let (tc1, tc2) = (param("tc1").unwrap_or(0.0), param("tc2").unwrap_or(0.0));
+                Kind::Resistor(number(0)? * (1.0 + tc1 * delta + tc2 * delta * delta))
+            }
+            "C" => Kind::Capacitor {
+                capacitance: number(0)?,
+                init_cond: param("ic"),
+            },
+            "L" => Kind::Inductor {
+                inductance: number(0)?,
+                init_cond: param("ic"),
+            },
+            "V" => Kind::VoltageSource(Source::parse(rest, card)?),
+            "I" => Kind::CurrentSource(Source::parse(rest, card)?),
+            "E" => Kind::Vcvs(number(0)?),
+            "G" => Kind::Vccs(number(0)?),
+            "F" => Kind::Cccs(number(1)?, control()?),
+            "D" => {
+                let diode = Diode::new(model()?, temperature)?;
+                // The series resistance is between the anode and an internal node.
+                element_nodes.push(if diode.resistance > 0.0 {
+                    nodes.index(&format!("{}#internal", name.to_lowercase()))
+                } else {
+                    element_nodes[0]
+                });
+                Kind::Diode(diode)
+            }
+            "Q" => Kind::Bjt(Bjt::new(model()?, temperature)?),
+            _ => Kind::Ccvs(number(1)?, control()?),
+        };
+        if let Kind::Resistor(resistance) = kind {
+            if resistance == 0.0 {
+                return Err(invalid());
+            }
+        }
+        Ok(Self {
+            name,
+            nodes: element_nodes,
+            kind,
+        })
+    }
+
+    /// Whether the current of the element is an unknown of the system. It is the case of the
+    /// elements that impose a voltage.
+    pub fn has_branch(&self) -> bool {
+        matches!(
+            self.kind,
+            Kind::Inductor { .. } | Kind::VoltageSource(_) | Kind::Vcvs(_) | Kind::Ccvs(..)
+        )
+    }
+
+    /// Whether the element is solved by Newton-Raphson.
+    pub fn is_nonlinear(&self) -> bool {
+        matches!(self.kind, Kind::Diode(_) | Kind::Bjt(_))
+    }
+}
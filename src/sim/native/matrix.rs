@@ -0,0 +1,136 @@
+use crate::error;
+use crate::intrinsics::*;
+use std::ops;
+
+/// The pivots smaller than this are considered to be 0, the matrix is then singular.
+const PIVOT_TOLERANCE: f64 = 1e-300;
+
+/// The numbers a system can be solved with: real numbers for the DC and the transient analyses,
+/// complex numbers for the AC analysis.
+pub trait Scalar:
+    Copy
+    + ops::Add<Output = Self>
+    + ops::Sub<Output = Self>
+    + ops::Mul<Output = Self>
+    + ops::Div<Output = Self>
+    + ops::Neg<Output = Self>
+{
+    fn zero() -> Self;
+    fn real(value: f64) -> Self;
+    fn norm(&self) -> f64;
+}
+
+impl Scalar for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn real(value: f64) -> Self {
+        value
+    }
+
+    fn norm(&self) -> f64 {
+        self.abs()
+    }
+}
+
+impl Scalar for Complex {
+    fn zero() -> Self {
+        Complex::new(0.0, 0.0)
+    }
+
+    fn real(value: f64) -> Self {
+        Complex::new(value, 0.0)
+    }
+
+    fn norm(&self) -> f64 {
+        Complex::norm(self)
+    }
+}
+
+/// The linear system `A x = b` of the modified nodal analysis. The rows and the columns are the
+/// unknowns: the voltages of the nodes (the ground excluded) then the currents of the branches.
+/// The elements add their contribution to the system (they are stamped), the rows and the
+/// columns of the ground are ignored.
+pub struct Matrix<T: Scalar> {
+    size: usize,
+    a: Vec<T>,
+    b: Vec<T>,
+}
+
+impl<T: Scalar> Matrix<T> {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            a: vec![T::zero(); size * size],
+            b: vec![T::zero(); size],
+        }
+    }
+
+    /// Add a value to `A`. `None` is the ground.
+    pub fn add(&mut self, row: Option<usize>, col: Option<usize>, value: T) {
+        if let (Some(row), Some(col)) = (row, col) {
+            let cell = &mut self.a[row * self.size + col];
+            *cell = *cell + value;
+        }
+    }
+
+    /// Add a value to `b`. `None` is the ground.
+    pub fn add_rhs(&mut self, row: Option<usize>, value: T) {
+        if let Some(row) = row {
+            self.b[row] = self.b[row] + value;
+        }
+    }
+
+    /// A conductance between two nodes.
+    pub fn conductance(&mut self, a: Option<usize>, b: Option<usize>, value: T) {
+        self.add(a, a, value);
+        self.add(b, b, value);
+        self.add(a, b, -value);
+        self.add(b, a, -value);
+    }
+
+    /// A current that flows from the node `a` to the node `b` through the element.
+    pub fn current(&mut self, a: Option<usize>, b: Option<usize>, value: T) {
+        self.add_rhs(a, -value);
+        self.add_rhs(b, value);
+    }
+
+    /// Solve the system with a Gaussian elimination with partial pivoting.
+    pub fn solve(mut self) -> Result<Vec<T>, error::Sim> {
+        let n = self.size;
+        for col in 0..n {
+            let pivot = (col..n)
+                .max_by(|&x, &y| {
+                    let (x, y) = (self.a[x * n + col].norm(), self.a[y * n + col].norm());
+                    x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .filter(|&row| self.a[row * n + col].norm() > PIVOT_TOLERANCE)
+                .ok_or(error::Sim::SingularMatrix)?;
+            if pivot != col {
+                for idx in 0..n {
+                    self.a.swap(pivot * n + idx, col * n + idx);
+                }
+                self.b.swap(pivot, col);
+            }
+            let diagonal = self.a[col * n + col];
+            for row in col + 1..n {
+                let factor = self.a[row * n + col] / diagonal;
+                if factor.norm() == 0.0 {
+                    continue;
+                }
+                for idx in col..n {
+                    self.a[row * n + idx] = self.a[row * n + idx] - factor * self.a[col * n + idx];
+                }
+                self.b[row] = self.b[row] - factor * self.b[col];
+            }
+        }
+        let mut x = vec![T::zero(); n];
+        for row in (0..n).rev() {
+            let sum =
+                (row + 1..n).fold(self.b[row], |sum, col| sum - self.a[row * n + col] * x[col]);
+            x[row] = sum / self.a[row * n + row];
+        }
+        Ok(x)
+    }
+}
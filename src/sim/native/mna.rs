@@ -0,0 +1,451 @@
+use crate::error;
+use crate::intrinsics::*;
+use crate::sim::native::device::Current;
+use crate::sim::native::element::{Element, Kind, Source};
+use crate::sim::native::matrix::{Matrix, Scalar};
+use std::collections::HashMap;
+
+/// The conductance added between every node and the ground (like the `gmin` of ngspice). A node
+/// only connected to capacitors has no DC path to the ground, without it the system is singular.
+pub const GMIN: f64 = 1e-12;
+/// The relative tolerance of the solutions (like the `reltol` of ngspice).
+pub const RELTOL: f64 = 1e-3;
+/// The absolute tolerance of the voltages (`vntol`).
+pub const VNTOL: f64 = 1e-6;
+/// The absolute tolerance of the currents (`abstol`).
+pub const ABSTOL: f64 = 1e-12;
+/// The maximum number of iterations of Newton-Raphson for a DC solution (`itl1`) and for a time
+/// point of the transient analysis (`itl4`).
+pub const DC_ITERATIONS: usize = 100;
+pub const TRANSIENT_ITERATIONS: usize = 20;
+/// The first conductance of the gmin stepping, it is divided by 10 down to 0.
+const GMIN_STEPPING_START: f64 = 1e-2;
+/// The smallest increment of the sources of the source stepping.
+const SOURCE_STEPPING_MIN: f64 = 1e-3;
+
+/// The nodes of a netlist by their name. The ground (`0`) is the node 0.
+pub struct Nodes {
+    names: Vec<String>,
+}
+
+impl Default for Nodes {
+    fn default() -> Self {
+        Self {
+            names: vec![String::from("0")],
+        }
+    }
+}
+
+impl Nodes {
+    /// The index of a node, a new node is added if it does not exist yet.
+    pub fn index(&mut self, name: &str) -> usize {
+        self.get(name).unwrap_or_else(|| {
+            self.names.push(name.to_string());
+            self.names.len() - 1
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|node| node == name)
+    }
+
+    /// The nodes without the ground.
+    pub fn names(&self) -> &[String] {
+        &self.names[1..]
+    }
+}
+
+/// The row (and the column) of a node in the system. The ground has none.
+pub fn row(node: usize) -> Option<usize> {
+    node.checked_sub(1)
+}
+
+/// A circuit written as a modified nodal analysis: the unknowns are the voltages of the nodes
+/// and the currents of the elements that impose a voltage (see `Element::has_branch`).
+pub struct Mna {
+    pub nodes: Nodes,
+    pub elements: Vec<Element>,
+    /// The row of the current of each element with a branch, by its name in lowercase.
+    branches: HashMap<String, usize>,
+}
+
+impl Mna {
+    pub fn new(nodes: Nodes, elements: Vec<Element>) -> Result<Self, error::Error> {
+        let mut branches = HashMap::new();
+        elements
+            .iter()
+            .filter(|element| element.has_branch())
+            .for_each(|element| {
+                let row = nodes.names().len() + branches.len();
+                branches.insert(element.name.to_lowercase(), row);
+            });
+        let mna = Self {
+            nodes,
+            elements,
+            branches,
+        };
+        for element in mna.elements.iter() {
+            if let Kind::Cccs(_, control) | Kind::Ccvs(_, control) = &element.kind {
+                if mna.branch(control).is_none() {
+                    return Err(Box::new(error::Sim::NativeInvalidControl(
+                        element.name.clone(),
+                        control.clone(),
+                    )));
+                }
+            }
+        }
+        Ok(mna)
+    }
+
+    pub fn size(&self) -> usize {
+        self.nodes.names().len() + self.branches.len()
+    }
+
+    /// The row of the current of an element.
+    pub fn branch(&self, name: &str) -> Option<usize> {
+        self.branches.get(&name.to_lowercase()).copied()
+    }
+
+    /// The independent source named `name`.
+    pub fn source_mut(&mut self, name: &str) -> Option<&mut Source> {
+        self.elements
+            .iter_mut()
+            .find(|element| element.name.eq_ignore_ascii_case(name))
+            .and_then(|element| match &mut element.kind {
+                Kind::VoltageSource(source) | Kind::CurrentSource(source) => Some(source),
+                _ => None,
+            })
+    }
+
+    /// The voltage of a node in a solution.
+    pub fn voltage<T: Scalar>(&self, x: &[T], node: usize) -> T {
+        row(node).map_or(T::zero(), |row| x[row])
+    }
+
+    /// The rows of the nodes of an element and the row of its current.
+    pub fn rows(&self, element: &Element) -> (Vec<Option<usize>>, Option<usize>) {
+        let nodes = element.nodes.iter().map(|&node| row(node)).collect();
+        (nodes, self.branch(&element.name))
+    }
+
+    /// The voltage across an element (between its first two nodes) in a solution.
+    pub fn element_voltage(&self, x: &[f64], element: &Element) -> f64 {
+        self.voltage(x, element.nodes[0]) - self.voltage(x, element.nodes[1])
+    }
+
+    /// Whether the circuit has nonlinear elements, it is then solved by Newton-Raphson.
+    pub fn is_nonlinear(&self) -> bool {
+        self.elements.iter().any(Element::is_nonlinear)
+    }
+
+    /// The voltages of the junctions of a diode (`[v, 0]`) or of a transistor (`[vbe, vbc]`) in
+    /// a solution.
+    pub fn junctions(&self, x: &[f64], element: &Element) -> [f64; 2] {
+        let voltage = |a: usize, b: usize| self.voltage(x, a) - self.voltage(x, b);
+        match &element.kind {
+            Kind::Diode(_) => [voltage(element.nodes[2], element.nodes[1]), 0.0],
+            Kind::Bjt(bjt) => {
+                let (c, b, e) = (element.nodes[0], element.nodes[1], element.nodes[2]);
+                [bjt.polarity * voltage(b, e), bjt.polarity * voltage(b, c)]
+            }
+            _ => [0.0; 2],
+        }
+    }
+
+    /// The capacitances of an element between two of its nodes in a solution: the capacitors
+    /// and the junctions of the diodes and of the transistors.
+    pub fn capacitances(&self, x: &[f64], element: &Element) -> Vec<(usize, usize, f64)> {
+        let nodes = &element.nodes;
+        match &element.kind {
+            Kind::Capacitor { capacitance, .. } => vec![(nodes[0], nodes[1], *capacitance)],
+            Kind::Diode(diode) => {
+                let [voltage, _] = self.junctions(x, element);
+                vec![(nodes[2], nodes[1], diode.capacitance(voltage))]
+            }
+            Kind::Bjt(bjt) => {
+                let [vbe, vbc] = self.junctions(x, element);
+                let [cbe, cbc] = bjt.capacitances(vbe, vbc);
+                vec![(nodes[1], nodes[2], cbe), (nodes[1], nodes[0], cbc)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Add the elements to the system, except the capacitors and the inductors which depend on
+    /// the analysis (without them, a capacitor is an open circuit and an inductor is a short
+    /// circuit) and the junctions which depend on the solution (see `stamp_junctions`). `source`
+    /// gives the value of the independent sources.
+    pub fn stamp<T: Scalar>(&self, matrix: &mut Matrix<T>, source: impl Fn(&Source) -> T) {
+        for node in 1..=self.nodes.names().len() {
+            matrix.add(row(node), row(node), T::real(GMIN));
+        }
+        for element in self.elements.iter() {
+            let (nodes, branch) = self.rows(element);
+            let (a, b) = (nodes[0], nodes[1]);
+            if branch.is_some() {
+                // The current flows from `a` to `b` through the element.
+                matrix.add(a, branch, T::real(1.0));
+                matrix.add(b, branch, T::real(-1.0));
+                matrix.add(branch, a, T::real(1.0));
+                matrix.add(branch, b, T::real(-1.0));
+            }
+            match &element.kind {
+                Kind::Resistor(resistance) => matrix.conductance(a, b, T::real(1.0 / resistance)),
+                Kind::Capacitor { .. } | Kind::Inductor { .. } => {}
+                Kind::VoltageSource(value) => matrix.add_rhs(branch, source(value)),
+                Kind::CurrentSource(value) => matrix.current(a, b, source(value)),
+                Kind::Vcvs(gain) => {
+                    matrix.add(branch, nodes[2], T::real(-gain));
+                    matrix.add(branch, nodes[3], T::real(*gain));
+                }
+                Kind::Vccs(gain) => {
+                    matrix.add(a, nodes[2], T::real(*gain));
+                    matrix.add(a, nodes[3], T::real(-gain));
+                    matrix.add(b, nodes[2], T::real(-gain));
+                    matrix.add(b, nodes[3], T::real(*gain));
+                }
+                Kind::Cccs(gain, control) => {
+                    let control = self.branch(control);
+                    matrix.add(a, control, T::real(*gain));
+                    matrix.add(b, control, T::real(-gain));
+                }
+                Kind::Ccvs(gain, control) => {
+                    matrix.add(branch, self.branch(control), T::real(-gain))
+                }
+                Kind::Diode(diode) => {
+                    if diode.resistance > 0.0 {
+                        matrix.conductance(a, nodes[2], T::real(1.0 / diode.resistance));
+                    }
+                }
+                Kind::Bjt(_) => {}
+            }
+        }
+    }
+
+    /// Add the junctions of a diode or of a transistor linearized at the voltages `junctions`:
+    /// the conductances of their small-signal model and, for Newton-Raphson (`is_bias`), the
+    /// currents that complete their tangent.
+    fn stamp_junctions<T: Scalar>(
+        &self,
+        matrix: &mut Matrix<T>,
+        element: &Element,
+        junctions: [f64; 2],
+        is_bias: bool,
+    ) {
+        let (nodes, _) = self.rows(element);
+        match &element.kind {
+            Kind::Diode(diode) => {
+                let voltage = junctions[0];
+                let (current, conductance) = diode.current(voltage);
+                matrix.conductance(nodes[2], nodes[1], T::real(conductance));
+                if is_bias {
+                    matrix.current(nodes[2], nodes[1], T::real(current - conductance * voltage));
+                }
+            }
+            Kind::Bjt(bjt) => {
+                let [vbe, vbc] = junctions;
+                let (c, b, e) = (nodes[0], nodes[1], nodes[2]);
+                let [collector, base] = bjt.currents(vbe, vbc);
+                let emitter = Current {
+                    value: -collector.value - base.value,
+                    dbe: -collector.dbe - base.dbe,
+                    dbc: -collector.dbc - base.dbc,
+                };
+                // The currents enter the transistor, they leave their node. The polarity
+                // vanishes from the derivatives by the voltages of the nodes.
+                for (row, current) in [(c, collector), (b, base), (e, emitter)] {
+                    matrix.add(row, b, T::real(current.dbe + current.dbc));
+                    matrix.add(row, e, T::real(-current.dbe));
+                    matrix.add(row, c, T::real(-current.dbc));
+                    if is_bias {
+                        let constant = current.value - current.dbe * vbe - current.dbc * vbc;
+                        matrix.add_rhs(row, T::real(-bjt.polarity * constant));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether two successive solutions of Newton-Raphson are close enough.
+    fn is_converged(&self, x: &[f64], next: &[f64]) -> bool {
+        let nodes = self.nodes.names().len();
+        x.iter()
+            .zip(next.iter())
+            .enumerate()
+            .all(|(idx, (x, next))| {
+                let absolute = if idx < nodes { VNTOL } else { ABSTOL };
+                (next - x).abs() <= RELTOL * x.abs().max(next.abs()) + absolute
+            })
+    }
+
+    /// Whether the currents of the junctions of an element linearized at the voltages
+    /// `junctions` match their currents in the next solution. The voltages converge slowly
+    /// where a junction conducts a lot, a small error of the voltage is a large error of the
+    /// current.
+    fn is_element_converged(&self, element: &Element, junctions: [f64; 2], next: &[f64]) -> bool {
+        let is_close = |predicted: f64, actual: f64| {
+            (predicted - actual).abs() <= RELTOL * predicted.abs().max(actual.abs()) + ABSTOL
+        };
+        let [vbe, vbc] = self.junctions(next, element);
+        match &element.kind {
+            Kind::Diode(diode) => {
+                let (current, conductance) = diode.current(junctions[0]);
+                is_close(
+                    current + conductance * (vbe - junctions[0]),
+                    diode.current(vbe).0,
+                )
+            }
+            Kind::Bjt(bjt) => {
+                let linearized = bjt.currents(junctions[0], junctions[1]);
+                let actual = bjt.currents(vbe, vbc);
+                linearized
+                    .iter()
+                    .zip(actual.iter())
+                    .all(|(current, actual)| {
+                        let predicted = current.value
+                            + current.dbe * (vbe - junctions[0])
+                            + current.dbc * (vbc - junctions[1]);
+                        is_close(predicted, actual.value)
+                    })
+            }
+            _ => true,
+        }
+    }
+
+    /// Solve the circuit by Newton-Raphson from a guess. `linear` adds the linear part of the
+    /// system, the junctions are linearized at the previous solution on each iteration. A
+    /// linear circuit is solved at once.
+    pub fn newton(
+        &self,
+        guess: &[f64],
+        iterations: usize,
+        linear: impl Fn(&mut Matrix<f64>),
+    ) -> Result<Vec<f64>, error::Sim> {
+        if !self.is_nonlinear() {
+            let mut matrix = Matrix::new(self.size());
+            linear(&mut matrix);
+            return matrix.solve();
+        }
+        let mut x = guess.to_vec();
+        let mut junctions = self
+            .elements
+            .iter()
+            .map(|element| self.junctions(&x, element))
+            .collect::<Vec<[f64; 2]>>();
+        for _ in 0..iterations {
+            let mut matrix = Matrix::new(self.size());
+            linear(&mut matrix);
+            let mut is_limited = false;
+            for (element, junction) in self.elements.iter().zip(junctions.iter_mut()) {
+                let voltages = self.junctions(&x, element);
+                let limited = match &element.kind {
+                    Kind::Diode(diode) => [diode.limit(voltages[0], junction[0]), 0.0],
+                    Kind::Bjt(bjt) => bjt.limit(voltages, *junction),
+                    _ => continue,
+                };
+                is_limited |= limited != voltages;
+                *junction = limited;
+                self.stamp_junctions(&mut matrix, element, limited, true);
+            }
+            let next = matrix.solve()?;
+            let is_converged = !is_limited
+                && self.is_converged(&x, &next)
+                && self
+                    .elements
+                    .iter()
+                    .zip(junctions.iter())
+                    .all(|(element, junction)| {
+                        self.is_element_converged(element, *junction, &next)
+                    });
+            x = next;
+            if is_converged {
+                return Ok(x);
+            }
+        }
+        Err(error::Sim::NativeNoConvergence)
+    }
+
+    /// The DC solution where `source` gives the value of the independent sources, from a guess
+    /// (ie: the previous point of a sweep). When Newton-Raphson does not converge, the solution
+    /// is approached by gmin stepping, then by source stepping.
+    pub fn dc(
+        &self,
+        source: impl Fn(&Source) -> f64,
+        guess: &[f64],
+    ) -> Result<Vec<f64>, error::Sim> {
+        // The solution with a conductance from every node to the ground and the sources scaled
+        // by a factor.
+        let solve = |gmin: f64, factor: f64, guess: &[f64]| {
+            self.newton(guess, DC_ITERATIONS, |matrix| {
+                self.stamp(matrix, |value| factor * source(value));
+                for node in 1..=self.nodes.names().len() {
+                    matrix.add(row(node), row(node), gmin);
+                }
+            })
+        };
+        match solve(0.0, 1.0, guess) {
+            Err(error::Sim::NativeNoConvergence) => {}
+            result => return result,
+        }
+        // The large conductances make the circuit almost linear, they are reduced until they
+        // vanish. Each solution is the guess of the next one.
+        let gmin_stepping = || {
+            let mut x = guess.to_vec();
+            let mut gmin = GMIN_STEPPING_START;
+            while gmin > GMIN {
+                x = solve(gmin, 1.0, &x)?;
+                gmin /= 10.0;
+            }
+            solve(0.0, 1.0, &x)
+        };
+        // The sources are raised from 0, the increment is halved when a step does not converge.
+        let source_stepping = || {
+            let mut x = solve(0.0, 0.0, &vec![0.0; self.size()])?;
+            let (mut factor, mut step): (f64, f64) = (0.0, 0.1);
+            while factor < 1.0 {
+                let next = (factor + step).min(1.0);
+                match solve(0.0, next, &x) {
+                    Ok(solution) => {
+                        x = solution;
+                        factor = next;
+                        step = (step * 2.0).min(0.5);
+                    }
+                    Err(error::Sim::NativeNoConvergence) if step > SOURCE_STEPPING_MIN => {
+                        step /= 2.0
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+            Ok(x)
+        };
+        gmin_stepping().or_else(|_| source_stepping())
+    }
+
+    /// The DC operating point: the capacitors are open circuits and the inductors are short
+    /// circuits.
+    pub fn operating_point(&self) -> Result<Vec<f64>, error::Sim> {
+        self.dc(|source| source.dc, &vec![0.0; self.size()])
+    }
+
+    /// The small-signal solution at a frequency (in Hz) around the operating point `op`. Only
+    /// the AC magnitudes of the sources excite the circuit. A capacitor is an admittance of
+    /// `jωC` and an inductor an impedance of `jωL`, the junctions are linearized at the
+    /// operating point.
+    pub fn ac(&self, frequency: f64, op: &[f64]) -> Result<Vec<Complex>, error::Sim> {
+        let mut matrix = Matrix::new(self.size());
+        let omega = 2.0 * std::f64::consts::PI * frequency;
+        self.stamp(&mut matrix, |source| source.ac);
+        for element in self.elements.iter() {
+            self.stamp_junctions(&mut matrix, element, self.junctions(op, element), false);
+            for (a, b, capacitance) in self.capacitances(op, element) {
+                matrix.conductance(row(a), row(b), Complex::new(0.0, omega * capacitance));
+            }
+            if let Kind::Inductor { inductance, .. } = element.kind {
+                let branch = self.branch(&element.name);
+                matrix.add(branch, branch, Complex::new(0.0, -omega * inductance));
+            }
+        }
+        matrix.solve()
+    }
+}
@@ -0,0 +1,594 @@
+//! A simulator written in Rust for the netlists of the circuits (see `simulate_in_chunks`).
+//!
+//! The matrices are built from the netlist of a run and not from the nodes of
+//! `sim::circuit::Circuit`. Every backend receives the same netlist (see
+//! `backend::SimulatorBackend::submit`) and the netlist of a run already has the values of its
+//! sweeps, its temperature, its Monte Carlo draw and its models. The elements follow the cards
+//! of the netlist: their parameters are read like ngspice reads them (`key=value`) and the
+//! internal nodes are named like ngspice names them (`name#internal`).
+
+mod device;
+mod element;
+mod matrix;
+mod mna;
+pub mod transient;
+
+use crate::error;
+use crate::intrinsics::*;
+use crate::sim::netlist;
+use crate::sim::temperature;
+use std::collections::HashMap;
+use std::future::Future;
+
+/// What a column of the output measures, a probe of the `.print` card.
+enum Measure {
+    /// The voltage between two nodes (`v(a,b)`).
+    Voltage(usize, usize),
+    /// The current of an element with a branch (`i(V1)`).
+    Current(usize),
+}
+
+/// The part of a complex value printed in a column of an AC analysis (`vr(a,b)` or `ii(V1)`).
+#[derive(Clone, Copy)]
+enum Part {
+    Real,
+    Imaginary,
+}
+
+struct Probe {
+    measure: Measure,
+    part: Part,
+}
+
+impl Probe {
+    /// Read a probe printed by ngspice: `v(a,b)`, `i(V1)` and their real (`vr`, `ir`) and
+    /// imaginary (`vi`, `ii`) parts.
+    fn parse(text: &str, mna: &mna::Mna) -> Result<Self, error::Error> {
+        let unknown =
+            || -> error::Error { Box::new(error::Sim::NativeUnknownProbe(text.to_string())) };
+        let (typ, args) = text.split_once('(').ok_or_else(unknown)?;
+        let args = args.strip_suffix(')').ok_or_else(unknown)?;
+        let typ = typ.to_lowercase();
+        let mut letters = typ.chars();
+        let letter = letters.next().ok_or_else(unknown)?;
+        let part = match letters.as_str() {
+            "i" => Part::Imaginary,
+            _ => Part::Real,
+        };
+        let measure = match letter {
+            'v' => {
+                let mut nodes = args.split(',').map(|node| mna.nodes.get(node.trim()));
+                match (nodes.next().flatten(), nodes.next().unwrap_or(Some(0))) {
+                    (Some(a), Some(b)) => Measure::Voltage(a, b),
+                    _ => return Err(unknown()),
+                }
+            }
+            'i' => Measure::Current(mna.branch(args).ok_or_else(unknown)?),
+            _ => return Err(unknown()),
+        };
+        Ok(Self { measure, part })
+    }
+
+    fn value<T: matrix::Scalar>(&self, mna: &mna::Mna, x: &[T]) -> T {
+        match self.measure {
+            Measure::Voltage(a, b) => mna.voltage(x, a) - mna.voltage(x, b),
+            Measure::Current(branch) => x[branch],
+        }
+    }
+
+    fn complex(&self, mna: &mna::Mna, x: &[Complex]) -> f64 {
+        let value = self.value(mna, x);
+        match self.part {
+            Part::Real => value.re,
+            Part::Imaginary => value.im,
+        }
+    }
+}
+
+/// Print a table like ngspice: a header (`Index x_label probes...`) then a row per point.
+fn table(x_label: &str, probes: &[&str], rows: Vec<(f64, Vec<f64>)>) -> String {
+    let mut output = format!("Index\t{}\t{}\n", x_label, probes.join("\t"));
+    rows.into_iter().enumerate().for_each(|(idx, (x, values))| {
+        output.push_str(&format!("{}\t{:e}", idx, x));
+        values
+            .iter()
+            .for_each(|value| output.push_str(&format!("\t{:e}", value)));
+        output.push('\n');
+    });
+    output
+}
+
+/// The frequencies of an AC analysis: `np` points per decade (`dec`) or per octave (`oct`), or
+/// `np` points in total (`lin`).
+fn frequencies(variation: &str, np: f64, start: f64, stop: f64) -> Vec<f64> {
+    let ratio = match variation {
+        "dec" => 10f64.powf(1.0 / np),
+        "oct" => 2f64.powf(1.0 / np),
+        _ => {
+            let step = if np > 1.0 {
+                (stop - start) / (np - 1.0)
+            } else {
+                0.0
+            };
+            return crate::sim::sweep_values(start, stop, step);
+        }
+    };
+    let mut frequencies = Vec::new();
+    let mut frequency = start;
+    while frequency <= stop * (1.0 + 1e-9) && frequencies.len() < 100_000 {
+        frequencies.push(frequency);
+        frequency *= ratio;
+    }
+    frequencies
+}
+
+/// Simulate a netlist at once, without pausing its analysis (see `simulate_in_chunks`).
+#[cfg(test)]
+pub fn simulate(netlist: &str) -> Result<String, error::Error> {
+    use std::task::{Context, Poll, Waker};
+    // The analysis never waits, it is done after a single poll.
+    let simulation = std::pin::pin!(simulate_in_chunks(netlist, |_| std::future::ready(Ok(()))));
+    match simulation.poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("the simulation is never paused"),
+    }
+}
+
+/// Simulate a netlist generated by `circuit::Circuit::to_string` without ngspice. Only the linear
+/// elements (resistors, capacitors, inductors, independent and controlled sources), the diodes,
+/// the bipolar transistors and the operating point, DC, AC and transient analyses are supported.
+/// The results are printed like ngspice prints them so they are read by the same parsers (see
+/// `plot::parser`).
+///
+/// A transient analysis is paused regularly to await `pause` with its progress (between 0 and
+/// 1), the simulation is abandoned with the error of `pause`.
+pub async fn simulate_in_chunks<P, F>(netlist: &str, pause: P) -> Result<String, error::Error>
+where
+    P: FnMut(f64) -> F,
+    F: Future<Output = Result<(), error::Error>>,
+{
+    let cards = netlist::cards(netlist);
+    let temperature = cards
+        .iter()
+        .find(|card| card.keyword() == ".temp")
+        .and_then(|card| card.words.get(1))
+        .and_then(|word| element::value(word))
+        .unwrap_or(temperature::NOMINAL);
+    let models = cards
+        .iter()
+        .filter(|card| card.keyword() == ".model")
+        .filter_map(|card| Some((card.words.get(1)?.to_lowercase(), card)))
+        .collect::<HashMap<String, &netlist::Card>>();
+    let mut nodes = mna::Nodes::default();
+    let mut elements = Vec::new();
+    let mut analysis = None;
+    let mut options = None;
+    let mut probes = Vec::new();
+    let mut is_definition = false;
+    for card in cards.iter() {
+        match &card.keyword()[..] {
+            ".subckt" => is_definition = true,
+            ".ends" => is_definition = false,
+            _ if is_definition => {}
+            ".temp" | ".model" => {}
+            ".options" => options = Some(card),
+            ".op" | ".dc" | ".ac" | ".tran" => analysis = Some(card),
+            ".print" => {
+                probes = card.line.split_whitespace().skip(2).collect::<Vec<&str>>();
+            }
+            keyword if keyword.starts_with('.') => {
+                return Err(Box::new(error::Sim::NativeUnsupportedAnalysis(
+                    keyword.to_string(),
+                )))
+            }
+            _ => elements.push(element::Element::parse(
+                card,
+                &mut nodes,
+                temperature,
+                &models,
+            )?),
+        }
+    }
+    let mut mna = mna::Mna::new(nodes, elements)?;
+    let analysis = analysis.ok_or(error::Sim::NativeNoAnalysis)?;
+    let columns = probes
+        .iter()
+        .map(|probe| Probe::parse(probe, &mna))
+        .collect::<Result<Vec<Probe>, error::Error>>()?;
+    let invalid =
+        || -> error::Error { Box::new(error::Sim::NativeInvalidCard(analysis.line.clone())) };
+    let number = |idx: usize| {
+        analysis
+            .words
+            .get(idx)
+            .and_then(|word| element::value(word))
+            .ok_or_else(invalid)
+    };
+    match &analysis.keyword()[..] {
+        ".op" => operating_point(&mna),
+        ".ac" => {
+            let variation = analysis.word(1).map_err(|_| invalid())?.to_lowercase();
+            let frequencies = frequencies(&variation, number(2)?, number(3)?, number(4)?);
+            let op = mna.operating_point()?;
+            let rows = frequencies
+                .into_iter()
+                .map(|frequency| {
+                    let x = mna.ac(frequency, &op)?;
+                    let values = columns.iter().map(|probe| probe.complex(&mna, &x));
+                    Ok((frequency, values.collect()))
+                })
+                .collect::<Result<Vec<(f64, Vec<f64>)>, error::Error>>()?;
+            Ok(table("frequency", &probes, rows))
+        }
+        ".tran" => {
+            // Only the first order of the gear method is supported, it is the backward Euler
+            // method.
+            let method = match options.and_then(|options| options.param("method")) {
+                Some(method) if method.eq_ignore_ascii_case("gear") => transient::Method::Euler,
+                _ => transient::Method::Trapezoidal,
+            };
+            let settings = transient::Settings {
+                step: number(1)?,
+                stop: number(2)?,
+                method,
+                uic: analysis
+                    .words
+                    .iter()
+                    .any(|word| word.eq_ignore_ascii_case("uic")),
+            };
+            let series = transient::simulate(&mna, &settings, &columns, pause).await?;
+            let rows = series
+                .first()
+                .map_or(Vec::new(), |points| {
+                    points.iter().map(|point| point.x).collect()
+                })
+                .into_iter()
+                .enumerate()
+                .map(|(idx, time)| (time, series.iter().map(|points| points[idx].y).collect()))
+                .collect();
+            Ok(table("time", &probes, rows))
+        }
+        _ => {
+            // The first source is swept for each value of the second one.
+            let sweeps = analysis.words[1..]
+                .chunks(4)
+                .map(|sweep| match sweep {
+                    [source, start, stop, step] => {
+                        match (
+                            element::value(start),
+                            element::value(stop),
+                            element::value(step),
+                        ) {
+                            (Some(start), Some(stop), Some(step)) => {
+                                Ok((source.clone(), crate::sim::sweep_values(start, stop, step)))
+                            }
+                            _ => Err(invalid()),
+                        }
+                    }
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<Vec<(String, Vec<f64>)>, error::Error>>()?;
+            let (inner, outer) = match &sweeps[..] {
+                [inner] => (inner, None),
+                [inner, outer] => (inner, Some(outer)),
+                _ => return Err(invalid()),
+            };
+            let outer_values = outer.map_or(vec![None], |(_, values)| {
+                values.iter().map(|&value| Some(value)).collect()
+            });
+            let mut rows = Vec::new();
+            // Each point starts from the solution of the previous one.
+            let mut x = vec![0.0; mna.size()];
+            for outer_value in outer_values {
+                if let (Some((source, _)), Some(value)) = (outer, outer_value) {
+                    mna.source_mut(source)
+                        .ok_or_else(|| error::Sim::NativeUnknownSource(source.clone()))?
+                        .dc = value;
+                }
+                for &value in inner.1.iter() {
+                    mna.source_mut(&inner.0)
+                        .ok_or_else(|| error::Sim::NativeUnknownSource(inner.0.clone()))?
+                        .dc = value;
+                    x = mna.dc(|source| source.dc, &x)?;
+                    let values = columns.iter().map(|probe| probe.value(&mna, &x));
+                    rows.push((value, values.collect()));
+                }
+            }
+            let x_label = if inner.0.to_lowercase().starts_with('i') {
+                "i-sweep"
+            } else {
+                "v-sweep"
+            };
+            Ok(table(x_label, &probes, rows))
+        }
+    }
+}
+
+/// The voltage of every node (`name value`) and the current of every element with a branch
+/// (`name#branch value`) in 2 tables, like the output of ngspice (see `op::OperatingPoint::parse`).
+fn operating_point(mna: &mna::Mna) -> Result<String, error::Error> {
+    let x = mna.operating_point()?;
+    let mut output = String::from("\tNode\tVoltage\n\t----\t-------\n");
+    mna.nodes
+        .names()
+        .iter()
+        .enumerate()
+        .for_each(|(idx, node)| {
+            output.push_str(&format!("{}\t{:e}\n", node, mna.voltage(&x, idx + 1)));
+        });
+    output.push_str("\n\tSource\tCurrent\n\t------\t-------\n");
+    mna.elements.iter().for_each(|element| {
+        if let Some(branch) = mna.branch(&element.name) {
+            output.push_str(&format!(
+                "{}#branch\t{:e}\n",
+                element.name.to_lowercase(),
+                x[branch]
+            ));
+        }
+    });
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f64 = 1e-6;
+
+    /// The value of a line (`name value`) of the output of an operating point analysis.
+    fn op_value(output: &str, name: &str) -> f64 {
+        output
+            .lines()
+            .find_map(|line| {
+                let cols = line.split_whitespace().collect::<Vec<&str>>();
+                match cols[..] {
+                    [col, value] if col == name => value.parse::<f64>().ok(),
+                    _ => None,
+                }
+            })
+            .unwrap()
+    }
+
+    /// The rows of a table (x then the values of the probes).
+    fn rows(output: &str) -> Vec<Vec<f64>> {
+        output
+            .lines()
+            .skip(1)
+            .map(|line| {
+                line.split_whitespace()
+                    .skip(1)
+                    .map(|col| col.parse::<f64>().unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn operating_point_is_read_like_ngspice() {
+        let output = simulate("A Circuit\nV1 a 0 dc 10\nR1 a b 1k\nR2 b 0 3k\n.op\n.end").unwrap();
+        let op = crate::sim::op::OperatingPoint::parse(&output).unwrap();
+        assert!((op.voltage("b").unwrap() - 7.5).abs() < TOLERANCE);
+        assert!((op.current("V1").unwrap() + 2.5e-3).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn unknown_probes() {
+        for probe in ["(a)", "é(a)", "v(z)"] {
+            let netlist = format!(
+                "A Circuit\nV1 a 0 dc 10\nR1 a 0 1k\n.dc V1 0 1 1\n.print dc {}\n.end",
+                probe
+            );
+            assert!(simulate(&netlist).is_err(), "{}", probe);
+        }
+    }
+
+    #[test]
+    fn voltage_divider() {
+        let output = simulate(
+            "A Circuit\nV1 a 0 dc 10\nR1 a b 1k\nR2 b 0 3k\nL1 b c 1m\nC1 c 0 1u\n.op\n.end",
+        )
+        .unwrap();
+        assert!((op_value(&output, "b") - 7.5).abs() < TOLERANCE);
+        // The inductor is a short circuit and the capacitor is an open circuit.
+        assert!((op_value(&output, "c") - 7.5).abs() < TOLERANCE);
+        assert!((op_value(&output, "v1#branch") + 2.5e-3).abs() < TOLERANCE);
+        assert!(op_value(&output, "l1#branch").abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn controlled_sources() {
+        // E doubles the voltage of `a`, F copies the current of the ampermeter `Vam` in `R3`.
+        let netlist = "A Circuit\nV1 a 0 dc 1\nR1 a 0 1k\nE1 b 0 a 0 2\nVam b c dc 0\nR2 c 0 1k\n\
+                       F1 0 d Vam 1\nR3 d 0 500\nG1 0 e a 0 1m\nR4 e 0 2k\nH1 f 0 Vam 1k\nR5 f 0 1k\n\
+                       .dc V1 0 2 1\n.print dc v(b,0) i(Vam) v(d,0) v(e,0) v(f,0)\n.end";
+        let rows = rows(&simulate(netlist).unwrap());
+        assert_eq!(rows.len(), 3);
+        let expected = [
+            2.0 * 2.0,
+            2.0 * 2.0 / 1000.0,
+            4e-3 * 500.0,
+            2e-3 * 2000.0,
+            4.0,
+        ];
+        rows[2][1..]
+            .iter()
+            .zip(expected.iter())
+            .for_each(|(value, expected)| assert!((value - expected).abs() < TOLERANCE));
+    }
+
+    #[test]
+    fn rc_low_pass_cutoff() {
+        // The cut-off frequency is 1 / (2πRC), the gain is -3 dB and the phase is -45°.
+        let cutoff = 1.0 / (2.0 * std::f64::consts::PI * 1e3 * 1e-6);
+        let netlist = format!(
+            "A Circuit\nV1 a 0 ac 1 SIN(0 1 1k 0 0 0)\nR1 a b 1k\nC1 b 0 1u\n\
+             .ac lin 1 {} {}\n.print ac vr(b,0) vi(b,0)\n.end",
+            cutoff, cutoff
+        );
+        let rows = rows(&simulate(&netlist).unwrap());
+        let value = Complex::new(rows[0][1], rows[0][2]);
+        assert!((value.db() + 10.0 * 2f64.log10()).abs() < 1e-6);
+        assert!((value.phase() + 45.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn decade_frequencies() {
+        let frequencies = frequencies("dec", 10.0, 1.0, 1e3);
+        assert_eq!(frequencies.len(), 31);
+        assert!((frequencies[30] - 1e3).abs() < 1e-6);
+    }
+
+    /// The largest difference between a series of a transient analysis and its expected values.
+    fn transient_error(netlist: &str, expected: impl Fn(f64) -> f64) -> f64 {
+        rows(&simulate(netlist).unwrap())
+            .iter()
+            .map(|row| (row[1] - expected(row[0])).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn rc_charge() {
+        let tau = 1e-3;
+        for method in ["trap", "gear maxord=1"] {
+            let netlist = format!(
+                "A Circuit\nV1 a 0 dc 10\nR1 a b 1k\nC1 b 0 1u ic=0\n.options NOACCT method={}\n\
+                 .tran 10u 5m uic\n.print tran v(b,0)\n.end",
+                method
+            );
+            let error = transient_error(&netlist, |time| 10.0 * (1.0 - (-time / tau).exp()));
+            assert!(error < 0.05, "{}: {}", method, error);
+        }
+    }
+
+    #[test]
+    fn capacitor_initial_condition() {
+        let netlist = "A Circuit\nR1 b 0 1k\nC1 b 0 1u ic=5\n.tran 10u 5m uic\n\
+                       .print tran v(b,0)\n.end";
+        let error = transient_error(netlist, |time| 5.0 * (-time / 1e-3).exp());
+        assert!(error < 0.01, "{}", error);
+    }
+
+    #[test]
+    fn lc_oscillation() {
+        // The inductor starts with 1 mA, the amplitude of the voltage is `I √(L/C)`. The
+        // trapezoidal method keeps the amplitude, the backward Euler method damps it.
+        let amplitude = 1e-3 * (1e-3f64 / 1e-6).sqrt();
+        let period = 2.0 * std::f64::consts::PI * (1e-3f64 * 1e-6).sqrt();
+        let last_period = |method: &str| {
+            let netlist = format!(
+                "A Circuit\nL1 a 0 1m ic=1m\nC1 a 0 1u\n.options NOACCT method={}\n\
+                 .tran 1u 2m uic\n.print tran v(a,0)\n.end",
+                method
+            );
+            rows(&simulate(&netlist).unwrap())
+                .iter()
+                .filter(|row| row[0] > 2e-3 - period)
+                .map(|row| row[1].abs())
+                .fold(0.0, f64::max)
+        };
+        assert!((last_period("trap") - amplitude).abs() < 0.02 * amplitude);
+        assert!(last_period("gear maxord=1") < 0.9 * amplitude);
+    }
+
+    #[test]
+    fn adaptive_step() {
+        // The step is small where the capacitor charges and grows once it is charged.
+        let netlist = "A Circuit\nV1 a 0 dc 10\nR1 a b 1k\nC1 b 0 1u\n.tran 1m 100m uic\n\
+                       .print tran v(b,0)\n.end";
+        let times = rows(&simulate(netlist).unwrap())
+            .iter()
+            .map(|row| row[0])
+            .collect::<Vec<f64>>();
+        let steps = times.windows(2).map(|pair| pair[1] - pair[0]);
+        let (first, last) = (times[1] - times[0], steps.last().unwrap());
+        assert!(first < 1e-4 && last > 5e-4, "{} {}", first, last);
+        assert!((times.last().unwrap() - 100e-3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unsupported_device() {
+        assert!(simulate("A Circuit\nV1 a 0 dc 1\nJ1 a 0 0 jfet\n.op\n.end").is_err());
+    }
+
+    const DIODE: &str = ".model dmod d(IS=10f RS=2 N=1.5 BV=20 IBV=1m)";
+
+    #[test]
+    fn diode_forward_curve() {
+        // The voltage of a diode driven by a current `I` is `n Vt ln(I / IS + 1) + RS I`.
+        let netlist = format!(
+            "A Circuit\nI1 0 a dc 1m\nD1 a 0 dmod\n{}\n.dc I1 1u 10m 1m\n.print dc v(a,0)\n.end",
+            DIODE
+        );
+        let nvt = 1.5 * device::thermal_voltage(temperature::NOMINAL);
+        rows(&simulate(&netlist).unwrap()).iter().for_each(|row| {
+            let expected = nvt * (row[0] / 10e-15 + 1.0).ln() + 2.0 * row[0];
+            assert!((row[1] - expected).abs() < 1e-4, "{} {}", row[1], expected);
+        });
+    }
+
+    #[test]
+    fn diode_breakdown() {
+        // At the current `IBV`, the reverse voltage of the junction is the breakdown voltage.
+        let netlist = format!("A Circuit\nI1 a 0 dc 1m\nD1 a 0 dmod\n{}\n.op\n.end", DIODE);
+        let output = simulate(&netlist).unwrap();
+        assert!((op_value(&output, "a") + 20.0 + 2e-3).abs() < 1e-3);
+    }
+
+    #[test]
+    fn diode_small_signal() {
+        // The small-signal resistance of the junction is `n Vt / I`.
+        let netlist = format!(
+            "A Circuit\nI1 0 a dc 1m ac 1\nD1 a 0 dmod\n{}\n.ac lin 1 1k 1k\n\
+             .print ac vr(a,0) vi(a,0)\n.end",
+            DIODE
+        );
+        let rows = rows(&simulate(&netlist).unwrap());
+        let expected = 1.5 * device::thermal_voltage(temperature::NOMINAL) / 1e-3 + 2.0;
+        assert!((rows[0][1] - expected).abs() < 1e-3 * expected);
+        assert!(rows[0][2].abs() < 1e-9);
+    }
+
+    #[test]
+    fn half_wave_rectifier() {
+        let netlist = "A Circuit\nV1 a 0 SIN(0 5 1k 0 0 0)\nD1 a b diode-1N4148\nR1 b 0 1k\n\
+                       .model diode-1N4148 d(IS=35p RS=64m N=1.24 TT=5n CJO=4p M=0.285 VJ=0.6 BV=75)\n\
+                       .tran 10u 2m\n.print tran v(a,0) v(b,0)\n.end";
+        let rows = rows(&simulate(netlist).unwrap());
+        let peak = rows.iter().map(|row| row[2]).fold(f64::MIN, f64::max);
+        let lowest = rows.iter().map(|row| row[2]).fold(f64::MAX, f64::min);
+        // The peak loses the forward voltage of the diode, the negative half-waves are blocked.
+        assert!(peak > 4.0 && peak < 4.5, "{}", peak);
+        assert!(lowest > -1e-3, "{}", lowest);
+        rows.iter()
+            .for_each(|row| assert!(row[2] <= row[1].max(0.0) + 1e-3, "{:?}", row));
+    }
+
+    #[test]
+    fn bjt_bias() {
+        // The base current is set by `RB`, the collector current is `BF` times larger and the
+        // base-emitter voltage follows the Shockley equation. A PNP transistor mirrors it.
+        for (model, sign) in [("npn", 1.0), ("pnp", -1.0)] {
+            let netlist = format!(
+                "A Circuit\nVcc vcc 0 dc {}\nRb vcc b 430k\nVb b bq dc 0\nRc vcc c 1k\n\
+                 Vc c cq dc 0\nQ1 cq bq 0 qmod\n.model qmod {}(IS=1f BF=100)\n.op\n.end",
+                sign * 10.0,
+                model
+            );
+            let output = simulate(&netlist).unwrap();
+            let (base, collector) = (
+                op_value(&output, "vb#branch"),
+                op_value(&output, "vc#branch"),
+            );
+            assert!(
+                (collector / base - 100.0).abs() < 1e-3,
+                "{}",
+                collector / base
+            );
+            let vbe = sign * op_value(&output, "bq");
+            let vt = device::thermal_voltage(temperature::NOMINAL);
+            let expected = vt * (sign * collector / 1e-15 + 1.0).ln();
+            assert!((vbe - expected).abs() < 1e-3, "{} {}", vbe, expected);
+            assert!((sign * op_value(&output, "c") - (10.0 - 1e3 * sign * collector)).abs() < 1e-6);
+        }
+    }
+}
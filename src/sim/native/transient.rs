@@ -0,0 +1,309 @@
+use crate::error;
+use crate::intrinsics::*;
+use crate::sim::native::element::{Element, Kind};
+use crate::sim::native::matrix::Matrix;
+use crate::sim::native::mna::{self, ABSTOL, RELTOL, VNTOL};
+use crate::sim::native::Probe;
+use std::future::Future;
+
+/// The factor by which the truncation error is overestimated (`trtol`).
+const TRTOL: f64 = 7.0;
+/// The length of the step that gives the solution at the time 0 from the initial conditions,
+/// relative to the maximum step.
+const INITIAL_STEP: f64 = 1e-9;
+/// The smallest step, relative to the maximum step, before the analysis is abandoned.
+const MIN_STEP: f64 = 1e-12;
+/// The factor by which the step is reduced when Newton-Raphson does not converge.
+const NO_CONVERGENCE_FACTOR: f64 = 0.125;
+/// The number of time points between two pauses of the analysis (see `simulate`).
+const CHUNK: usize = 100;
+
+/// How the capacitors and the inductors are integrated between two time points. Each of them is
+/// replaced by a companion model: a conductance with a current source for a capacitor, an
+/// impedance with a voltage source for an inductor.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Method {
+    /// The backward Euler method (`method=gear maxord=1` for ngspice), stable but damps the
+    /// oscillations.
+    Euler,
+    /// The trapezoidal method, the default of ngspice.
+    Trapezoidal,
+}
+
+pub struct Settings {
+    /// The step of the output, the internal step never exceeds it.
+    pub step: f64,
+    pub stop: f64,
+    pub method: Method,
+    /// Start from the initial conditions of the capacitors and the inductors (`uic`) instead of
+    /// the operating point. Without initial condition, a capacitor starts discharged and an
+    /// inductor without current.
+    pub uic: bool,
+}
+
+/// The voltage across a capacitance or an inductor and its current at the last time point.
+#[derive(Clone, Copy, Default)]
+struct State {
+    voltage: f64,
+    current: f64,
+}
+
+/// The companion model of a capacitance for a step of length `h`: the conductance and the
+/// current of its current source.
+fn capacitor(capacitance: f64, state: State, h: f64, method: Method) -> (f64, f64) {
+    match method {
+        Method::Euler => {
+            let conductance = capacitance / h;
+            (conductance, -conductance * state.voltage)
+        }
+        Method::Trapezoidal => {
+            let conductance = 2.0 * capacitance / h;
+            (conductance, -conductance * state.voltage - state.current)
+        }
+    }
+}
+
+/// The companion model of an inductor for a step of length `h`: the impedance and the voltage
+/// of its voltage source.
+fn inductor(inductance: f64, state: State, h: f64, method: Method) -> (f64, f64) {
+    match method {
+        Method::Euler => {
+            let impedance = inductance / h;
+            (impedance, -impedance * state.current)
+        }
+        Method::Trapezoidal => {
+            let impedance = 2.0 * inductance / h;
+            (impedance, -impedance * state.current - state.voltage)
+        }
+    }
+}
+
+struct Transient<'mna> {
+    mna: &'mna mna::Mna,
+    /// The solution at the last time point. The capacitances of the junctions are evaluated at
+    /// it during the next step.
+    x: Vec<f64>,
+    /// The states of each element: one per capacitance (see `Mna::capacitances`), one for an
+    /// inductor.
+    states: Vec<Vec<State>>,
+}
+
+impl<'mna> Transient<'mna> {
+    fn new(mna: &'mna mna::Mna) -> Self {
+        let x = vec![0.0; mna.size()];
+        let states = mna
+            .elements
+            .iter()
+            .map(|element| Self::initial_states(mna, &x, element))
+            .collect();
+        Self { mna, x, states }
+    }
+
+    /// Solve the circuit at `time` after a step of length `h` from the current states.
+    fn solve(&self, time: f64, h: f64, method: Method) -> Result<Vec<f64>, error::Sim> {
+        let mna = self.mna;
+        let capacitances = mna
+            .elements
+            .iter()
+            .map(|element| mna.capacitances(&self.x, element))
+            .collect::<Vec<Vec<(usize, usize, f64)>>>();
+        mna.newton(
+            &self.x,
+            mna::TRANSIENT_ITERATIONS,
+            |matrix: &mut Matrix<f64>| {
+                mna.stamp(matrix, |source| source.at(time));
+                let elements = mna.elements.iter().zip(capacitances.iter());
+                for ((element, capacitances), states) in elements.zip(self.states.iter()) {
+                    if let Kind::Inductor { inductance, .. } = element.kind {
+                        let branch = mna.branch(&element.name);
+                        let (impedance, voltage) = inductor(inductance, states[0], h, method);
+                        matrix.add(branch, branch, -impedance);
+                        matrix.add_rhs(branch, voltage);
+                    }
+                    for (&(a, b, capacitance), state) in capacitances.iter().zip(states.iter()) {
+                        let (conductance, current) = capacitor(capacitance, *state, h, method);
+                        matrix.conductance(mna::row(a), mna::row(b), conductance);
+                        matrix.current(mna::row(a), mna::row(b), current);
+                    }
+                }
+            },
+        )
+    }
+
+    /// Update the states with the solution of a step, it becomes the last solution. Returns the
+    /// previous one.
+    fn accept(&mut self, x: Vec<f64>, h: f64, method: Method) -> Vec<f64> {
+        let mna = self.mna;
+        for (element, states) in mna.elements.iter().zip(self.states.iter_mut()) {
+            if let Kind::Inductor { .. } = element.kind {
+                states[0] = State {
+                    voltage: mna.element_voltage(&x, element),
+                    current: mna.branch(&element.name).map_or(0.0, |branch| x[branch]),
+                };
+                continue;
+            }
+            // The capacitances are the ones of the step (at the previous solution).
+            let capacitances = mna.capacitances(&self.x, element);
+            for (&(a, b, capacitance), state) in capacitances.iter().zip(states.iter_mut()) {
+                let (conductance, current) = capacitor(capacitance, *state, h, method);
+                let voltage = mna.voltage(&x, a) - mna.voltage(&x, b);
+                *state = State {
+                    voltage,
+                    current: conductance * voltage + current,
+                };
+            }
+        }
+        std::mem::replace(&mut self.x, x)
+    }
+
+    /// The states of the solution at the time 0. The current of the capacitances is not known,
+    /// it is not used by the first step (see `simulate`).
+    fn reset(&mut self, x: Vec<f64>) {
+        let mna = self.mna;
+        for (element, states) in mna.elements.iter().zip(self.states.iter_mut()) {
+            *states = match element.kind {
+                Kind::Inductor { .. } => vec![State {
+                    voltage: mna.element_voltage(&x, element),
+                    current: mna.branch(&element.name).map_or(0.0, |branch| x[branch]),
+                }],
+                _ => mna
+                    .capacitances(&x, element)
+                    .iter()
+                    .map(|&(a, b, _)| State {
+                        voltage: mna.voltage(&x, a) - mna.voltage(&x, b),
+                        current: 0.0,
+                    })
+                    .collect(),
+            };
+        }
+        self.x = x;
+    }
+
+    /// The states of an element at the time 0 from the initial conditions. The junctions start
+    /// from the solution `x`.
+    fn initial_states(mna: &mna::Mna, x: &[f64], element: &Element) -> Vec<State> {
+        match element.kind {
+            Kind::Capacitor { init_cond, .. } => vec![State {
+                voltage: init_cond.unwrap_or(0.0),
+                current: 0.0,
+            }],
+            Kind::Inductor { init_cond, .. } => vec![State {
+                voltage: 0.0,
+                current: init_cond.unwrap_or(0.0),
+            }],
+            _ => vec![State::default(); mna.capacitances(x, element).len()],
+        }
+    }
+}
+
+/// The local truncation error of a step relative to its tolerance (the step is accepted under
+/// 1). The error is estimated with the difference between the solution and its linear
+/// extrapolation from the two previous time points, which grows with the second derivative of
+/// the solution.
+fn error_ratio(mna: &mna::Mna, x: &[f64], previous: &[f64], predicted: &[f64]) -> f64 {
+    let nodes = mna.nodes.names().len();
+    x.iter()
+        .zip(previous.iter())
+        .zip(predicted.iter())
+        .enumerate()
+        .map(|(idx, ((x, previous), predicted))| {
+            let absolute = if idx < nodes { VNTOL } else { ABSTOL };
+            let tolerance = RELTOL * x.abs().max(previous.abs()) + absolute;
+            (x - predicted).abs() / (TRTOL * tolerance)
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Simulate the circuit from the time 0 to `stop`. The step is adapted to keep the local
+/// truncation error under its tolerance: it shrinks where the solution bends and grows (up to
+/// `step`) where it is smooth. Returns a series per probe, each point is a time point.
+///
+/// The analysis pauses every `CHUNK` time points to await `pause` with its progress (between 0
+/// and 1), it is abandoned with the error of `pause`.
+pub(super) async fn simulate<P, F>(
+    mna: &mna::Mna,
+    settings: &Settings,
+    probes: &[Probe],
+    mut pause: P,
+) -> Result<Vec<Vec<Point>>, error::Error>
+where
+    P: FnMut(f64) -> F,
+    F: Future<Output = Result<(), error::Error>>,
+{
+    let max_step = settings.step.min(settings.stop / 50.0);
+    if max_step.is_nan() || max_step <= 0.0 {
+        return Err(Box::new(error::Sim::NativeInvalidStep));
+    }
+    let mut transient = Transient::new(mna);
+    let x = if settings.uic {
+        // A tiny step from the initial states gives a solution where the capacitors keep their
+        // voltages and the inductors their currents.
+        transient.solve(0.0, INITIAL_STEP * max_step, Method::Euler)?
+    } else {
+        mna.operating_point()?
+    };
+    transient.reset(x);
+    let mut series = vec![Vec::new(); probes.len()];
+    let mut push = |time: f64, x: &[f64]| {
+        probes
+            .iter()
+            .zip(series.iter_mut())
+            .for_each(|(probe, points)| points.push(Point::new(time, probe.value(mna, x))));
+    };
+    push(0.0, &transient.x);
+    let mut points = 1;
+    let mut time = 0.0;
+    let mut h = max_step / 100.0;
+    // The previous time point and the step that led to the current one, for the extrapolation.
+    let mut previous: Option<(Vec<f64>, f64)> = None;
+    while time < settings.stop * (1.0 - 1e-12) {
+        h = h.min(settings.stop - time);
+        // The first step uses the backward Euler method, the trapezoidal method needs the
+        // current of the capacitors which is not known at the time 0.
+        let method = match previous {
+            Some(_) => settings.method,
+            None => Method::Euler,
+        };
+        let next = match transient.solve(time + h, h, method) {
+            Ok(next) => next,
+            Err(error::Sim::NativeNoConvergence) => {
+                h *= NO_CONVERGENCE_FACTOR;
+                if h < MIN_STEP * max_step {
+                    return Err(Box::new(error::Sim::NativeTimestepTooSmall(time)));
+                }
+                continue;
+            }
+            Err(error) => return Err(Box::new(error)),
+        };
+        let x = &transient.x;
+        let ratio = match &previous {
+            Some((before, previous_h)) => {
+                let predicted = x
+                    .iter()
+                    .zip(before.iter())
+                    .map(|(x, before)| x + (x - before) * h / previous_h)
+                    .collect::<Vec<f64>>();
+                error_ratio(mna, &next, x, &predicted)
+            }
+            None => 0.0,
+        };
+        // The new step is the step that would give an error of 90% of the tolerance.
+        let factor = if ratio > 0.0 { 0.9 / ratio.sqrt() } else { 2.0 };
+        if ratio > 1.0 {
+            h *= factor.max(0.25);
+            if h < MIN_STEP * max_step {
+                return Err(Box::new(error::Sim::NativeTimestepTooSmall(time)));
+            }
+            continue;
+        }
+        time += h;
+        push(time, &next);
+        previous = Some((transient.accept(next, h, method), h));
+        h = (h * factor.min(2.0)).min(max_step);
+        points += 1;
+        if points % CHUNK == 0 {
+            pause(time / settings.stop).await?;
+        }
+    }
+    Ok(series)
+}
@@ -0,0 +1,167 @@
+use crate::{error, unit};
+
+/// A line of the netlist (continuation lines included) split into words. The parameters
+/// (`key=value`) are kept apart, their keys are in lowercase.
+pub struct Card {
+    pub line: String,
+    pub words: Vec<String>,
+    params: Vec<(String, String)>,
+}
+
+impl Card {
+    pub fn new(line: String) -> Self {
+        let spaced = line.replace(['(', ')', ','], " ").replace('=', " = ");
+        let tokens = spaced.split_whitespace().collect::<Vec<&str>>();
+        let mut words = Vec::new();
+        let mut params = Vec::new();
+        let mut idx = 0;
+        while idx < tokens.len() {
+            if tokens.get(idx + 1) == Some(&"=") && idx + 2 < tokens.len() {
+                params.push((tokens[idx].to_lowercase(), tokens[idx + 2].to_string()));
+                idx += 3;
+            } else {
+                words.push(tokens[idx].to_string());
+                idx += 1;
+            }
+        }
+        Self {
+            line,
+            words,
+            params,
+        }
+    }
+
+    /// The lowercase keyword of a control card (ie: `.tran`) or the letter of an element card.
+    pub fn keyword(&self) -> String {
+        match self.words.first() {
+            Some(word) if word.starts_with('.') => word.to_lowercase(),
+            Some(word) => word.chars().take(1).flat_map(char::to_uppercase).collect(),
+            None => String::new(),
+        }
+    }
+
+    pub fn word(&self, idx: usize) -> Result<&str, error::Import> {
+        self.words
+            .get(idx)
+            .map(|word| &word[..])
+            .ok_or_else(|| self.invalid())
+    }
+
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| &value[..])
+    }
+
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    pub fn invalid(&self) -> error::Import {
+        error::Import::InvalidCard(self.line.clone())
+    }
+}
+
+/// Split the netlist into cards. The first line is the title, the comments and the `.control`
+/// blocks (ngspice scripts) are skipped and the netlist ends at `.end`.
+pub fn cards(input: &str) -> Vec<Card> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut is_control = false;
+    for line in input.lines().skip(1) {
+        let line = match line.find(';') {
+            Some(idx) => &line[..idx],
+            None => line,
+        };
+        let line = line.trim();
+        let keyword = line.split_whitespace().next().unwrap_or("").to_lowercase();
+        match &keyword[..] {
+            ".control" => is_control = true,
+            ".endc" => is_control = false,
+            ".end" if !is_control => break,
+            _ if is_control || line.is_empty() || line.starts_with('*') => {}
+            _ if line.starts_with('+') => {
+                if let Some(last) = lines.last_mut() {
+                    last.push(' ');
+                    last.push_str(&line[1..]);
+                }
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines.into_iter().map(Card::new).collect()
+}
+
+/// A number written in SPICE (ie: `4.7k`, `10uF`, `1Meg` or `1e-3`) and the prefix of its scale
+/// factor. The letters after the scale factor (usually a unit) are ignored.
+pub fn number(text: &str) -> Option<(f64, unit::Prefix)> {
+    let end = (1..=text.len())
+        .rev()
+        .filter(|&end| text.is_char_boundary(end))
+        .find(|&end| text[..end].parse::<f64>().is_ok())?;
+    let value = text[..end].parse::<f64>().ok()?;
+    let suffix = text[end..].to_lowercase();
+    if suffix.starts_with("meg") {
+        return Some((value, unit::Prefix::Mega));
+    } else if suffix.starts_with("mil") {
+        return Some((value * 25.4e-6, unit::Prefix::None));
+    }
+    Some(match suffix.chars().next() {
+        Some('t') => (value * 1e3, unit::Prefix::Giga),
+        Some('g') => (value, unit::Prefix::Giga),
+        Some('k') => (value, unit::Prefix::Kilo),
+        Some('m') => (value, unit::Prefix::Milli),
+        Some('u') | Some('µ') | Some('μ') => (value, unit::Prefix::Micro),
+        Some('n') => (value, unit::Prefix::Nano),
+        Some('p') => (value, unit::Prefix::Pico),
+        Some('f') => (value * 1e-3, unit::Prefix::Pico),
+        _ => (value, unit::Prefix::None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continuations_and_comments() {
+        let cards = cards(
+            "Title R9 a b 1\n\
+             * A comment\n\
+             R1 in out 4.7k ; the load\n\
+             + tc1=1e-3\n\
+             \n\
+             .control\n\
+             run\n\
+             .endc\n\
+             C1 out 0 10uF IC=2\n\
+             .END\n\
+             R2 a b 1",
+        );
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].words, vec!["R1", "in", "out", "4.7k"]);
+        assert_eq!(cards[0].param("tc1"), Some("1e-3"));
+        assert_eq!(cards[1].keyword(), "C");
+        assert_eq!(cards[1].param("ic"), Some("2"));
+    }
+
+    #[test]
+    fn keywords() {
+        let keyword = |line: &str| Card::new(line.to_string()).keyword();
+        assert_eq!(keyword(".TRAN 1u 1m"), ".tran");
+        assert_eq!(keyword("vin in 0 1"), "V");
+        assert_eq!(keyword("ωout in 0 1"), "Ω");
+        assert_eq!(keyword(""), "");
+    }
+
+    #[test]
+    fn numbers() {
+        assert!(number("4.7k") == Some((4.7, unit::Prefix::Kilo)));
+        assert!(number("1Meg") == Some((1.0, unit::Prefix::Mega)));
+        assert!(number("10uF") == Some((10.0, unit::Prefix::Micro)));
+        assert!(number("1e-3") == Some((1e-3, unit::Prefix::None)));
+        assert!(number("2m") == Some((2.0, unit::Prefix::Milli)));
+        assert!(number("5µs") == Some((5.0, unit::Prefix::Micro)));
+        assert!(number("out").is_none());
+    }
+}
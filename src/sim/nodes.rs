@@ -0,0 +1,211 @@
+use crate::intrinsics::*;
+use crate::schema::wires;
+use std::collections::HashMap;
+
+/// The size of a cell of the spatial index. The wires are usually drawn on a grid of 10 so a
+/// cell contains a few segments.
+const CELL_SIZE: f64 = 50.0;
+/// The distance under which a point touches a wire (see `wires::Layout::collide_with_point`).
+const TOLERANCE: f64 = 5.0;
+
+/// Disjoint sets of wires. Each set is a node of the circuit.
+struct UnionFind {
+    parents: Vec<usize>,
+    ranks: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            ranks: vec![0; len],
+        }
+    }
+
+    fn find(&mut self, idx: usize) -> usize {
+        let mut root = idx;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        // Every element on the path points directly to the root for the next searches.
+        let mut idx = idx;
+        while self.parents[idx] != root {
+            let next = self.parents[idx];
+            self.parents[idx] = root;
+            idx = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.ranks[a] < self.ranks[b] {
+            self.parents[a] = b;
+        } else {
+            self.parents[b] = a;
+            if self.ranks[a] == self.ranks[b] {
+                self.ranks[a] += 1;
+            }
+        }
+    }
+}
+
+/// Items (wires or connectors) indexed by the cells of a grid that their segments go through.
+/// Only the items near a point need to be checked to find the items that touch this point.
+pub struct Grid {
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Grid {
+    /// Index the segments of the items, the segments of an item are given one after the other. A
+    /// point is a segment from the point to itself.
+    pub fn new(segments: impl Iterator<Item = (usize, Point, Point)>) -> Self {
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        segments.for_each(|(idx, start, end)| {
+            let (min_x, min_y) = Self::cell(Point::new(
+                start.x.min(end.x) - TOLERANCE,
+                start.y.min(end.y) - TOLERANCE,
+            ));
+            let (max_x, max_y) = Self::cell(Point::new(
+                start.x.max(end.x) + TOLERANCE,
+                start.y.max(end.y) + TOLERANCE,
+            ));
+            for x in min_x..=max_x {
+                for y in min_y..=max_y {
+                    let cell = cells.entry((x, y)).or_default();
+                    if cell.last() != Some(&idx) {
+                        cell.push(idx);
+                    }
+                }
+            }
+        });
+        Self { cells }
+    }
+
+    /// Index the segments of the wires.
+    pub fn wires(wires: &[wires::Wire]) -> Self {
+        Self::new(wires.iter().enumerate().flat_map(|(idx, wire)| {
+            let points = &wire.layout.shape.points;
+            let segments = points
+                .iter()
+                .zip(points.iter().skip(1))
+                .map(move |(start, end)| (idx, *start, *end));
+            // A wire with a single point is a point.
+            let point = match points[..] {
+                [point] => Some((idx, point, point)),
+                _ => None,
+            };
+            segments.chain(point)
+        }))
+    }
+
+    fn cell(point: Point) -> (i64, i64) {
+        (
+            (point.x / CELL_SIZE).floor() as i64,
+            (point.y / CELL_SIZE).floor() as i64,
+        )
+    }
+
+    /// The items that might touch a point.
+    pub fn near(&self, point: Point) -> &[usize] {
+        self.cells
+            .get(&Self::cell(point))
+            .map_or(&[], |wires| &wires[..])
+    }
+}
+
+/// Group the wires into nodes, each node is the list of the indices of its wires. Two wires are
+/// in the same node if they are connected (see `wires::Wire::collide_with_wire`). The nodes are
+/// sorted by their first wire.
+pub fn nodes(wires: &[wires::Wire]) -> Vec<Vec<usize>> {
+    let grid = Grid::wires(wires);
+    let mut sets = UnionFind::new(wires.len());
+    wires.iter().enumerate().for_each(|(idx, wire)| {
+        wire.layout.extremities().into_iter().for_each(|extremity| {
+            grid.near(extremity).iter().for_each(|&other| {
+                if other != idx && wire.collide_with_wire(&wires[other]) {
+                    sets.union(idx, other);
+                }
+            });
+        });
+    });
+    let mut nodes: Vec<Vec<usize>> = Vec::new();
+    let mut node_of_root = HashMap::new();
+    (0..wires.len()).for_each(|idx| {
+        let root = sets.find(idx);
+        let node = *node_of_root.entry(root).or_insert_with(|| {
+            nodes.push(Vec::new());
+            nodes.len() - 1
+        });
+        nodes[node].push(idx);
+    });
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wire(x1: f64, y1: f64, x2: f64, y2: f64) -> wires::Wire {
+        wires::Wire::new(Point::new(x1, y1), Point::new(x2, y2))
+    }
+
+    #[test]
+    fn t_junction_connects() {
+        let wires = vec![wire(0.0, 0.0, 100.0, 0.0), wire(50.0, 0.0, 50.0, 50.0)];
+        assert_eq!(nodes(&wires), vec![vec![0, 1]]);
+    }
+
+    #[test]
+    fn crossing_does_not_connect() {
+        let wires = vec![wire(0.0, 0.0, 100.0, 0.0), wire(50.0, -50.0, 50.0, 50.0)];
+        assert_eq!(nodes(&wires), vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn large_chain_is_one_node() {
+        let wires = (0..20_000)
+            .map(|idx| wire(idx as f64 * 10.0, 0.0, (idx + 1) as f64 * 10.0, 0.0))
+            .collect::<Vec<wires::Wire>>();
+        let nodes = nodes(&wires);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].len(), wires.len());
+    }
+
+    #[test]
+    fn large_combs_are_separate_nodes() {
+        // Each comb is a long wire with teeth that end on it (T-junctions). The combs are next to
+        // each other without touching.
+        let (combs, teeth) = (100, 50);
+        let mut wires = Vec::new();
+        for comb in 0..combs {
+            let y = comb as f64 * 40.0;
+            wires.push(wire(0.0, y, teeth as f64 * 20.0, y));
+            for tooth in 0..teeth {
+                let x = tooth as f64 * 20.0 + 10.0;
+                wires.push(wire(x, y, x, y + 20.0));
+            }
+        }
+        let nodes = nodes(&wires);
+        assert_eq!(nodes.len(), combs);
+        assert!(nodes.iter().all(|node| node.len() == teeth + 1));
+    }
+
+    #[test]
+    fn large_mesh_of_crossings_is_unconnected() {
+        // Horizontal and vertical wires that only cross each other.
+        let count = 150;
+        let length = count as f64 * 20.0;
+        let mut wires = Vec::new();
+        for idx in 0..count {
+            let position = idx as f64 * 20.0 + 10.0;
+            wires.push(wire(-10.0, position, length + 10.0, position));
+            wires.push(wire(position, -10.0, position, length + 10.0));
+        }
+        let nodes = nodes(&wires);
+        assert_eq!(nodes.len(), wires.len());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::intrinsics::*;
+use crate::schema::ctx;
+use crate::unit;
+use std::collections::HashMap;
+
+/// The result of an operating point analysis. Every node voltage and every current flowing
+/// through a voltage source (or an ampermeter, which is a voltage source of 0V). The keys are
+/// always in lowercase because ngspice does not keep the case of the names.
+pub struct OperatingPoint {
+    voltages: HashMap<String, f64>,
+    currents: HashMap<String, f64>,
+}
+
+impl OperatingPoint {
+    /// Parse the output of ngspice for a `.op` analysis. Only the tables that start with a
+    /// `Node Voltage` or a `Source Current` header are read. The node voltages are printed as
+    /// `name value` (or `V(name) value` for older versions) and the currents as
+    /// `name#branch value`. A table ends at the first line that is not a value.
+    pub fn parse(output: &str) -> Option<Self> {
+        let mut voltages = HashMap::new();
+        let mut currents = HashMap::new();
+        let mut in_table = false;
+        output.lines().for_each(|line| {
+            let cols = line.split_whitespace().collect::<Vec<&str>>();
+            if cols.is_empty() || cols.iter().all(|col| col.chars().all(|c| c == '-')) {
+                return;
+            }
+            let header = cols
+                .iter()
+                .map(|col| col.to_lowercase())
+                .collect::<Vec<String>>();
+            if header == ["node", "voltage"] || header == ["source", "current"] {
+                in_table = true;
+                return;
+            }
+            let value = match cols.get(1).map(|value| value.parse::<f64>()) {
+                Some(Ok(value)) if in_table && cols.len() == 2 => value,
+                _ => {
+                    in_table = false;
+                    return;
+                }
+            };
+            let name = cols[0].to_lowercase();
+            if let Some(branch) = name.strip_suffix("#branch") {
+                currents.insert(branch.to_string(), value);
+            } else {
+                let node = match (name.strip_prefix("v("), name.ends_with(')')) {
+                    (Some(node), true) => node[..node.len() - 1].to_string(),
+                    _ => name,
+                };
+                voltages.insert(node, value);
+            }
+        });
+        if voltages.is_empty() && currents.is_empty() {
+            None
+        } else {
+            Some(Self { voltages, currents })
+        }
+    }
+
+    pub fn voltage(&self, node: &str) -> Option<f64> {
+        if node == "0" {
+            return Some(0.0);
+        }
+        self.voltages.get(&node.to_lowercase()).copied()
+    }
+
+    pub fn current(&self, branch: &str) -> Option<f64> {
+        self.currents.get(&branch.to_lowercase()).copied()
+    }
+}
+
+/// Where the results of an operating point analysis are drawn on the schema. The positions are
+/// computed when the netlist is generated, the values are added once ngspice responds.
+pub struct Annotations {
+    pub nodes: Vec<(String, Point)>,
+    pub branches: Vec<(String, Point)>,
+    pub values: Option<OperatingPoint>,
+}
+
+impl Annotations {
+    pub fn new(nodes: Vec<(String, Point)>, branches: Vec<(String, Point)>) -> Self {
+        Self {
+            nodes,
+            branches,
+            values: None,
+        }
+    }
+
+    pub fn color() -> &'static str {
+        "#ffc048"
+    }
+
+    pub fn draw(&self, ctx: &ctx::Ctx) {
+        if let Some(values) = &self.values {
+            ctx.set_fill_style(Self::color());
+            self.nodes.iter().for_each(|(node, position)| {
+                if let Some(voltage) = values.voltage(node) {
+                    let text = format!("{}: {}", node, unit::format(voltage, &unit::Unit::Volt));
+                    ctx.fill_text_const(&text, *position + Point::new(2.0, -3.0), 11.0);
+                }
+            });
+            self.branches.iter().for_each(|(branch, position)| {
+                if let Some(current) = values.current(branch) {
+                    let text = unit::format(current, &unit::Unit::Ampere);
+                    ctx.fill_text_const(&text, *position, 11.0);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+Circuit: A Circuit
+
+Doing analysis at TEMP = 27.000000 and TNOM = 27.000000
+
+No. of Data Rows : 1
+Reference value :  0.00000e+00
+\tNode                                  Voltage
+\t----                                  -------
+\t----\t-------
+\ta                                1.000000e+01
+\tV(B)                             5.000000e+00
+
+\tSource\tCurrent
+\t------\t-------
+
+\tv1#branch                        -5.00000e-03
+
+Total analysis time 0.001
+steps 12
+";
+
+    #[test]
+    fn node_voltages() {
+        let op = OperatingPoint::parse(OUTPUT).unwrap();
+        assert_eq!(op.voltage("a"), Some(10.0));
+        assert_eq!(op.voltage("b"), Some(5.0));
+        assert_eq!(op.voltage("0"), Some(0.0));
+    }
+
+    #[test]
+    fn branch_currents() {
+        let op = OperatingPoint::parse(OUTPUT).unwrap();
+        assert_eq!(op.current("V1"), Some(-5e-3));
+        assert_eq!(op.voltage("v1#branch"), None);
+    }
+
+    #[test]
+    fn lines_outside_of_the_tables_are_ignored() {
+        let op = OperatingPoint::parse(OUTPUT).unwrap();
+        assert_eq!(op.voltages.len(), 2);
+        assert_eq!(op.currents.len(), 1);
+        assert_eq!(op.voltage("steps"), None);
+        assert!(OperatingPoint::parse("time 0.001\nsteps 12\n").is_none());
+    }
+}
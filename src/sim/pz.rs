@@ -0,0 +1,115 @@
+use crate::intrinsics::*;
+use crate::plot;
+
+/// The result of a pole-zero analysis (`.pz`). The poles and the zeros are complex numbers in
+/// rad/s.
+pub struct PoleZero {
+    poles: Vec<Complex>,
+    zeros: Vec<Complex>,
+}
+
+impl PoleZero {
+    /// Parse the output of ngspice for a `.pz` analysis. Every pole and every zero is printed as
+    /// `pole(1) = re, im` or `zero(1) = re, im`.
+    pub fn parse(output: &str) -> Option<Self> {
+        let mut poles = Vec::new();
+        let mut zeros = Vec::new();
+        output.lines().for_each(|line| {
+            let cols = line.split('=').map(|col| col.trim()).collect::<Vec<&str>>();
+            if cols.len() != 2 {
+                return;
+            }
+            let values = cols[1]
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<f64>())
+                .collect::<Result<Vec<f64>, std::num::ParseFloatError>>();
+            if let Ok(values) = values {
+                if values.len() != 2 {
+                    return;
+                }
+                let value = Complex::new(values[0], values[1]);
+                let name = cols[0].to_lowercase();
+                if name.starts_with("pole") {
+                    poles.push(value);
+                } else if name.starts_with("zero") {
+                    zeros.push(value);
+                }
+            }
+        });
+        if poles.is_empty() && zeros.is_empty() {
+            None
+        } else {
+            Some(Self { poles, zeros })
+        }
+    }
+
+    /// The poles and the zeros to draw on the complex plane (x_label, y_labels, series). The poles
+    /// always come before the zeros.
+    pub fn series(&self) -> plot::Data {
+        let points = |values: &[Complex]| {
+            values
+                .iter()
+                .map(|value| Point::new(value.re, value.im))
+                .collect::<Vec<Point>>()
+        };
+        (
+            String::from("Re (rad/s)"),
+            vec![String::from("Pôles"), String::from("Zéros")],
+            vec![points(&self.poles), points(&self.zeros)],
+        )
+    }
+
+    pub fn table(&self) -> plot::Table {
+        let mut rows = Vec::new();
+        for (name, values) in [("Pôle", &self.poles), ("Zéro", &self.zeros)] {
+            values.iter().enumerate().for_each(|(idx, value)| {
+                rows.push(vec![
+                    format!("{} {}", name, idx + 1),
+                    format!("{:.4e}", value.re),
+                    format!("{:.4e}", value.im),
+                ]);
+            });
+        }
+        plot::Table {
+            title: String::from("Pôles et zéros"),
+            headers: vec![String::new(), String::from("Re"), String::from("Im")],
+            rows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_poles_and_zeros() {
+        let output = "\
+Circuit: A Circuit
+
+Doing analysis at TEMP = 27.000000 and TNOM = 27.000000
+
+pole(1) = -1.000000e+03, 0.000000e+00
+pole(2) = -5.000000e+02, 2.000000e+03
+pole(3) = -5.000000e+02, -2.000000e+03
+zero(1) = 0.000000e+00, 0.000000e+00
+";
+        let pz = PoleZero::parse(output).unwrap();
+        assert_eq!(pz.poles.len(), 3);
+        assert_eq!(pz.zeros.len(), 1);
+        assert_eq!((pz.poles[1].re, pz.poles[1].im), (-500.0, 2e3));
+        assert_eq!((pz.poles[2].re, pz.poles[2].im), (-500.0, -2e3));
+        assert_eq!((pz.zeros[0].re, pz.zeros[0].im), (0.0, 0.0));
+    }
+
+    #[test]
+    fn no_pole_and_no_zero_is_none() {
+        let output = "\
+Circuit: A Circuit
+
+Doing analysis at TEMP = 27.000000 and TNOM = 27.000000
+";
+        assert!(PoleZero::parse(output).is_none());
+    }
+}
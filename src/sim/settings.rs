@@ -0,0 +1,442 @@
+use crate::schema::properties;
+use crate::sim::native::transient::Method;
+use crate::sim::{fourier, montecarlo, temperature, DcSweep, Engine, DEFAULT_SERVER};
+use crate::{error, unit};
+use std::collections::BTreeMap;
+use std::{fmt, str};
+
+/// The values of the inputs of the simulation menu, named like their input (ie: `tran__step`).
+/// It is the form in which the settings are saved with the project and bound to the menu
+/// (`view::read_settings` and `view::write_settings`), the settings of a simulation are parsed
+/// from it once (see `Settings::parse`).
+#[derive(Clone, Default)]
+pub struct Values {
+    values: BTreeMap<String, properties::Value>,
+}
+
+impl Values {
+    pub fn new(values: BTreeMap<String, properties::Value>) -> Self {
+        Self { values }
+    }
+
+    pub fn get(&self, key: &str) -> Result<&properties::Value, error::Error> {
+        match self.values.get(key) {
+            Some(value) => Ok(value),
+            None => Err(Box::new(error::Sim::SettingNotFound(key.to_string()))),
+        }
+    }
+
+    /// The text of a value (ie: the selected option of a select).
+    pub fn text(&self, key: &str) -> Result<String, error::Error> {
+        Ok(self.get(key)?.value_to_string())
+    }
+
+    pub fn set(&mut self, key: &str, value: properties::Value) {
+        self.values.insert(key.to_string(), value);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &properties::Value)> {
+        self.values.iter()
+    }
+
+    /// Write the settings of an analysis, its type included.
+    pub fn set_analysis(&mut self, analysis: &Analysis) {
+        self.set("sim__type", properties::Value::from(analysis.key()));
+        match analysis {
+            Analysis::Transient(transient) => {
+                self.set("tran__step", transient.step.clone());
+                self.set("tran__stop", transient.stop.clone());
+                let method = match transient.method {
+                    Method::Euler => "euler",
+                    Method::Trapezoidal => "trap",
+                };
+                self.set("tran__method", properties::Value::from(method));
+                match &transient.fourier {
+                    Some(fourier) => {
+                        self.set(
+                            "sim__four-probe",
+                            properties::Value::from(&fourier.probe[..]),
+                        );
+                        self.set(
+                            "sim__four-fundamental",
+                            properties::Value::Unit(
+                                fourier.fundamental,
+                                unit::Unit::Hertz,
+                                unit::Prefix::None,
+                                None,
+                            ),
+                        );
+                    }
+                    None => self.set("sim__four-probe", properties::Value::from("")),
+                }
+            }
+            Analysis::Frequency(frequency) => self.set_frequency("freq", frequency),
+            Analysis::OperatingPoint => {}
+            Analysis::Dc(sweeps) => {
+                for (idx, suffix) in ["", "2"].iter().enumerate() {
+                    let key = |name: &str| format!("sim__dc-{}{}", name, suffix);
+                    match sweeps.get(idx) {
+                        Some(sweep) => {
+                            self.set(&key("source"), properties::Value::from(&sweep.source[..]));
+                            self.set(&key("start"), sweep.start.clone());
+                            self.set(&key("stop"), sweep.stop.clone());
+                            self.set(&key("step"), sweep.step.clone());
+                        }
+                        None => self.set(&key("source"), properties::Value::from("")),
+                    }
+                }
+            }
+            Analysis::Noise(noise) => {
+                self.set(
+                    "sim__noise-output",
+                    properties::Value::from(&noise.output[..]),
+                );
+                self.set(
+                    "sim__noise-source",
+                    properties::Value::from(&noise.source[..]),
+                );
+                self.set_frequency("noise", &noise.frequency);
+            }
+            Analysis::TransferFunction(transfer) => {
+                self.set(
+                    "sim__tf-output",
+                    properties::Value::from(&transfer.output[..]),
+                );
+                self.set(
+                    "sim__tf-source",
+                    properties::Value::from(&transfer.source[..]),
+                );
+            }
+            Analysis::PoleZero(transfer) => {
+                self.set(
+                    "sim__pz-output",
+                    properties::Value::from(&transfer.output[..]),
+                );
+                self.set(
+                    "sim__pz-source",
+                    properties::Value::from(&transfer.source[..]),
+                );
+            }
+        }
+    }
+
+    /// Write a range of frequencies, the names of the values start with `sim__{prefix}-`.
+    fn set_frequency(&mut self, prefix: &str, frequency: &Frequency) {
+        let key = |name: &str| format!("sim__{}-{}", prefix, name);
+        self.set(
+            &key("variation-type"),
+            properties::Value::from(&frequency.variation[..]),
+        );
+        self.set(&key("np"), properties::Value::from(&frequency.points[..]));
+        self.set(&key("fstart"), frequency.start.clone());
+        self.set(&key("fstop"), frequency.stop.clone());
+    }
+
+    /// Write the temperatures of the simulation, a single temperature is not a sweep.
+    pub fn set_temperature(&mut self, temperature: &temperature::Settings) {
+        match &temperature.temperatures[..] {
+            [temperature] => {
+                self.set("sim__temp", properties::Value::from(*temperature));
+                self.set("sim__temp-sweep", properties::Value::from(""));
+            }
+            temperatures => {
+                let sweep = temperatures
+                    .iter()
+                    .map(|temperature| temperature.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",");
+                self.set("sim__temp-sweep", properties::Value::String(sweep));
+            }
+        }
+    }
+}
+
+impl str::FromStr for Values {
+    type Err = error::Error;
+
+    /// This function is used when importing a file. The format is the same as the properties of
+    /// a part without the visibility and the order: `tran__step<unit[1|s|m]>!sim__type<string[tran]>!`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut values = BTreeMap::new();
+        for setting in s.split('!').filter(|setting| !setting.is_empty()) {
+            match (setting.find('<'), setting.rfind('>')) {
+                (Some(start), Some(end)) if start < end => {
+                    values.insert(
+                        setting[..start].to_string(),
+                        setting[start + 1..end].parse::<properties::Value>()?,
+                    );
+                }
+                _ => return Err(Box::new(error::Import::MissingToken)),
+            }
+        }
+        Ok(Self::new(values))
+    }
+}
+
+impl fmt::Debug for Values {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.values
+            .iter()
+            .try_for_each(|(key, value)| write!(f, "{}<{:?}>!", key, value))
+    }
+}
+
+/// The settings of a transient analysis. A fourier analysis of a probe can follow it.
+#[derive(Clone)]
+pub struct Transient {
+    pub step: properties::Value,
+    pub stop: properties::Value,
+    /// The integration method, the backward Euler method is the first order of the gear method.
+    /// The trapezoidal method is the default of ngspice.
+    pub method: Method,
+    pub fourier: Option<fourier::Settings>,
+}
+
+/// A range of frequencies: how the points are distributed (`dec`, `oct` or `lin`), the number of
+/// points (per decade or per octave) and the bounds.
+#[derive(Clone)]
+pub struct Frequency {
+    pub variation: String,
+    pub points: String,
+    pub start: properties::Value,
+    pub stop: properties::Value,
+}
+
+/// The noise at an output (a voltmeter) referred to an input source.
+#[derive(Clone)]
+pub struct Noise {
+    pub output: String,
+    pub source: String,
+    pub frequency: Frequency,
+}
+
+/// The transfer between an input source and an output (a probe or a voltmeter).
+#[derive(Clone)]
+pub struct Transfer {
+    pub output: String,
+    pub source: String,
+}
+
+/// The analysis run by a simulation with its settings. The sources and the probes are selected by
+/// name, they are only checked against the circuit when the netlist is built (see
+/// `sim::Analysis`).
+#[derive(Clone)]
+pub enum Analysis {
+    Transient(Transient),
+    Frequency(Frequency),
+    OperatingPoint,
+    /// The swept sources, the second one is optional.
+    Dc(Vec<DcSweep>),
+    Noise(Noise),
+    TransferFunction(Transfer),
+    PoleZero(Transfer),
+}
+
+impl Analysis {
+    /// The key of the type of analysis (`sim__type`).
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Transient(_) => "tran",
+            Self::Frequency(_) => "freq",
+            Self::OperatingPoint => "op",
+            Self::Dc(_) => "dc",
+            Self::Noise(_) => "noise",
+            Self::TransferFunction(_) => "tf",
+            Self::PoleZero(_) => "pz",
+        }
+    }
+
+    fn parse(values: &Values) -> Result<Self, error::Error> {
+        let frequency = |prefix: &str| -> Result<Frequency, error::Error> {
+            let key = |name: &str| format!("sim__{}-{}", prefix, name);
+            Ok(Frequency {
+                variation: values.text(&key("variation-type"))?,
+                points: values.text(&key("np"))?,
+                start: values.get(&key("fstart"))?.clone(),
+                stop: values.get(&key("fstop"))?.clone(),
+            })
+        };
+        let transfer = |prefix: &str| -> Result<Transfer, error::Error> {
+            Ok(Transfer {
+                output: values.text(&format!("sim__{}-output", prefix))?,
+                source: values.text(&format!("sim__{}-source", prefix))?,
+            })
+        };
+        match &values.text("sim__type")?[..] {
+            "tran" => {
+                let fourier = fourier::Settings::parse(
+                    values.text("sim__four-probe")?,
+                    values.get("sim__four-fundamental")?.as_f64(),
+                )?;
+                let method = match &values.text("tran__method")?[..] {
+                    "euler" => Method::Euler,
+                    _ => Method::Trapezoidal,
+                };
+                Ok(Self::Transient(Transient {
+                    step: values.get("tran__step")?.clone(),
+                    stop: values.get("tran__stop")?.clone(),
+                    method,
+                    fourier,
+                }))
+            }
+            "freq" => Ok(Self::Frequency(frequency("freq")?)),
+            "op" => Ok(Self::OperatingPoint),
+            "dc" => {
+                let mut sweeps = Vec::new();
+                for suffix in ["", "2"] {
+                    let key = |name: &str| format!("sim__dc-{}{}", name, suffix);
+                    let source = values.text(&key("source"))?;
+                    // A source that is not selected is not swept.
+                    if source.is_empty() {
+                        continue;
+                    }
+                    sweeps.push(DcSweep {
+                        source,
+                        start: values.get(&key("start"))?.clone(),
+                        stop: values.get(&key("stop"))?.clone(),
+                        step: values.get(&key("step"))?.clone(),
+                    });
+                }
+                Ok(Self::Dc(sweeps))
+            }
+            "noise" => {
+                let transfer = transfer("noise")?;
+                Ok(Self::Noise(Noise {
+                    output: transfer.output,
+                    source: transfer.source,
+                    frequency: frequency("noise")?,
+                }))
+            }
+            "tf" => Ok(Self::TransferFunction(transfer("tf")?)),
+            "pz" => Ok(Self::PoleZero(transfer("pz")?)),
+            _ => Err(Box::new(error::Sim::UnavailableAnalysis)),
+        }
+    }
+}
+
+/// The settings of a simulation: the analysis, the temperatures, the Monte Carlo analysis, the
+/// simulator and the timeout of the runs. They do not depend on the DOM, a netlist can be built
+/// from them without a browser (see `circuit::Circuit::to_string`).
+#[derive(Clone)]
+pub struct Settings {
+    pub analysis: Analysis,
+    pub temperature: temperature::Settings,
+    pub montecarlo: Option<montecarlo::Settings>,
+    pub engine: Engine,
+    /// The longest time a run can take in seconds, there is no limit when it is `None`.
+    pub timeout: Option<f64>,
+}
+
+impl Settings {
+    /// Parse the values of the simulation menu. An empty timeout means that there is no limit.
+    pub fn parse(values: &Values) -> Result<Self, error::Error> {
+        let engine = values.text("sim__engine")?;
+        let server = values.text("sim__server")?;
+        let server = |scheme: &str| match server.trim() {
+            "" => format!("{}://{}", scheme, DEFAULT_SERVER),
+            server => server.to_string(),
+        };
+        let engine = match &engine[..] {
+            "native" => Engine::Native,
+            "http" => Engine::Http(server("http")),
+            "websocket" => Engine::WebSocket(server("ws")),
+            _ => Engine::Ngspice,
+        };
+        let timeout = values
+            .text("sim__timeout")?
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|timeout| timeout.is_finite() && *timeout > 0.0);
+        Ok(Self {
+            analysis: Analysis::parse(values)?,
+            temperature: temperature::Settings::parse(
+                &values.text("sim__temp")?,
+                &values.text("sim__temp-sweep")?,
+            )?,
+            montecarlo: montecarlo::Settings::parse(
+                &values.text("sim__mc-runs")?,
+                values.text("sim__mc-probe")?,
+                &values.text("sim__mc-measure")?,
+            )?,
+            engine,
+            timeout,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The values saved by a project with a transient analysis.
+    const PROJECT: &str = "sim__type<string[tran]>!tran__step<f64[0.001]>!tran__stop<f64[1]>!\
+        tran__method<string[euler]>!sim__four-probe<string[]>!sim__four-fundamental<f64[0]>!\
+        sim__temp<string[]>!sim__temp-sweep<string[]>!sim__mc-runs<string[0]>!\
+        sim__mc-probe<string[]>!sim__mc-measure<string[]>!sim__engine<string[ngspice]>!\
+        sim__server<string[]>!sim__timeout<string[60]>!";
+
+    #[test]
+    fn transient_project() {
+        let settings = Settings::parse(&PROJECT.parse::<Values>().unwrap()).unwrap();
+        match settings.analysis {
+            Analysis::Transient(transient) => {
+                assert!(transient.method == Method::Euler);
+                assert!(transient.fourier.is_none());
+            }
+            _ => panic!("not a transient analysis"),
+        }
+        assert_eq!(
+            settings.temperature.temperatures,
+            vec![temperature::NOMINAL]
+        );
+        assert!(settings.montecarlo.is_none());
+        assert!(settings.engine == Engine::Ngspice);
+        assert_eq!(settings.timeout, Some(60.0));
+    }
+
+    #[test]
+    fn analysis_round_trip() {
+        let sweep = DcSweep {
+            source: String::from("VV1"),
+            start: properties::Value::from(0.0),
+            stop: properties::Value::from(5.0),
+            step: properties::Value::from(0.5),
+        };
+        let mut values = PROJECT.parse::<Values>().unwrap();
+        values.set_analysis(&Analysis::Dc(vec![sweep]));
+        values.set_temperature(&temperature::Settings {
+            temperatures: vec![-40.0, 85.0],
+        });
+        values.set("sim__timeout", properties::Value::from(""));
+        let settings = Settings::parse(&values).unwrap();
+        match settings.analysis {
+            Analysis::Dc(sweeps) => {
+                assert_eq!(sweeps.len(), 1);
+                assert_eq!(sweeps[0].to_string(), "VV1 0 5 0.5");
+            }
+            _ => panic!("not a DC analysis"),
+        }
+        assert_eq!(settings.temperature.temperatures, vec![-40.0, 85.0]);
+        assert_eq!(settings.timeout, None);
+    }
+
+    #[test]
+    fn unknown_analysis() {
+        let mut values = PROJECT.parse::<Values>().unwrap();
+        values.set("sim__type", properties::Value::from("hb"));
+        assert!(Settings::parse(&values).is_err());
+    }
+
+    #[test]
+    fn missing_setting() {
+        let mut values = Values::default();
+        PROJECT
+            .parse::<Values>()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| *key != "sim__timeout")
+            .for_each(|(key, value)| values.set(key, value.clone()));
+        assert!(Settings::parse(&values).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use crate::schema::properties;
+use crate::{error, sim};
+
+/// The default temperature of ngspice (in °C).
+pub const NOMINAL: f64 = 27.0;
+
+/// The temperatures (in °C) at which the circuit is simulated. There is one run per temperature
+/// when the temperature is swept.
+#[derive(Clone)]
+pub struct Settings {
+    pub temperatures: Vec<f64>,
+}
+
+impl Settings {
+    /// Parse the temperature of the simulation. The sweep (`-40,27,85` or `start:stop:step`)
+    /// replaces the temperature if it is not empty.
+    pub fn parse(temperature: &str, sweep: &str) -> Result<Self, error::Error> {
+        let temperatures = match (temperature.trim(), sweep.trim()) {
+            (_, sweep) if !sweep.is_empty() => match sweep.parse::<properties::Sweep>() {
+                // The temperatures are counted before they are generated.
+                Ok(sweep) if sweep.count() > sim::MAX_RUNS => {
+                    return Err(Box::new(error::Sim::TooManyRuns(
+                        sweep.count(),
+                        sim::MAX_RUNS,
+                    )));
+                }
+                Ok(sweep) => sweep.values(),
+                Err(_) => vec![sweep
+                    .parse::<f64>()
+                    .map_err(|_| error::Sim::InvalidTemperature(sweep.to_string()))?],
+            },
+            ("", _) => vec![NOMINAL],
+            (temperature, _) => vec![temperature
+                .parse::<f64>()
+                .map_err(|_| error::Sim::InvalidTemperature(temperature.to_string()))?],
+        };
+        if temperatures.is_empty() {
+            return Err(Box::new(error::Sim::InvalidTemperature(sweep.to_string())));
+        }
+        Ok(Self { temperatures })
+    }
+
+    pub fn is_swept(&self) -> bool {
+        self.temperatures.len() > 1
+    }
+}
+
+/// The line of the netlist that sets the temperature of the circuit.
+pub fn to_spice(temperature: f64) -> String {
+    format!(".temp {}\n", temperature)
+}
+
+/// The label of the run at a temperature.
+pub fn label(temperature: f64) -> String {
+    format!("T={} °C", temperature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temperatures(temperature: &str, sweep: &str) -> Vec<f64> {
+        Settings::parse(temperature, sweep).unwrap().temperatures
+    }
+
+    #[test]
+    fn sweeps() {
+        assert_eq!(temperatures("", ""), vec![NOMINAL]);
+        assert_eq!(temperatures("50", ""), vec![50.0]);
+        // The sweep replaces the temperature.
+        assert_eq!(temperatures("50", "-40,27,85"), vec![-40.0, 27.0, 85.0]);
+        assert_eq!(
+            temperatures("", "-40:85:25"),
+            vec![-40.0, -15.0, 10.0, 35.0, 60.0, 85.0]
+        );
+        assert_eq!(temperatures("", "85"), vec![85.0]);
+        assert!(!Settings::parse("50", "").unwrap().is_swept());
+        assert!(Settings::parse("", "0,85").unwrap().is_swept());
+        assert!(Settings::parse("hot", "").is_err());
+        assert!(Settings::parse("", "-40:85").is_err());
+    }
+
+    #[test]
+    fn sweep_limit() {
+        let error = Settings::parse("", "-40:85:1e-9").err().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<error::Sim>(),
+            Some(error::Sim::TooManyRuns(_, sim::MAX_RUNS))
+        ));
+        assert_eq!(temperatures("", "0:999:1").len(), sim::MAX_RUNS);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{plot, unit};
+
+/// The result of a small-signal transfer function analysis (`.tf`). The gain is the ratio
+/// between the output and the input source at DC.
+pub struct TransferFunction {
+    gain: f64,
+    input_resistance: f64,
+    output_resistance: f64,
+}
+
+impl TransferFunction {
+    /// Parse the output of ngspice for a `.tf` analysis. Every value is printed as
+    /// `name = value`, the name of the impedances contains the name of the source or the output
+    /// (ie: `vin#input_impedance` or `output_impedance_at_v(a,b)`).
+    pub fn parse(output: &str) -> Option<Self> {
+        let mut gain = None;
+        let mut input_resistance = None;
+        let mut output_resistance = None;
+        output.lines().for_each(|line| {
+            let cols = line.split('=').map(|col| col.trim()).collect::<Vec<&str>>();
+            if cols.len() != 2 {
+                return;
+            }
+            if let Ok(value) = cols[1].parse::<f64>() {
+                let name = cols[0].to_lowercase();
+                if name.contains("transfer_function") {
+                    gain = Some(value);
+                } else if name.contains("input_impedance") {
+                    input_resistance = Some(value);
+                } else if name.contains("output_impedance") {
+                    output_resistance = Some(value);
+                }
+            }
+        });
+        Some(Self {
+            gain: gain?,
+            input_resistance: input_resistance?,
+            output_resistance: output_resistance?,
+        })
+    }
+
+    pub fn table(&self) -> plot::Table {
+        let resistance = |value: f64| unit::format(value, &unit::Unit::Ohm);
+        plot::Table {
+            title: String::from("Fonction de transfert"),
+            headers: vec![String::new(), String::from("Valeur")],
+            rows: vec![
+                vec![String::from("Gain DC"), format!("{:.4e}", self.gain)],
+                vec![
+                    String::from("Résistance d'entrée"),
+                    resistance(self.input_resistance),
+                ],
+                vec![
+                    String::from("Résistance de sortie"),
+                    resistance(self.output_resistance),
+                ],
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_transfer_function() {
+        let output = "\
+Circuit: A Circuit
+
+Doing analysis at TEMP = 27.000000 and TNOM = 27.000000
+
+transfer_function = 5.000000e-01
+output_impedance_at_v(out) = 5.000000e+02
+vin#input_impedance = 2.000000e+03
+";
+        let tf = TransferFunction::parse(output).unwrap();
+        assert_eq!(tf.gain, 0.5);
+        assert_eq!(tf.input_resistance, 2e3);
+        assert_eq!(tf.output_resistance, 500.0);
+    }
+
+    #[test]
+    fn missing_impedance_is_none() {
+        let output = "\
+transfer_function = 5.000000e-01
+vin#input_impedance = 2.000000e+03
+";
+        assert!(TransferFunction::parse(output).is_none());
+    }
+}
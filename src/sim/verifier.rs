@@ -1,32 +1,187 @@
 use crate::error;
-use crate::schema::{parts, wires};
+use crate::schema::parts;
+use std::collections::{BTreeSet, HashMap};
 
 pub struct Verifier<'entity> {
-    pub errors: Vec<error::Error>,
+    pub diagnostics: Vec<error::Diagnostic>,
     parts: &'entity Vec<parts::Part>,
-    _wires: &'entity Vec<wires::Wire>,
-    names: Vec<String>,
+    /// The name of each node and its wires.
+    nodes: &'entity [(String, Vec<usize>)],
+    /// The name of each part checked so far with its index.
+    names: HashMap<String, usize>,
 }
 
 impl<'entity> Verifier<'entity> {
-    pub fn check(parts: &'entity Vec<parts::Part>, wires: &'entity Vec<wires::Wire>) -> Self {
+    /// Check the parts and the graph of the nodes. The parts must already be connected to their
+    /// nodes.
+    pub fn check(parts: &'entity Vec<parts::Part>, nodes: &'entity [(String, Vec<usize>)]) -> Self {
         let mut verifier = Self {
-            errors: Vec::new(),
+            diagnostics: Vec::new(),
             parts,
-            _wires: wires,
-            names: Vec::new(),
+            nodes,
+            names: HashMap::new(),
         };
         verifier.check_parts();
+        verifier.check_node_labels();
+        if !verifier.diagnostics.iter().any(error::Diagnostic::is_error) {
+            verifier.check_nodes();
+        }
         verifier
     }
 
+    /// The checks on the graph of the nodes. Each of them finds a circuit that makes ngspice fail
+    /// (usually with a singular matrix).
+    fn check_nodes(&mut self) {
+        let graph = Graph::new(self.parts);
+        self.check_islands(&graph);
+        self.check_dc_paths(&graph);
+        self.check_voltage_loops(&graph);
+    }
+
+    /// Every island (a group of nodes connected by parts) must have its own ground.
+    fn check_islands(&mut self, graph: &Graph) {
+        let mut visited = BTreeSet::new();
+        for node in graph.nodes.iter() {
+            if visited.contains(node) {
+                continue;
+            }
+            let island = graph.reachable(node, |_| true);
+            if !island.contains("0") {
+                let nodes = island.iter().cloned().collect::<Vec<String>>();
+                self.diagnostics.push(error::Diagnostic::error(
+                    error::Sim::IslandWithoutGround(nodes.join(", ")),
+                    nodes.into_iter().map(error::Entity::Node).collect(),
+                ));
+            }
+            visited.extend(island);
+        }
+    }
+
+    /// Every node of an island with a ground must have a DC path to the ground, capacitors and
+    /// current sources do not conduct at DC. A group of nodes without a DC path is a cutset: it
+    /// is only fed by capacitors and current sources. If a current source feeds it, the current
+    /// has nowhere to go and the current sources are reported, otherwise each node is reported.
+    fn check_dc_paths(&mut self, graph: &Graph) {
+        let grounded = graph.reachable("0", Edge::conducts_dc);
+        let mut floating = graph
+            .reachable("0", |_| true)
+            .difference(&grounded)
+            .cloned()
+            .collect::<BTreeSet<String>>();
+        while let Some(node) = floating.iter().next().cloned() {
+            let cutset = graph.reachable(&node, Edge::conducts_dc);
+            floating = floating.difference(&cutset).cloned().collect();
+            let feeding = graph
+                .edges
+                .iter()
+                .filter(|edge| {
+                    edge.nodes.iter().any(|node| cutset.contains(node))
+                        && edge.nodes.iter().any(|node| !cutset.contains(node))
+                })
+                .collect::<Vec<&Edge>>();
+            if feeding.iter().any(|edge| edge.kind == Kind::CurrentSource) {
+                let parts = feeding
+                    .iter()
+                    .map(|edge| edge.name.clone())
+                    .collect::<Vec<String>>();
+                let nodes = cutset.iter().cloned().collect::<Vec<String>>();
+                let entities = feeding
+                    .iter()
+                    .map(|edge| error::Entity::Part(edge.part))
+                    .chain(nodes.iter().cloned().map(error::Entity::Node))
+                    .collect();
+                self.diagnostics.push(error::Diagnostic::error(
+                    error::Sim::CurrentSourceCutset(parts.join(", "), nodes.join(", ")),
+                    entities,
+                ));
+            } else {
+                for node in cutset.into_iter() {
+                    self.diagnostics.push(error::Diagnostic::error(
+                        error::Sim::NoDcPathToGround(node.clone()),
+                        vec![error::Entity::Node(node)],
+                    ));
+                }
+            }
+        }
+    }
+
+    /// A loop made only of voltage sources and inductors (inductors are shorts at DC) forces the
+    /// sum of the voltages of the sources to be 0. The loops are found by building a forest of
+    /// those parts, a part that connects 2 nodes already in the same tree closes a loop.
+    fn check_voltage_loops(&mut self, graph: &Graph) {
+        let mut forest: HashMap<&str, Vec<(&str, usize)>> = HashMap::new();
+        for (idx, edge) in graph.edges.iter().enumerate() {
+            if !matches!(edge.kind, Kind::VoltageSource | Kind::Inductor) || edge.nodes.len() != 2 {
+                continue;
+            }
+            let (a, b) = (&edge.nodes[0][..], &edge.nodes[1][..]);
+            match Graph::path(&forest, a, b) {
+                Some(path) => {
+                    let edges = path
+                        .into_iter()
+                        .chain(std::iter::once(idx))
+                        .map(|idx| &graph.edges[idx])
+                        .collect::<Vec<&Edge>>();
+                    let parts = edges
+                        .iter()
+                        .map(|edge| edge.name.clone())
+                        .collect::<Vec<String>>();
+                    self.diagnostics.push(error::Diagnostic::error(
+                        error::Sim::VoltageSourceLoop(parts.join(", ")),
+                        edges
+                            .iter()
+                            .map(|edge| error::Entity::Part(edge.part))
+                            .collect(),
+                    ));
+                }
+                None => {
+                    forest.entry(a).or_default().push((b, idx));
+                    forest.entry(b).or_default().push((a, idx));
+                }
+            }
+        }
+    }
+
+    /// Separate nodes with the same name are merged in the netlist. It is how net labels work but
+    /// it might not be what the user wanted, so a warning is shown. Grounds are always merged.
+    fn check_node_labels(&mut self) {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        self.nodes
+            .iter()
+            .for_each(|(name, _)| *counts.entry(&name[..]).or_default() += 1);
+        let mut merged = counts
+            .into_iter()
+            .filter(|(name, count)| *count > 1 && *name != "0")
+            .map(|(name, _)| name.to_string())
+            .collect::<Vec<String>>();
+        merged.sort();
+        for name in merged.into_iter() {
+            let labels = self
+                .parts
+                .iter()
+                .enumerate()
+                .filter(|(_, part)| part.typ == "lumped.node")
+                .filter(|(_, part)| {
+                    matches!(part.properties.get("name"), Ok(property) if property.value.to_string() == name)
+                })
+                .map(|(idx, _)| error::Entity::Part(idx));
+            let entities = labels
+                .chain(std::iter::once(error::Entity::Node(name.clone())))
+                .collect();
+            self.diagnostics.push(error::Diagnostic::warning(
+                error::Sim::MergedNodes(name),
+                entities,
+            ));
+        }
+    }
+
     fn check_parts(&mut self) {
         let mut is_ground_present = false;
         let mut is_node_or_probe_present = false;
-        for part in self.parts {
-            self.check_for_name_collision(part);
+        for (idx, part) in self.parts.iter().enumerate() {
+            self.check_for_name_collision(idx, part);
             if &part.typ == "lumped.ground" {
-                self.check_ground_name(part);
+                self.check_ground_name(idx, part);
                 is_ground_present = true;
             }
             if &part.typ == "lumped.node" || &part.typ[..5] == "probe" {
@@ -34,30 +189,308 @@ impl<'entity> Verifier<'entity> {
             }
         }
         if !is_ground_present {
-            self.errors.push(Box::new(error::Sim::NoGround));
+            self.diagnostics
+                .push(error::Diagnostic::error(error::Sim::NoGround, Vec::new()));
         }
         if !is_node_or_probe_present {
-            self.errors.push(Box::new(error::Sim::NoProbe));
+            self.diagnostics
+                .push(error::Diagnostic::error(error::Sim::NoProbe, Vec::new()));
         }
     }
 
-    fn check_for_name_collision(&mut self, part: &parts::Part) {
+    /// The parts of the netlist must have different names. The named nodes are not in the netlist,
+    /// nodes with the same name are merged (see `check_node_labels`).
+    fn check_for_name_collision(&mut self, idx: usize, part: &parts::Part) {
+        if part.typ == "lumped.node" {
+            return;
+        }
         if let Ok(property) = part.properties.get("name") {
             let name = property.value.to_string();
-            if self.names.contains(&name) && name != "0" {
-                self.errors
-                    .push(Box::new(error::Sim::MultipleSameName(name.clone())));
-            } else {
-                self.names.push(name);
+            match self.names.get(&name) {
+                Some(&first) if name != "0" => {
+                    self.diagnostics.push(error::Diagnostic::error(
+                        error::Sim::MultipleSameName(name.clone()),
+                        vec![error::Entity::Part(first), error::Entity::Part(idx)],
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    self.names.insert(name, idx);
+                }
             }
         }
     }
 
-    fn check_ground_name(&mut self, part: &parts::Part) {
+    fn check_ground_name(&mut self, idx: usize, part: &parts::Part) {
         if let Ok(property) = part.properties.get("name") {
             if property.value.to_string() != "0" {
-                self.errors.push(Box::new(error::Sim::GroundWithBadName));
+                self.diagnostics.push(error::Diagnostic::error(
+                    error::Sim::GroundWithBadName,
+                    vec![error::Entity::Part(idx)],
+                ));
+            }
+        }
+    }
+}
+
+/// What a part of the netlist is, as far as the checks on the graph are concerned.
+#[derive(PartialEq)]
+enum Kind {
+    VoltageSource,
+    CurrentSource,
+    Inductor,
+    Capacitor,
+    /// Every other part (resistors, diodes, transistors, blocks, ...).
+    Other,
+}
+
+impl Kind {
+    /// The kind of a part of the library (ie: `source.voltage_dc`). An ammeter is a voltage source
+    /// of 0 V in the netlist.
+    fn new(typ: &str) -> Self {
+        match typ {
+            "source.voltage_dc" | "source.voltage_ac" | "probe.ampermeter" => Self::VoltageSource,
+            "source.current_dc" | "source.current_ac" => Self::CurrentSource,
+            "lumped.inductor" => Self::Inductor,
+            "lumped.capacitor" => Self::Capacitor,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A part of the netlist and the nodes it connects together.
+struct Edge {
+    /// The index of the part.
+    part: usize,
+    name: String,
+    nodes: Vec<String>,
+    kind: Kind,
+}
+
+impl Edge {
+    fn conducts_dc(&self) -> bool {
+        !matches!(self.kind, Kind::Capacitor | Kind::CurrentSource)
+    }
+}
+
+/// The nodes of a circuit and the parts of the netlist that connect them.
+struct Graph {
+    nodes: BTreeSet<String>,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    fn new(parts: &[parts::Part]) -> Self {
+        let mut nodes = BTreeSet::new();
+        let mut edges = Vec::new();
+        for (idx, part) in parts.iter().enumerate() {
+            let connectors = part
+                .layout
+                .connectors
+                .iter()
+                .filter_map(|connector| connector.connected_to.clone())
+                .collect::<Vec<String>>();
+            nodes.extend(connectors.iter().cloned());
+            // Parts that are not in the netlist (grounds, named nodes and voltmeters) connect
+            // nothing.
+            if let Some(device) = part.device() {
+                let kind = Kind::new(&part.typ);
+                let name = part
+                    .properties
+                    .get("name")
+                    .map_or(device, |property| property.value.to_string());
+                edges.push(Edge {
+                    part: idx,
+                    name,
+                    nodes: connectors,
+                    kind,
+                });
+            }
+        }
+        Self { nodes, edges }
+    }
+
+    /// The nodes connected to `start` (`start` included) through the parts accepted by
+    /// `is_followed`.
+    fn reachable(&self, start: &str, is_followed: fn(&Edge) -> bool) -> BTreeSet<String> {
+        let mut neighbours: HashMap<&str, Vec<&str>> = HashMap::new();
+        self.edges
+            .iter()
+            .filter(|edge| is_followed(edge))
+            .for_each(|edge| {
+                edge.nodes.iter().for_each(|node| {
+                    neighbours
+                        .entry(&node[..])
+                        .or_default()
+                        .extend(edge.nodes.iter().map(|node| &node[..]));
+                });
+            });
+        let mut reachable = BTreeSet::new();
+        if !self.nodes.contains(start) {
+            return reachable;
+        }
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            if reachable.insert(node.to_string()) {
+                if let Some(next) = neighbours.get(node) {
+                    stack.extend(next.iter().filter(|node| !reachable.contains(**node)));
+                }
             }
         }
+        reachable
+    }
+
+    /// The edges of the path between 2 nodes of a forest (each node with its neighbours and the
+    /// edges to them). There is no path if the nodes are in different trees.
+    fn path(
+        forest: &HashMap<&str, Vec<(&str, usize)>>,
+        from: &str,
+        to: &str,
+    ) -> Option<Vec<usize>> {
+        let mut previous: HashMap<&str, (&str, usize)> = HashMap::new();
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            if node == to {
+                let mut path = Vec::new();
+                let mut node = to;
+                while let Some(&(parent, edge)) = previous.get(node) {
+                    path.push(edge);
+                    node = parent;
+                }
+                return Some(path);
+            }
+            for &(next, edge) in forest.get(node).into_iter().flatten() {
+                if next != from && !previous.contains_key(next) {
+                    previous.insert(next, (node, edge));
+                    stack.push(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::properties;
+    use crate::PARTS;
+
+    /// A part of the library with its name, connected to `nodes`.
+    fn part(typ: &str, name: &str, nodes: &[&str]) -> parts::Part {
+        let mut part = PARTS.get(typ).unwrap();
+        if let Some(property) = part.properties.properties.get_mut("name") {
+            property.value = properties::Value::from(name);
+        }
+        part.layout
+            .connectors
+            .iter_mut()
+            .zip(nodes.iter())
+            .for_each(|(connector, node)| connector.connected_to = Some(node.to_string()));
+        part
+    }
+
+    /// The diagnostics of the parts, each node they are connected to is a separate net.
+    fn diagnostics(parts: Vec<parts::Part>) -> Vec<error::Diagnostic> {
+        let mut nodes = parts
+            .iter()
+            .flat_map(|part| part.layout.connectors.iter())
+            .filter_map(|connector| connector.connected_to.clone())
+            .collect::<Vec<String>>();
+        nodes.sort();
+        nodes.dedup();
+        let nodes = nodes
+            .into_iter()
+            .map(|node| (node, Vec::new()))
+            .collect::<Vec<(String, Vec<usize>)>>();
+        Verifier::check(&parts, &nodes).diagnostics
+    }
+
+    #[test]
+    fn net_labels_are_merged() {
+        let parts = vec![
+            part("source.voltage_dc", "1", &["out", "0"]),
+            part("lumped.resistor", "2", &["out", "0"]),
+            part("lumped.node", "out", &["out"]),
+            part("lumped.node", "out", &["out"]),
+            part("lumped.ground", "0", &["0"]),
+        ];
+        // The labels are on 2 separate nets which have the same name.
+        let labelled = vec![
+            (String::from("0"), Vec::new()),
+            (String::from("out"), Vec::new()),
+            (String::from("out"), Vec::new()),
+        ];
+        let verifier = Verifier::check(&parts, &labelled);
+        assert!(!verifier.diagnostics.iter().any(error::Diagnostic::is_error));
+        assert_eq!(verifier.diagnostics.len(), 1);
+        assert!(matches!(
+            verifier.diagnostics[0].error.downcast_ref::<error::Sim>(),
+            Some(error::Sim::MergedNodes(name)) if name == "out"
+        ));
+        let parts = vec![
+            part("source.voltage_dc", "1", &["out", "0"]),
+            part("lumped.resistor", "2", &["out", "0"]),
+            part("lumped.resistor", "2", &["out", "0"]),
+            part("lumped.node", "out", &["out"]),
+            part("lumped.ground", "0", &["0"]),
+        ];
+        assert!(diagnostics(parts).iter().any(error::Diagnostic::is_error));
+    }
+
+    /// The errors of a circuit grounded at `0` with a label on the node `a`.
+    fn errors(mut parts: Vec<parts::Part>) -> Vec<String> {
+        parts.push(part("lumped.ground", "0", &["0"]));
+        parts.push(part("lumped.node", "a", &["a"]));
+        diagnostics(parts)
+            .into_iter()
+            .filter(error::Diagnostic::is_error)
+            .map(|diagnostic| format!("{:?}", diagnostic.error))
+            .collect()
+    }
+
+    #[test]
+    fn floating_island() {
+        let errors = errors(vec![
+            part("source.voltage_dc", "in", &["a", "0"]),
+            part("lumped.resistor", "1", &["b", "c"]),
+            part("lumped.resistor", "2", &["c", "b"]),
+        ]);
+        assert_eq!(errors, vec![r#"IslandWithoutGround("b, c")"#]);
+    }
+
+    #[test]
+    fn capacitor_only_node() {
+        let errors = errors(vec![
+            part("source.voltage_dc", "in", &["a", "0"]),
+            part("lumped.capacitor", "1", &["a", "b"]),
+            part("lumped.capacitor", "2", &["b", "0"]),
+        ]);
+        assert_eq!(errors, vec![r#"NoDcPathToGround("b")"#]);
+    }
+
+    #[test]
+    fn voltage_source_loop() {
+        let loop_errors = errors(vec![
+            part("source.voltage_dc", "in", &["a", "0"]),
+            part("lumped.resistor", "1", &["a", "b"]),
+            part("lumped.inductor", "2", &["a", "0"]),
+        ]);
+        assert_eq!(loop_errors, vec![r#"VoltageSourceLoop("in, 2")"#]);
+        // An ammeter is a voltage source of 0 V.
+        let loop_errors = errors(vec![
+            part("source.voltage_ac", "in", &["a", "0"]),
+            part("probe.ampermeter", "i", &["a", "0"]),
+        ]);
+        assert_eq!(loop_errors, vec![r#"VoltageSourceLoop("in, i")"#]);
+    }
+
+    #[test]
+    fn current_source_cutset() {
+        let errors = errors(vec![
+            part("source.current_dc", "in", &["0", "a"]),
+            part("lumped.capacitor", "1", &["a", "0"]),
+        ]);
+        assert_eq!(errors, vec![r#"CurrentSourceCutset("in, 1", "a")"#]);
     }
 }
@@ -94,6 +94,47 @@ impl Prefix {
             Self::Pico,
         ]
     }
+
+    /// The value by which a number must be multiplied to remove the prefix.
+    pub fn factor(&self) -> f64 {
+        match self {
+            Self::Giga => 1e9,
+            Self::Mega => 1e6,
+            Self::Kilo => 1e3,
+            Self::None => 1.0,
+            Self::Deci => 1e-1,
+            Self::Centi => 1e-2,
+            Self::Milli => 1e-3,
+            Self::Micro => 1e-6,
+            Self::Nano => 1e-9,
+            Self::Pico => 1e-12,
+        }
+    }
+
+    /// Find the engineering prefix that best fit a value (the deci and centi prefix are skipped).
+    /// Return the value scaled to the prefix and the prefix.
+    pub fn engineering(value: f64) -> (f64, Self) {
+        let magnitude = value.abs();
+        if magnitude == 0.0 || !magnitude.is_finite() {
+            return (value, Self::None);
+        }
+        for prefix in Self::as_array().iter() {
+            if *prefix == Self::Deci || *prefix == Self::Centi {
+                continue;
+            }
+            if magnitude >= prefix.factor() {
+                return (value / prefix.factor(), *prefix);
+            }
+        }
+        (value / Self::Pico.factor(), Self::Pico)
+    }
+}
+
+/// Format a value with its unit using the best engineering prefix, for example `0.0012` volts
+/// gives `1.20 mV`.
+pub fn format(value: f64, unit: &Unit) -> String {
+    let (scaled, prefix) = Prefix::engineering(value);
+    format!("{:.2} {}{}", scaled, prefix, unit)
 }
 
 impl From<usize> for Prefix {
@@ -1,5 +1,21 @@
 use crate::dom;
-use crate::unit;
+use crate::dom::form::{select, text_input};
+use crate::schema::properties;
+use crate::sim::{jobs, montecarlo, settings, temperature};
+use crate::{plot, unit};
+
+/// The selects used to choose a swept source with the name of the inputs of the sweep. The unit
+/// of those inputs depends on the type of source that is selected.
+const SWEPT_SOURCES: [(&str, [&str; 3]); 2] = [
+    (
+        "sim__dc-source",
+        ["sim__dc-start", "sim__dc-stop", "sim__dc-step"],
+    ),
+    (
+        "sim__dc-source2",
+        ["sim__dc-start2", "sim__dc-stop2", "sim__dc-step2"],
+    ),
+];
 
 pub fn generate_toolbar() {
     let tools = dom::create_element(
@@ -82,10 +98,45 @@ pub fn generate_simulation() {
                 vec![
                     ("tran".to_string(), "Transitoire".to_string()),
                     ("freq".to_string(), "Fréquence".to_string()),
-                    //("op".to_string(), "Point d'opération".to_string()),
+                    ("op".to_string(), "Point d'opération".to_string()),
+                    ("dc".to_string(), "Balayage DC".to_string()),
+                    ("noise".to_string(), "Bruit".to_string()),
+                    ("tf".to_string(), "Fonction de transfert".to_string()),
+                    ("pz".to_string(), "Pôles et zéros".to_string()),
+                ],
+                0,
+            ),
+            &dom::form::label::new("Simulateur", dom::attributes! {}),
+            &dom::form::select::create(
+                "sim__engine",
+                vec![
+                    ("ngspice".to_string(), "ngspice".to_string()),
+                    (
+                        "native".to_string(),
+                        "Intégré (R, L, C, sources, diodes et BJT)".to_string(),
+                    ),
+                    (
+                        "http".to_string(),
+                        "Serveur ngspice local (HTTP)".to_string(),
+                    ),
+                    (
+                        "websocket".to_string(),
+                        "Serveur ngspice local (WebSocket)".to_string(),
+                    ),
                 ],
                 0,
             ),
+            &dom::form::label::new("Adresse du serveur local", dom::attributes! {}),
+            &dom::form::text_input::new(dom::attributes! {
+                "name" => "sim__server",
+                "placeholder" => "localhost:8080/ngspice"
+            }),
+            &dom::form::label::new("Délai maximal d'une simulation (s)", dom::attributes! {}),
+            &dom::form::text_input::new(dom::attributes! {
+                "name" => "sim__timeout",
+                "value" => "60",
+                "placeholder" => "0 (aucun délai)"
+            }),
         ],
     );
 
@@ -117,8 +168,22 @@ pub fn generate_simulation() {
                     dom::attributes! { "name" => "tran__stop-unit", "value" => "s" },
                 ),
             ]),
+            &dom::form::label::new("Intégration", dom::attributes! {}),
+            &select::create(
+                "tran__method",
+                vec![
+                    ("trap".to_string(), "Trapèzes".to_string()),
+                    ("euler".to_string(), "Euler implicite".to_string()),
+                ],
+                0,
+            ),
+            &dom::form::label::new("Fourier analysis (probe)", dom::attributes! {}),
+            &select::create("sim__four-probe", vec![], 0),
+            &dom::form::label::new("Fundamental frequency", dom::attributes! {}),
+            &frequency_input("sim__four-fundamental", "1", 2),
         ],
     );
+    let _ = dom::select("[name=\"sim__four-probe\"]").set_attribute("data-sources", "");
     // FREQ SIMULATION
     dom::append_children(
         &dom::select("#menu__simulations-type-freq"),
@@ -167,18 +232,435 @@ pub fn generate_simulation() {
                     dom::attributes! { "name" => "sim__freq-fstop-unit", "value" => "Hz" },
                 ),
             ]),
-            &dom::form::label::create("Data Type"),
-            &dom::form::select::create(
-                "sim__freq-data-type",
+        ],
+    );
+}
+
+/// Create the inputs to sweep a source. The name of the inputs are suffixed with `suffix`.
+fn sweep_inputs(suffix: &str) -> Vec<web_sys::Element> {
+    let mut elements = Vec::new();
+    for (label, name, value) in [
+        ("Start", "start", "0"),
+        ("Stop", "stop", "10"),
+        ("Step", "step", "1"),
+    ] {
+        let name = format!("sim__dc-{}{}", name, suffix);
+        let unit_name = format!("{}-unit", name);
+        elements.push(dom::form::label::new(label, dom::attributes! {}));
+        elements.push(dom::form::group(vec![
+            text_input::new(dom::attributes! {
+                "name" => &name[..],
+                "data-property-type" => "unit",
+                "value" => value
+            }),
+            select::create_unit(&format!("{}-unit-prefix", name), &unit::Unit::Volt, 3),
+            text_input::hidden(dom::attributes! { "name" => &unit_name[..], "value" => "V" }),
+        ]));
+    }
+    elements
+}
+
+pub fn generate_dc_simulation() {
+    let mut elements = vec![
+        dom::form::label::new("Source", dom::attributes! {}),
+        select::create("sim__dc-source", vec![], 0),
+    ];
+    elements.append(&mut sweep_inputs(""));
+    elements.push(dom::form::label::new(
+        "Second source (optional)",
+        dom::attributes! {},
+    ));
+    elements.push(select::create("sim__dc-source2", vec![], 0));
+    elements.append(&mut sweep_inputs("2"));
+    dom::append_children(
+        &dom::select("#menu__simulations-type-dc"),
+        elements.iter().collect::<Vec<&web_sys::Element>>(),
+    );
+    SWEPT_SOURCES.iter().for_each(|(source, _)| {
+        let _ = dom::select(&format!("[name=\"{}\"]", source)).set_attribute("data-sources", "");
+    });
+}
+
+/// Update the sources that can be swept by a DC analysis with the sources of the circuit. The
+/// second sweep is optional so it can be empty. The unit of the inputs of a sweep follows the type
+/// of the selected source.
+pub fn update_sources(sources: Vec<String>) {
+    SWEPT_SOURCES
+        .iter()
+        .enumerate()
+        .for_each(|(idx, (source, inputs))| {
+            let mut options = sources
+                .iter()
+                .map(|source| (source.clone(), source.clone()))
+                .collect::<Vec<(String, String)>>();
+            if idx > 0 {
+                options.insert(0, (String::new(), "None".to_string()));
+            }
+            select::update_options(source, options);
+            let device = select::value::<String>(dom::select(&format!("[name=\"{}\"]", source)))
+                .unwrap_or_default();
+            let unit = if device.starts_with('I') {
+                unit::Unit::Ampere
+            } else {
+                unit::Unit::Volt
+            };
+            inputs.iter().for_each(|input| {
+                select::update_options(
+                    &format!("{}-unit-prefix", input),
+                    select::unit_options(&unit),
+                );
+                let _ = dom::select(&format!("[name=\"{}-unit\"]", input))
+                    .set_attribute("value", &unit.to_string());
+            });
+        });
+}
+
+/// Create a unit input for a frequency.
+fn frequency_input(name: &str, value: &str, prefix: usize) -> web_sys::Element {
+    let unit_name = format!("{}-unit", name);
+    dom::form::group(vec![
+        text_input::new(dom::attributes! {
+            "name" => name,
+            "data-property-type" => "unit",
+            "value" => value
+        }),
+        select::create_unit(&format!("{}-unit-prefix", name), &unit::Unit::Hertz, prefix),
+        text_input::hidden(dom::attributes! { "name" => &unit_name[..], "value" => "Hz" }),
+    ])
+}
+
+pub fn generate_noise_simulation() {
+    dom::append_children(
+        &dom::select("#menu__simulations-type-noise"),
+        vec![
+            &dom::form::label::new("Output (voltmeter)", dom::attributes! {}),
+            &select::create("sim__noise-output", vec![], 0),
+            &dom::form::label::new("Input source", dom::attributes! {}),
+            &select::create("sim__noise-source", vec![], 0),
+            &dom::form::label::new("Variation type", dom::attributes! {}),
+            &select::create(
+                "sim__noise-variation-type",
                 vec![
-                    ("db".to_string(), "Decibel (dB)".to_string()),
-                    ("p".to_string(), "Phase".to_string()),
-                    ("r".to_string(), "Real part".to_string()),
-                    ("i".to_string(), "Imaginary part".to_string()),
-                    ("m".to_string(), "Magnitude".to_string()),
+                    ("dec".to_string(), "Decade".to_string()),
+                    ("oct".to_string(), "Octave".to_string()),
+                    ("lin".to_string(), "Linear".to_string()),
                 ],
                 0,
             ),
+            &dom::form::label::new("Number of point (between variation)", dom::attributes! {}),
+            &text_input::new(dom::attributes! { "name" => "sim__noise-np", "value" => "10" }),
+            &dom::form::label::new("Starting Frequency", dom::attributes! {}),
+            &frequency_input("sim__noise-fstart", "1", 3),
+            &dom::form::label::new("Final Frequency", dom::attributes! {}),
+            &frequency_input("sim__noise-fstop", "20", 2),
+        ],
+    );
+    for name in ["sim__noise-output", "sim__noise-source"] {
+        let _ = dom::select(&format!("[name=\"{}\"]", name)).set_attribute("data-sources", "");
+    }
+}
+
+pub fn generate_tf_simulation() {
+    dom::append_children(
+        &dom::select("#menu__simulations-type-tf"),
+        vec![
+            &dom::form::label::new("Output (probe)", dom::attributes! {}),
+            &select::create("sim__tf-output", vec![], 0),
+            &dom::form::label::new("Input source", dom::attributes! {}),
+            &select::create("sim__tf-source", vec![], 0),
+        ],
+    );
+    for name in ["sim__tf-output", "sim__tf-source"] {
+        let _ = dom::select(&format!("[name=\"{}\"]", name)).set_attribute("data-sources", "");
+    }
+}
+
+pub fn generate_pz_simulation() {
+    dom::append_children(
+        &dom::select("#menu__simulations-type-pz"),
+        vec![
+            &dom::form::label::new("Output (voltmeter)", dom::attributes! {}),
+            &select::create("sim__pz-output", vec![], 0),
+            &dom::form::label::new("Input source", dom::attributes! {}),
+            &select::create("sim__pz-source", vec![], 0),
+        ],
+    );
+    for name in ["sim__pz-output", "sim__pz-source"] {
+        let _ = dom::select(&format!("[name=\"{}\"]", name)).set_attribute("data-sources", "");
+    }
+}
+
+/// The temperature of the simulation, it is shared by every type of simulation. When the sweep is
+/// not empty, the simulation is run once per temperature.
+pub fn generate_temperature() {
+    let nominal = temperature::NOMINAL.to_string();
+    dom::append_children(
+        &dom::select("#menu__simulations-temperature"),
+        vec![
+            &dom::form::label::new("Temperature (°C)", dom::attributes! {}),
+            &dom::form::text_input::new(
+                dom::attributes! { "name" => "sim__temp", "value" => &nominal[..] },
+            ),
+            &dom::form::label::new("Temperature sweep (°C)", dom::attributes! {}),
+            &dom::form::text_input::new(dom::attributes! {
+                "name" => "sim__temp-sweep",
+                "value" => "",
+                "placeholder" => "-40,27,85",
+            }),
+        ],
+    );
+}
+
+/// The settings of the Monte Carlo analysis, they are shared by every type of simulation. The
+/// analysis is disabled when the number of runs is 0.
+pub fn generate_montecarlo() {
+    dom::append_children(
+        &dom::select("#menu__simulations-montecarlo"),
+        vec![
+            &dom::form::label::new("Monte Carlo (runs)", dom::attributes! {}),
+            &dom::form::text_input::new(
+                dom::attributes! { "name" => "sim__mc-runs", "value" => "0" },
+            ),
+            &dom::form::label::new("Monte Carlo (probe)", dom::attributes! {}),
+            &select::create("sim__mc-probe", vec![], 0),
+            &dom::form::label::new("Measurement", dom::attributes! {}),
+            &select::create(
+                "sim__mc-measure",
+                montecarlo::Measure::ALL
+                    .iter()
+                    .map(|measure| (measure.key().to_string(), measure.name().to_string()))
+                    .collect(),
+                0,
+            ),
+        ],
+    );
+    let _ = dom::select("[name=\"sim__mc-probe\"]").set_attribute("data-sources", "");
+}
+
+/// The inputs of the simulation menu that are bound to a setting. The inputs of the unit and of the
+/// prefix of a value are part of the value.
+fn settings_inputs() -> Vec<(String, web_sys::Element)> {
+    dom::select_all("#menu__simulations [name]")
+        .into_iter()
+        .filter_map(|input| {
+            let name = input.get_attribute("name")?;
+            if name.ends_with("-unit") || name.ends_with("-unit-prefix") {
+                None
+            } else {
+                Some((name, input))
+            }
+        })
+        .collect()
+}
+
+/// Read the simulation settings from the simulation menu.
+pub fn read_settings() -> settings::Values {
+    let mut settings = settings::Values::default();
+    settings_inputs().into_iter().for_each(|(name, input)| {
+        let value = if input.has_attribute("data-property-type") {
+            properties::Value::from(input)
+        } else if input.tag_name() == "SELECT" {
+            properties::Value::String(select::value::<String>(input).unwrap_or_default())
+        } else {
+            properties::Value::String(text_input::value::<String>(input).unwrap_or_default())
+        };
+        settings.set(&name, value);
+    });
+    settings
+}
+
+/// Show the simulation settings in the simulation menu. The settings that are not in the menu
+/// are ignored and the inputs without a setting are left as is.
+pub fn write_settings(settings: &settings::Values) {
+    let inputs = settings_inputs();
+    settings.iter().for_each(|(name, value)| {
+        let input = match inputs.iter().find(|(input_name, _)| input_name == name) {
+            Some((_, input)) => input,
+            None => return,
+        };
+        if let properties::Value::Unit(_, _, prefix, _)
+        | properties::Value::Sweep(_, Some((_, prefix))) = value
+        {
+            set_input_value(
+                &dom::select(&format!("[name=\"{}-unit-prefix\"]", name)),
+                &prefix.index().to_string(),
+            );
+        }
+        set_input_value(input, &value.value_to_string());
+    });
+}
+
+/// Change the value of an input or of a select.
+fn set_input_value(input: &web_sys::Element, value: &str) {
+    if let Ok(select) = dom::convert::<web_sys::HtmlSelectElement>(input.clone()) {
+        select.set_value(value);
+    } else if let Ok(input) = dom::convert::<web_sys::HtmlInputElement>(input.clone()) {
+        input.set_value(value);
+    }
+}
+
+/// What a select of the simulation settings lists.
+#[derive(Clone, Copy)]
+enum Listing {
+    Sources,
+    Voltmeters,
+    Probes,
+}
+
+/// The selects of the simulation settings that list parts of the circuit, what they list and if
+/// they can be left empty.
+const PART_LISTS: [(&str, Listing, bool); 8] = [
+    ("sim__four-probe", Listing::Probes, true),
+    ("sim__mc-probe", Listing::Probes, false),
+    ("sim__noise-output", Listing::Voltmeters, false),
+    ("sim__noise-source", Listing::Sources, false),
+    ("sim__tf-output", Listing::Probes, false),
+    ("sim__tf-source", Listing::Sources, false),
+    ("sim__pz-output", Listing::Voltmeters, false),
+    ("sim__pz-source", Listing::Sources, false),
+];
+
+/// Update the selects of the simulation settings with the sources, the voltmeters and the probes
+/// (voltmeters included) of the circuit.
+pub fn update_part_lists(sources: &[String], voltmeters: &[String], probes: &[String]) {
+    PART_LISTS.iter().for_each(|(name, listing, is_optional)| {
+        let names = match listing {
+            Listing::Sources => sources,
+            Listing::Voltmeters => voltmeters,
+            Listing::Probes => probes,
+        };
+        let mut options = names
+            .iter()
+            .map(|name| (name.clone(), name.clone()))
+            .collect::<Vec<(String, String)>>();
+        if *is_optional {
+            options.insert(0, (String::new(), "None".to_string()));
+        }
+        select::update_options(name, options);
+    });
+}
+
+/// Show tables of results next to the plot (ie: the harmonics of a fourier analysis). The tables
+/// are hidden when there are none.
+pub fn update_tables(tables: &[plot::Table]) {
+    let container = dom::select("#sim__tables");
+    container.set_inner_html("");
+    let class = if tables.is_empty() {
+        "panel__results-table panel__scrollable hide"
+    } else {
+        "panel__results-table panel__scrollable"
+    };
+    let _ = container.set_attribute("class", class);
+    let cell = |tag: &str, text: &str| {
+        dom::create_element(tag, dom::attributes! { "inner_html" => text }, vec![])
+    };
+    tables.iter().for_each(|table| {
+        let mut rows = vec![dom::create_element(
+            "tr",
+            dom::attributes! {},
+            table.headers.iter().map(|header| cell("th", header)).collect(),
+        )];
+        table.rows.iter().for_each(|row| {
+            rows.push(dom::create_element(
+                "tr",
+                dom::attributes! {},
+                row.iter().map(|value| cell("td", value)).collect(),
+            ));
+        });
+        dom::append_children(
+            &container,
+            vec![
+                &dom::create_element(
+                    "p",
+                    dom::attributes! { "class" => "form__label-bold", "inner_html" => &table.title[..] },
+                    vec![],
+                ),
+                &dom::create_element("table", dom::attributes! {}, rows),
+            ],
+        );
+    });
+}
+
+/// Show the runs of the simulations with their status, their elapsed time and their progress. A
+/// button cancels the simulation while it runs.
+pub fn update_jobs(jobs: &jobs::Jobs, now: f64) {
+    let container = dom::select("#sim__jobs");
+    container.set_inner_html("");
+    let cell =
+        |text: &str| dom::create_element("td", dom::attributes! { "inner_html" => text }, vec![]);
+    let rows = jobs
+        .iter()
+        .map(|job| {
+            let elapsed = job
+                .elapsed(now)
+                .map_or(String::new(), |elapsed| format!("{:.1} s", elapsed));
+            let progress = match (job.progress, &job.status) {
+                (Some(progress), _) => cell(&format!("{:.0} %", progress * 100.0)),
+                // ngspice reached with `ngspice_call` or with HTTP only responds once it is done.
+                (None, jobs::Status::Running) => {
+                    let progress = cell("?");
+                    let _ = progress.set_attribute(
+                        "title",
+                        "L'avancement est rapporté seulement par le simulateur intégré \
+                         (analyse transitoire) et le serveur ngspice local (WebSocket).",
+                    );
+                    progress
+                }
+                (None, _) => cell(""),
+            };
+            let status = dom::create_element(
+                "td",
+                dom::attributes! { "inner_html" => job.status.name() },
+                vec![],
+            );
+            if let jobs::Status::Failed(error) = &job.status {
+                let _ = status.set_attribute("title", error);
+            }
+            dom::create_element(
+                "tr",
+                dom::attributes! {},
+                vec![cell(&job.label), status, cell(&elapsed), progress],
+            )
+        })
+        .collect::<Vec<_>>();
+    if rows.is_empty() {
+        return;
+    }
+    dom::append_children(
+        &container,
+        vec![
+            &dom::create_element(
+                "p",
+                dom::attributes! { "class" => "form__label-bold", "inner_html" => "Simulations" },
+                vec![],
+            ),
+            &dom::create_element("table", dom::attributes! {}, rows),
         ],
     );
+    if jobs.is_active() {
+        dom::append_children(
+            &container,
+            vec![&dom::create_element(
+                "button",
+                dom::attributes! {
+                    "id" => "sim__cancel",
+                    "class" => "form__button",
+                    "inner_html" => "Annuler",
+                },
+                vec![],
+            )],
+        );
+    }
+}
+
+/// Draw the histogram of a Monte Carlo analysis above the tables of results.
+pub fn update_histogram(histogram: &plot::histogram::Histogram) {
+    let container = dom::select("#sim__tables");
+    let canvas = dom::canvas::create(dom::attributes! {
+        "width" => "260",
+        "height" => "160",
+    });
+    histogram.draw(&dom::canvas::as_canvas(canvas.clone()));
+    let _ = container.set_attribute("class", "panel__results-table panel__scrollable");
+    let _ = container.prepend_with_node_1(&canvas);
 }
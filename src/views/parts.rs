@@ -1,52 +1,76 @@
+use crate::schema::parts;
 use crate::{dom, intrinsics::*, PARTS};
 
+/// The category of the blocks of the project (see `parts::Subcircuit`).
+const SUBCIRCUITS: &str = "subcircuit";
+
 /// Generate the list of components at the left of the editor.
 pub fn load() {
+    PARTS.parts.iter().for_each(|(category, lib)| {
+        new_category(&category[..], &lib.title);
+        lib.parts.iter().for_each(|(_, part)| {
+            let container = dom::select(&format!("[data-category=\"{}\"]", category));
+            new_button(&container, part, "data-part");
+        })
+    });
+    new_category(SUBCIRCUITS, "Sous-circuits");
+}
+
+/// Replace the buttons of the blocks of the project. Their instances are added with the
+/// `data-subcircuit` attribute (see `events::add_events_schema`).
+pub fn update_subcircuits(subcircuits: &[parts::Subcircuit]) {
+    let container = dom::select(&format!("[data-category=\"{}\"]", SUBCIRCUITS));
+    container.set_inner_html("");
+    subcircuits
+        .iter()
+        .for_each(|subcircuit| new_button(&container, &subcircuit.instance(""), "data-subcircuit"));
+}
+
+/// A button that shows a part, the identifier of the part is kept in the `attribute`.
+fn new_button(container: &web_sys::Element, part: &parts::Part, attribute: &str) {
     const BUTTON_WIDTH: f64 = 65.0;
     const BUTTON_HEIGHT: f64 = 45.0;
     const WIDTH_STR: &'static str = "65";
     const HEIGHT_STR: &'static str = "45";
     const BUTTON_RATIO: f64 = 0.75;
 
-    PARTS.parts.iter().for_each(|(category, lib)| {
-        new_category(&category[..], &lib.title);
-        lib.parts.iter().for_each(|(_, part)| {
-            let container = dom::select(&format!("[data-category=\"{}\"]", category));
-            let part_id = part.to_string();
-            let part_name = match &part.name {
-                Some(name) => name,
-                _ => &part_id,
-            };
-            let (canvas, mut ctx) = dom::canvas::new(dom::attributes! {
-                "width" => WIDTH_STR,
-                "height" => HEIGHT_STR,
-            });
-            let size = Size::new(
-                part.layout.size.w * BUTTON_RATIO,
-                part.layout.size.h * BUTTON_RATIO,
-            );
-            ctx.translate(Point::new(
-                (BUTTON_WIDTH - size.w) / 2.0,
-                (BUTTON_HEIGHT - size.h) / 2.0,
-            ));
-            ctx.scale(BUTTON_RATIO);
-            part.draw(&ctx);
-            dom::append_children(
-                &container,
-                vec![&dom::create_element(
-                    "div",
-                    dom::attributes! {
-                        "class" => "dialog-component-button",
-                        "data-part" => &part_id,
-                    },
-                    vec![
-                        canvas,
-                        dom::form::label::new(part_name, dom::attributes! {}),
-                    ],
-                )],
-            );
-        })
+    let part_id = match parts::Subcircuit::of(part) {
+        Some(name) => name.to_string(),
+        None => part.to_string(),
+    };
+    let part_name = match &part.name {
+        Some(name) => name,
+        _ => &part_id,
+    };
+    let (canvas, mut ctx) = dom::canvas::new(dom::attributes! {
+        "width" => WIDTH_STR,
+        "height" => HEIGHT_STR,
     });
+    // The symbols of the large blocks are shrunk to fit in the button.
+    let ratio = BUTTON_RATIO
+        .min(BUTTON_WIDTH / part.layout.size.w)
+        .min(BUTTON_HEIGHT / part.layout.size.h);
+    let size = Size::new(part.layout.size.w * ratio, part.layout.size.h * ratio);
+    ctx.translate(Point::new(
+        (BUTTON_WIDTH - size.w) / 2.0,
+        (BUTTON_HEIGHT - size.h) / 2.0,
+    ));
+    ctx.scale(ratio);
+    part.draw(&ctx);
+    dom::append_children(
+        container,
+        vec![&dom::create_element(
+            "div",
+            dom::attributes! {
+                "class" => "dialog-component-button",
+                attribute => &part_id,
+            },
+            vec![
+                canvas,
+                dom::form::label::new(part_name, dom::attributes! {}),
+            ],
+        )],
+    );
 }
 
 fn new_category(name: &str, title: &str) {
@@ -4,18 +4,33 @@ const main = async () => {
     await init();
     set_up();
 
+    // The requests being simulated by their id, they can be aborted.
+    const requests = new Map();
+    const respond = (detail) => {
+        requests.delete(detail.id);
+        window.dispatchEvent(new CustomEvent('ngspice_response', { detail }));
+    };
+
     window.addEventListener('ngspice_call', (event) => {
+        const { id, netlist } = event.detail;
+        const controller = new AbortController();
+        requests.set(id, controller);
         fetch('http://134.122.41.6:80/ngspice', {
             method: 'POST',
             mode: 'cors',
-            body: event.detail,
+            body: netlist,
+            signal: controller.signal,
         })
             .then((response) => response.text())
-            .then((value) => {
-                window.dispatchEvent(new CustomEvent('ngspice_response', {
-                    detail: value,
-                }))
-            });
+            .then((output) => respond({ id, output }))
+            .catch((error) => respond({ id, error: String(error) }));
+    });
+
+    window.addEventListener('ngspice_cancel', (event) => {
+        const controller = requests.get(event.detail.id);
+        if (controller) {
+            controller.abort();
+        }
     });
 };
 main();